@@ -3,11 +3,11 @@
 alias psql='time docker exec -it pg psql -U montecarlo'
 
 echo "### Importing /sample/product_props.tsv [$(wc -l sample/product_props.tsv|cut -d' ' -f1) lines]"
-psql -c "COPY product_props FROM '/sample/product_props.tsv';"
+psql -c "COPY product_props (id, simulation_forecast_days, scenario_random_range_factor, maximum_historic_days, maximum_quantity, minimum_quantity, new_batch_default_expiration_days, active, created_at, updated_at) FROM '/sample/product_props.tsv';"
 echo ""
 
 echo "### Importing /sample/product_batch.tsv [$(wc -l sample/product_batch.tsv|cut -d' ' -f1) lines]"
-psql -c "COPY product_batch FROM '/sample/product_batch.tsv' WITH NULL as 'null';"
+psql -c "COPY product_batch (id, product_id, entry_date, deadline_date, finished_date, quantity, created_at, updated_at) FROM '/sample/product_batch.tsv' WITH NULL as 'null';"
 echo ""
 
 echo "### Importing /sample/product_mov_hist.tsv to product_mov_hist [$(wc -l sample/product_mov_hist.tsv|cut -d' ' -f1) lines]"
@@ -19,13 +19,13 @@ psql -c "COPY product_mov_hist_no_part FROM '/sample/product_mov_hist.tsv';"
 echo ""
 
 echo "### Importing /sample/product_simulation_summary.tsv to product_simulation_summary [$(wc -l sample/product_simulation_summary.tsv|cut -d' ' -f1) lines]"
-psql -c "COPY product_simulation_summary FROM '/sample/product_simulation_summary.tsv';"
+psql -c "COPY product_simulation_summary (id, product_id, probability_losses_by_missing, probability_losses_by_nospace, probability_losses_by_expirat, start_date, end_date, first_date_with_losses, created_at) FROM '/sample/product_simulation_summary.tsv';"
 echo ""
 
 echo "### Importing /sample/product_simulation_summary_by_day.tsv to product_simulation_summary_by_day [$(wc -l sample/product_simulation_summary_by_day.tsv|cut -d' ' -f1) lines]"
-psql -c "COPY product_simulation_summary_by_day FROM '/sample/product_simulation_summary_by_day.tsv';"
+psql -c "COPY product_simulation_summary_by_day (product_simulation_summary_id, date, probability_losses_by_missing, probability_losses_by_nospace, probability_losses_by_expirat, created_at) FROM '/sample/product_simulation_summary_by_day.tsv';"
 echo ""
 
 echo "### Importing /sample/general_conf.tsv to general_conf [$(wc -l sample/general_conf.tsv|cut -d' ' -f1) lines]"
-psql -c "COPY general_conf FROM '/sample/general_conf.tsv';"
+psql -c "COPY general_conf (id, default_simulation_forecast_days, default_scenario_random_range_factor, default_maximum_historic_days, created_at) FROM '/sample/general_conf.tsv';"
 echo ""
\ No newline at end of file
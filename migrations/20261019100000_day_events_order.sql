@@ -0,0 +1,9 @@
+-- Order of the movements applied on each simulated day and how the expiration date is compared.
+-- day_events_order is a comma separated list with each of: withdraw, entry, expiration
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_day_events_order TEXT NOT NULL DEFAULT 'withdraw,entry,expiration',
+    ADD COLUMN IF NOT EXISTS default_expiration_inclusive BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS day_events_order TEXT,
+    ADD COLUMN IF NOT EXISTS expiration_inclusive BOOLEAN;
@@ -0,0 +1,20 @@
+-- Timestamped movements, used to build the intra-day profile of entries and withdrawals.
+CREATE TABLE IF NOT EXISTS product_mov_event (
+    id BIGSERIAL,
+    product_id UUID REFERENCES product_props (id) NOT NULL,
+    entry_qty INTEGER NOT NULL DEFAULT 0,
+    withdrawal_qty INTEGER NOT NULL DEFAULT 0,
+    mov_timestamp TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id, product_id)
+);
+CREATE INDEX IF NOT EXISTS idx_product_mov_event_product_id ON product_mov_event (product_id);
+
+-- Length of each simulation step. Must divide a day (1, 2, 3, 4, 6, 8, 12 or 24 hours).
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_time_step_hours SMALLINT NOT NULL DEFAULT 24
+        CHECK(default_time_step_hours > 0 AND default_time_step_hours <= 24);
+
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS time_step_hours SMALLINT
+        CHECK(time_step_hours > 0 AND time_step_hours <= 24);
@@ -0,0 +1,21 @@
+-- Inbound batches held in quality control can't be withdrawn until qc_release_date.
+ALTER TABLE product_batch
+    ADD COLUMN IF NOT EXISTS qc_release_date TIMESTAMPTZ;
+
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS qc_hold_days SMALLINT CHECK(qc_hold_days >= 0) DEFAULT 0 NOT NULL,
+    ADD COLUMN IF NOT EXISTS qc_rejection_rate DECIMAL(4,3) CHECK(qc_rejection_rate >= 0 AND qc_rejection_rate <= 1) DEFAULT 0 NOT NULL;
+
+-- DECIMAL(3,3) can't hold a probability of 1: the loss probabilities take the precision
+-- of the rejection probability.
+ALTER TABLE product_simulation_summary
+    ALTER COLUMN probability_losses_by_missing TYPE DECIMAL(4,3),
+    ALTER COLUMN probability_losses_by_nospace TYPE DECIMAL(4,3),
+    ALTER COLUMN probability_losses_by_expirat TYPE DECIMAL(4,3),
+    ADD COLUMN IF NOT EXISTS probability_losses_by_rejection DECIMAL(4,3) NOT NULL DEFAULT 0;
+
+ALTER TABLE product_simulation_summary_by_day
+    ALTER COLUMN probability_losses_by_missing TYPE DECIMAL(4,3),
+    ALTER COLUMN probability_losses_by_nospace TYPE DECIMAL(4,3),
+    ALTER COLUMN probability_losses_by_expirat TYPE DECIMAL(4,3),
+    ADD COLUMN IF NOT EXISTS probability_losses_by_rejection DECIMAL(4,3) NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- Demand not met by the stock is either lost ('lost_sales') or kept in a backlog ('backorder').
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS shortage_mode TEXT CHECK(shortage_mode IN ('lost_sales', 'backorder')) DEFAULT 'lost_sales' NOT NULL;
+
+ALTER TABLE product_simulation_summary_by_day
+    ADD COLUMN IF NOT EXISTS average_backlog_quantity NUMERIC NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS average_backlog_age_days NUMERIC NOT NULL DEFAULT 0;
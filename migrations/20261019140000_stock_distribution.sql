@@ -0,0 +1,7 @@
+-- Percentiles of the stock quantity across the simulation runs (fan chart).
+ALTER TABLE product_simulation_summary_by_day
+    ADD COLUMN IF NOT EXISTS stock_quantity_p5 NUMERIC NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS stock_quantity_p25 NUMERIC NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS stock_quantity_p50 NUMERIC NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS stock_quantity_p75 NUMERIC NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS stock_quantity_p95 NUMERIC NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- Distribution across the simulation runs of each inventory KPI.
+CREATE TABLE IF NOT EXISTS product_simulation_kpi (
+    product_simulation_summary_id INTEGER NOT NULL,
+    kpi TEXT NOT NULL,
+    runs INTEGER NOT NULL CHECK(runs > 0),
+    mean NUMERIC NOT NULL,
+    p5 NUMERIC NOT NULL,
+    p25 NUMERIC NOT NULL,
+    p50 NUMERIC NOT NULL,
+    p75 NUMERIC NOT NULL,
+    p95 NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_simulation_summary_id, kpi)
+);
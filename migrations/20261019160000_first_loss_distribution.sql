@@ -0,0 +1,18 @@
+-- Distribution across the runs of the first date with each loss type ('any' for any loss type).
+CREATE TABLE IF NOT EXISTS product_simulation_first_loss (
+    product_simulation_summary_id INTEGER NOT NULL,
+    loss_type TEXT NOT NULL CHECK(loss_type IN ('missing', 'nospace', 'expirat', 'rejection', 'any')),
+    runs_with_loss INTEGER NOT NULL CHECK(runs_with_loss > 0),
+    earliest_date DATE NOT NULL,
+    median_date DATE NOT NULL,
+    p90_date DATE NOT NULL,
+    earliest_days INTEGER NOT NULL,
+    median_days INTEGER NOT NULL,
+    p90_days INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_simulation_summary_id, loss_type)
+);
+
+-- Share of the runs that reach the end date without any loss.
+ALTER TABLE product_simulation_summary
+    ADD COLUMN IF NOT EXISTS probability_no_losses DECIMAL(4,3) NOT NULL DEFAULT 0;
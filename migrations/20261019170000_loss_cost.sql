@@ -0,0 +1,13 @@
+-- Money lost per unit of each loss type.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS unit_cost NUMERIC NOT NULL DEFAULT 0 CHECK(unit_cost >= 0),
+    ADD COLUMN IF NOT EXISTS lost_margin_per_unit NUMERIC NOT NULL DEFAULT 0 CHECK(lost_margin_per_unit >= 0),
+    ADD COLUMN IF NOT EXISTS disposal_cost_per_unit NUMERIC NOT NULL DEFAULT 0 CHECK(disposal_cost_per_unit >= 0),
+    ADD COLUMN IF NOT EXISTS overcapacity_cost_per_unit NUMERIC NOT NULL DEFAULT 0 CHECK(overcapacity_cost_per_unit >= 0);
+
+ALTER TABLE product_simulation_summary
+    ADD COLUMN IF NOT EXISTS expected_loss_cost NUMERIC NOT NULL DEFAULT 0;
+
+ALTER TABLE product_simulation_summary_by_day
+    ADD COLUMN IF NOT EXISTS expected_loss_cost NUMERIC NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS loss_cost_p95 NUMERIC NOT NULL DEFAULT 0;
@@ -0,0 +1,28 @@
+-- Replenishment policy: when the stock position drops to reorder_point an order
+-- up to maximum_quantity is placed, arriving after replenishment_lead_time_days.
+-- Without a reorder_point the historic entries are simulated instead.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS reorder_point INTEGER CHECK(reorder_point >= 0),
+    ADD COLUMN IF NOT EXISTS replenishment_lead_time_days SMALLINT NOT NULL DEFAULT 0 CHECK(replenishment_lead_time_days >= 0),
+    ADD COLUMN IF NOT EXISTS target_service_level DECIMAL(4,3) CHECK(target_service_level BETWEEN 0 AND 1);
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_target_service_level DECIMAL(4,3) NOT NULL DEFAULT 0.95 CHECK(default_target_service_level BETWEEN 0 AND 1),
+    ADD COLUMN IF NOT EXISTS default_replenishment_candidates INTEGER NOT NULL DEFAULT 11 CHECK(default_replenishment_candidates > 1);
+
+-- Suggested minimum_quantity (safety stock) and reorder_point, for review before applying them to product_props.
+CREATE TABLE IF NOT EXISTS product_replenishment_recommendation (
+    id SERIAL PRIMARY KEY,
+    product_id UUID REFERENCES product_props (id),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    target_service_level DECIMAL(4,3) NOT NULL,
+    lead_time_days SMALLINT NOT NULL,
+    minimum_quantity INTEGER NOT NULL,
+    reorder_point INTEGER NOT NULL,
+    expected_service_level DECIMAL(4,3) NOT NULL,
+    expected_expired_quantity NUMERIC NOT NULL,
+    is_target_reached BOOLEAN NOT NULL,
+    candidates INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
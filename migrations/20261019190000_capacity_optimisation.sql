@@ -0,0 +1,35 @@
+-- Target for the probability of "no space" losses when searching the stock maximum quantity.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS target_nospace_probability DECIMAL(4,3) CHECK(target_nospace_probability BETWEEN 0 AND 1);
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_target_nospace_probability DECIMAL(4,3) NOT NULL DEFAULT 0.05 CHECK(default_target_nospace_probability BETWEEN 0 AND 1),
+    ADD COLUMN IF NOT EXISTS default_capacity_candidates INTEGER NOT NULL DEFAULT 11 CHECK(default_capacity_candidates > 1);
+
+-- Smallest simulated stock maximum quantity keeping the "no space" probability under the target,
+-- NULL when no candidate reaches it.
+CREATE TABLE IF NOT EXISTS product_capacity_recommendation (
+    id SERIAL PRIMARY KEY,
+    product_id UUID REFERENCES product_props (id),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    target_nospace_probability DECIMAL(4,3) NOT NULL,
+    current_maximum_quantity INTEGER NOT NULL,
+    recommended_maximum_quantity INTEGER,
+    candidates INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Loss probabilities and cost of each simulated stock maximum quantity.
+CREATE TABLE IF NOT EXISTS product_capacity_curve (
+    product_capacity_recommendation_id INTEGER NOT NULL,
+    stock_maximum_quantity INTEGER NOT NULL,
+    probability_losses_by_missing DECIMAL(4,3) NOT NULL,
+    probability_losses_by_nospace DECIMAL(4,3) NOT NULL,
+    probability_losses_by_expirat DECIMAL(4,3) NOT NULL,
+    probability_losses_by_rejection DECIMAL(4,3) NOT NULL,
+    probability_no_losses DECIMAL(4,3) NOT NULL,
+    expected_loss_cost NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_capacity_recommendation_id, stock_maximum_quantity)
+);
@@ -0,0 +1,22 @@
+-- Shelf life sensitivity: the simulation re-run for a range of new_batch_default_expiration_days values.
+CREATE TABLE IF NOT EXISTS product_shelf_life_analysis (
+    id SERIAL PRIMARY KEY,
+    product_id UUID REFERENCES product_props (id),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    current_expiration_days SMALLINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS product_shelf_life_sensitivity (
+    product_shelf_life_analysis_id INTEGER NOT NULL,
+    new_batch_default_expiration_days SMALLINT NOT NULL,
+    probability_losses_by_missing DECIMAL(4,3) NOT NULL,
+    probability_losses_by_nospace DECIMAL(4,3) NOT NULL,
+    probability_losses_by_expirat DECIMAL(4,3) NOT NULL,
+    probability_losses_by_rejection DECIMAL(4,3) NOT NULL,
+    probability_no_losses DECIMAL(4,3) NOT NULL,
+    expected_loss_cost NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_shelf_life_analysis_id, new_batch_default_expiration_days)
+);
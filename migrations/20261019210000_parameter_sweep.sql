@@ -0,0 +1,48 @@
+-- Grid or random sample over the simulation parameters, each point run through the Monte Carlo simulation.
+CREATE TABLE IF NOT EXISTS product_parameter_sweep (
+    id SERIAL PRIMARY KEY,
+    product_id UUID REFERENCES product_props (id),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    design TEXT NOT NULL CHECK(design IN ('grid', 'random')),
+    axes TEXT NOT NULL,
+    runs INTEGER NOT NULL CHECK(runs > 0),
+    points INTEGER NOT NULL CHECK(points > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Parameter values simulated at each point, swept or not, and their results.
+CREATE TABLE IF NOT EXISTS product_parameter_sweep_point (
+    product_parameter_sweep_id INTEGER NOT NULL,
+    point_index INTEGER NOT NULL,
+    random_range_factor NUMERIC NOT NULL,
+    entry_trend_factor NUMERIC NOT NULL,
+    withdrawal_trend_factor NUMERIC NOT NULL,
+    stock_maximum_quantity INTEGER NOT NULL,
+    new_batch_default_expiration_days SMALLINT NOT NULL,
+    qc_rejection_rate NUMERIC NOT NULL,
+    reorder_point INTEGER,
+    lead_time_days SMALLINT,
+    probability_losses_by_missing DECIMAL(4,3) NOT NULL,
+    probability_losses_by_nospace DECIMAL(4,3) NOT NULL,
+    probability_losses_by_expirat DECIMAL(4,3) NOT NULL,
+    probability_losses_by_rejection DECIMAL(4,3) NOT NULL,
+    probability_no_losses DECIMAL(4,3) NOT NULL,
+    expected_loss_cost NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_parameter_sweep_id, point_index)
+);
+
+-- Tornado chart data: mean metric with the parameter in its lower and upper quartiles.
+CREATE TABLE IF NOT EXISTS product_parameter_sweep_sensitivity (
+    product_parameter_sweep_id INTEGER NOT NULL,
+    parameter TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    low_value NUMERIC NOT NULL,
+    high_value NUMERIC NOT NULL,
+    metric_at_low NUMERIC NOT NULL,
+    metric_at_high NUMERIC NOT NULL,
+    swing NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_parameter_sweep_id, parameter, metric)
+);
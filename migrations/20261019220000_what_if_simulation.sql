@@ -0,0 +1,3 @@
+-- Summaries of what-if runs, simulated with in-memory overrides, are tagged by a label.
+ALTER TABLE product_simulation_summary
+    ADD COLUMN IF NOT EXISTS what_if_label TEXT;
@@ -0,0 +1,24 @@
+-- Promotions and known events lifting the demand of a product, or of every product in a category.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS category TEXT,
+    ADD COLUMN IF NOT EXISTS exclude_promotions_from_history BOOLEAN;
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_exclude_promotions_from_history BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The demand of each day in the range is multiplied by the multiplier, then the additive quantity is added.
+CREATE TABLE IF NOT EXISTS product_promotion (
+    id SERIAL PRIMARY KEY,
+    product_id UUID REFERENCES product_props (id),
+    category TEXT,
+    name TEXT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    withdrawal_multiplier NUMERIC CHECK(withdrawal_multiplier >= 0),
+    withdrawal_additive_qty NUMERIC CHECK(withdrawal_additive_qty >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK(start_date <= end_date),
+    CHECK(product_id IS NOT NULL OR category IS NOT NULL),
+    CHECK(withdrawal_multiplier IS NOT NULL OR withdrawal_additive_qty IS NOT NULL)
+);
+CREATE INDEX IF NOT EXISTS idx_product_promotion_dates ON product_promotion (start_date, end_date);
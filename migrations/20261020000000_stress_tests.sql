@@ -0,0 +1,36 @@
+-- Named stress tests run, deterministically, alongside every simulation of the active products.
+-- The factor multiplies the entries (supplier_outage), the withdrawals (demand_shock)
+-- or the stock maximum quantity (capacity_cut) from the offset, counted from the first
+-- simulated day, for the duration in days; without a duration until the end of the simulation.
+CREATE TABLE IF NOT EXISTS stress_test (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL CHECK(kind IN ('supplier_outage', 'demand_shock', 'capacity_cut')),
+    start_offset_days SMALLINT NOT NULL DEFAULT 0 CHECK(start_offset_days >= 0),
+    duration_days SMALLINT CHECK(duration_days > 0),
+    factor NUMERIC NOT NULL CHECK(factor >= 0),
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO stress_test (name, kind, start_offset_days, duration_days, factor) VALUES
+    ('supplier outage 7 days', 'supplier_outage', 0, 7, 0),
+    ('demand +50% for 2 weeks', 'demand_shock', 0, 14, 1.5),
+    ('capacity halved', 'capacity_cut', 0, NULL, 0.5)
+ON CONFLICT (name) DO NOTHING;
+
+-- Deterministic outcome of each stress test, stored next to the probabilistic summary.
+CREATE TABLE IF NOT EXISTS product_simulation_stress_result (
+    product_simulation_summary_id INTEGER NOT NULL,
+    stress_test_name TEXT NOT NULL,
+    shortage_qty NUMERIC NOT NULL,
+    nospace_qty NUMERIC NOT NULL,
+    expired_qty NUMERIC NOT NULL,
+    rejected_qty NUMERIC NOT NULL,
+    loss_cost NUMERIC NOT NULL,
+    minimum_stock_qty NUMERIC NOT NULL,
+    final_stock_qty NUMERIC NOT NULL,
+    first_date_with_losses DATE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_simulation_summary_id, stress_test_name)
+);
@@ -0,0 +1,6 @@
+-- Trend factors fitted on the yearly volumes of the simulated season, instead of no trend.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS estimate_trend BOOLEAN;
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_estimate_trend BOOLEAN NOT NULL DEFAULT FALSE;
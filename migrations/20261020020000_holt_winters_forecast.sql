@@ -0,0 +1,7 @@
+-- Source of the expected movements of each simulated day: the historic 'average' of the same
+-- week of year and day of week, or a 'holt_winters' forecast fitted on the daily history.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS forecast_model TEXT CHECK(forecast_model IN ('average', 'holt_winters'));
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_forecast_model TEXT NOT NULL DEFAULT 'average' CHECK(default_forecast_model IN ('average', 'holt_winters'));
@@ -0,0 +1,14 @@
+-- Intermittent demand models of the slow movers: 'croston', its bias corrected variant 'sba',
+-- or 'auto' choosing 'sba' when the share of days without withdrawals reaches the ratio.
+ALTER TABLE product_props
+    DROP CONSTRAINT IF EXISTS product_props_forecast_model_check;
+ALTER TABLE product_props
+    ADD CONSTRAINT product_props_forecast_model_check CHECK(forecast_model IN ('average', 'holt_winters', 'croston', 'sba', 'auto'));
+
+ALTER TABLE general_conf
+    DROP CONSTRAINT IF EXISTS general_conf_default_forecast_model_check;
+ALTER TABLE general_conf
+    ADD CONSTRAINT general_conf_default_forecast_model_check CHECK(default_forecast_model IN ('average', 'holt_winters', 'croston', 'sba', 'auto'));
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_intermittent_zero_demand_ratio DECIMAL(4,3) NOT NULL DEFAULT 0.5 CHECK(default_intermittent_zero_demand_ratio BETWEEN 0 AND 1);
@@ -0,0 +1,27 @@
+-- Backtest of the forecast models of each product over rolling origins: each origin forecasts
+-- the horizon after it from the history before it. One row per candidate model and run,
+-- the model with the best score by the selection metric being selected. The simulation uses
+-- the latest selected model, with its smoothing factors, when the product has no forecast_model.
+CREATE TABLE IF NOT EXISTS product_forecast_backtest (
+    id SERIAL PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES product_props (id),
+    reference_date DATE NOT NULL,
+    model TEXT NOT NULL CHECK(model IN ('average', 'holt_winters', 'croston', 'sba')),
+    origins SMALLINT NOT NULL CHECK(origins > 0),
+    horizon_days SMALLINT NOT NULL CHECK(horizon_days > 0),
+    mae NUMERIC NOT NULL,
+    mase NUMERIC,
+    pinball_loss NUMERIC NOT NULL,
+    is_selected BOOLEAN NOT NULL,
+    alpha NUMERIC,
+    beta NUMERIC,
+    gamma_weekly NUMERIC,
+    gamma_yearly NUMERIC,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_forecast_backtest_origins SMALLINT NOT NULL DEFAULT 4 CHECK(default_forecast_backtest_origins > 0),
+    ADD COLUMN IF NOT EXISTS default_forecast_backtest_horizon_days SMALLINT NOT NULL DEFAULT 28 CHECK(default_forecast_backtest_horizon_days > 0),
+    ADD COLUMN IF NOT EXISTS default_forecast_pinball_quantile DECIMAL(3,2) NOT NULL DEFAULT 0.9 CHECK(default_forecast_pinball_quantile > 0 AND default_forecast_pinball_quantile < 1),
+    ADD COLUMN IF NOT EXISTS default_forecast_selection_metric TEXT NOT NULL DEFAULT 'mase' CHECK(default_forecast_selection_metric IN ('mae', 'mase', 'pinball_loss'));
@@ -0,0 +1,36 @@
+-- Calibration of the daily loss probabilities, the simulation being run at past reference
+-- dates and its predictions compared with the losses of replaying the observed movements
+-- from the stock at those dates. One row per loss kind of each product, and of all the
+-- products together without product_id; the hit rate being the share of the days with an
+-- observed loss predicted with a probability of at least 0.5.
+CREATE TABLE IF NOT EXISTS product_calibration_backtest (
+    id SERIAL PRIMARY KEY,
+    product_id UUID REFERENCES product_props (id),
+    first_reference_date DATE NOT NULL,
+    last_reference_date DATE NOT NULL,
+    loss_kind TEXT NOT NULL CHECK(loss_kind IN ('shortage', 'nospace', 'expiration')),
+    forecast_days INTEGER NOT NULL,
+    loss_days INTEGER NOT NULL,
+    brier_score NUMERIC NOT NULL,
+    hit_rate NUMERIC,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Reliability diagram of each backtest: the observed frequency of the loss for each range
+-- of predicted probability.
+CREATE TABLE IF NOT EXISTS product_calibration_reliability (
+    product_calibration_backtest_id INTEGER NOT NULL REFERENCES product_calibration_backtest (id),
+    bin_index SMALLINT NOT NULL,
+    lower_probability DECIMAL(4,3) NOT NULL,
+    upper_probability DECIMAL(4,3) NOT NULL,
+    forecast_days INTEGER NOT NULL,
+    mean_probability NUMERIC,
+    observed_frequency NUMERIC,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_calibration_backtest_id, bin_index)
+);
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_calibration_reference_dates SMALLINT NOT NULL DEFAULT 6 CHECK(default_calibration_reference_dates > 0),
+    ADD COLUMN IF NOT EXISTS default_calibration_step_days SMALLINT NOT NULL DEFAULT 30 CHECK(default_calibration_step_days > 0),
+    ADD COLUMN IF NOT EXISTS default_calibration_bins SMALLINT NOT NULL DEFAULT 10 CHECK(default_calibration_bins > 0);
@@ -0,0 +1,16 @@
+-- Random range factor of each product suggested from the spread of its daily movements around
+-- their average of the same week of year and day of week, for review before changing the
+-- product props. The Brier scores, of the daily loss probabilities over the past reference
+-- dates of the calibration backtest, are those of the current and of the suggested factor.
+CREATE TABLE IF NOT EXISTS product_random_range_suggestion (
+    id SERIAL PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES product_props (id),
+    reference_date DATE NOT NULL,
+    current_factor DECIMAL(3,2) NOT NULL,
+    suggested_factor DECIMAL(3,2) NOT NULL,
+    relative_std NUMERIC NOT NULL,
+    observed_days INTEGER NOT NULL,
+    brier_score_before NUMERIC,
+    brier_score_after NUMERIC,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
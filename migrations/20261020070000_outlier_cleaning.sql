@@ -0,0 +1,11 @@
+-- Cleaning of the one-off movements (bulk orders, data-entry errors) of the history before
+-- it is averaged: the movements above an upper bound, from the median and the MAD or from
+-- the quartiles of the positive movements, are clipped to the bound or excluded.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS clean_outliers BOOLEAN;
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_clean_outliers BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS default_outlier_method TEXT NOT NULL DEFAULT 'mad' CHECK(default_outlier_method IN ('mad', 'iqr')),
+    ADD COLUMN IF NOT EXISTS default_outlier_threshold DECIMAL(4,2) NOT NULL DEFAULT 3.5 CHECK(default_outlier_threshold > 0),
+    ADD COLUMN IF NOT EXISTS default_outlier_action TEXT NOT NULL DEFAULT 'clip' CHECK(default_outlier_action IN ('clip', 'exclude'));
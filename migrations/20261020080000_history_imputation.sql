@@ -0,0 +1,16 @@
+-- Imputation of the movements of the days of the year without history, instead of no movement,
+-- with a data-quality report of each simulation counting the simulated days that used it.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS history_imputation TEXT CHECK(history_imputation IN ('zero', 'neighbouring_weeks', 'same_weekday', 'overall_mean'));
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_history_imputation TEXT NOT NULL DEFAULT 'neighbouring_weeks' CHECK(default_history_imputation IN ('zero', 'neighbouring_weeks', 'same_weekday', 'overall_mean'));
+
+CREATE TABLE IF NOT EXISTS product_simulation_data_quality (
+    product_simulation_summary_id INTEGER NOT NULL PRIMARY KEY,
+    history_imputation TEXT NOT NULL,
+    simulated_days INTEGER NOT NULL CHECK(simulated_days >= 0),
+    forecast_days INTEGER NOT NULL CHECK(forecast_days >= 0),
+    imputed_days INTEGER NOT NULL CHECK(imputed_days >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,17 @@
+-- Analog products of a new product, whose history scaled by the scaling factor is borrowed
+-- until the product has enough of its own: the weight of its own history grows with its days
+-- of history, up to the whole weight after analog_full_history_days.
+CREATE TABLE IF NOT EXISTS product_analog (
+    product_id UUID NOT NULL REFERENCES product_props (id),
+    analog_product_id UUID NOT NULL REFERENCES product_props (id),
+    scaling_factor NUMERIC NOT NULL DEFAULT 1 CHECK(scaling_factor > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_id, analog_product_id),
+    CHECK(product_id <> analog_product_id)
+);
+
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS analog_full_history_days SMALLINT CHECK(analog_full_history_days > 0);
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_analog_full_history_days SMALLINT NOT NULL DEFAULT 365 CHECK(default_analog_full_history_days > 0);
@@ -0,0 +1,7 @@
+-- Withdrawals switching between a quiet and a busy regime lasting for weeks, a Markov chain
+-- fitted on the weekly withdrawals of the history drawing the regime of each simulated day.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS demand_regimes BOOLEAN;
+
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_demand_regimes BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+-- Shelf life of the new batches in hours, for sub-day steps.
+-- Takes precedence over new_batch_default_expiration_days when set.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS new_batch_expiration_hours INTEGER
+        CHECK(new_batch_expiration_hours > 0);
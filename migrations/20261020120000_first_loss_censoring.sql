@@ -0,0 +1,8 @@
+-- The runs without the loss are censored at the end date: the median and p90
+-- are NULL when they fall beyond it.
+ALTER TABLE product_simulation_first_loss
+    ADD COLUMN IF NOT EXISTS runs INTEGER NOT NULL DEFAULT 0,
+    ALTER COLUMN median_date DROP NOT NULL,
+    ALTER COLUMN p90_date DROP NOT NULL,
+    ALTER COLUMN median_days DROP NOT NULL,
+    ALTER COLUMN p90_days DROP NOT NULL;
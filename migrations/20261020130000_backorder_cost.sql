@@ -0,0 +1,4 @@
+-- Delay cost of each backordered unit for each day it waits in the backlog.
+ALTER TABLE product_props
+    ADD COLUMN IF NOT EXISTS backorder_cost_per_unit_day NUMERIC NOT NULL DEFAULT 0
+        CHECK(backorder_cost_per_unit_day >= 0);
@@ -0,0 +1,9 @@
+-- While no candidate reaches the target, the capacity search range is doubled
+-- up to this factor times the current stock maximum quantity.
+ALTER TABLE general_conf
+    ADD COLUMN IF NOT EXISTS default_capacity_search_max_factor SMALLINT NOT NULL DEFAULT 8
+        CHECK(default_capacity_search_max_factor >= 2);
+
+-- Why there is no recommended capacity, NULL when there is one.
+ALTER TABLE product_capacity_recommendation
+    ADD COLUMN IF NOT EXISTS no_recommendation_reason TEXT;
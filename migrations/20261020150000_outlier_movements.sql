@@ -0,0 +1,13 @@
+-- Movements of the history found to be outliers by the latest cleaning of the product, with
+-- the quantity they are cleaned to. The averages of the history clip or leave them out.
+CREATE TABLE IF NOT EXISTS product_mov_outlier (
+    product_id UUID NOT NULL REFERENCES product_props (id),
+    mov_date DATE NOT NULL,
+    quantity TEXT NOT NULL CHECK(quantity IN ('entry', 'withdrawal')),
+    qty NUMERIC NOT NULL,
+    bound NUMERIC NOT NULL,
+    cleaned_qty NUMERIC NOT NULL,
+    is_excluded BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_id, mov_date, quantity)
+);
@@ -3,10 +3,36 @@ use std::time::{Duration, Instant};
 
 #[derive(Debug, FromRow, Clone)]
 pub struct GeneralConf {
-    pub id: i32,                                          // SERIAL PRIMARY KEY,
+    pub id: i32,                                            // SERIAL PRIMARY KEY,
     pub default_simulation_forecast_days: i16, // SMALLINT NOT NULL CHECK(default_simulation_forecast_days >= 0),
     pub default_scenario_random_range_factor: BigDecimal, // DECIMAL(3,2) NOT NULL,
     pub default_maximum_historic_days: i16, // SMALLINT NOT NULL CHECK(default_maximum_historic_days >= 0),
+    pub default_day_events_order: String,   // TEXT NOT NULL DEFAULT 'withdraw,entry,expiration',
+    pub default_expiration_inclusive: bool, // BOOLEAN NOT NULL DEFAULT FALSE,
+    pub default_time_step_hours: i16,       // SMALLINT NOT NULL DEFAULT 24,
+    pub default_target_service_level: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0.95,
+    pub default_replenishment_candidates: i32, // INTEGER NOT NULL DEFAULT 11,
+    pub default_target_nospace_probability: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0.05,
+    pub default_capacity_candidates: i32,   // INTEGER NOT NULL DEFAULT 11,
+    pub default_exclude_promotions_from_history: bool, // BOOLEAN NOT NULL DEFAULT FALSE,
+    pub default_estimate_trend: bool,       // BOOLEAN NOT NULL DEFAULT FALSE,
+    pub default_forecast_model: String,     // TEXT NOT NULL DEFAULT 'average',
+    pub default_intermittent_zero_demand_ratio: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0.5,
+    pub default_forecast_backtest_origins: i16, // SMALLINT NOT NULL DEFAULT 4,
+    pub default_forecast_backtest_horizon_days: i16, // SMALLINT NOT NULL DEFAULT 28,
+    pub default_forecast_pinball_quantile: BigDecimal, // DECIMAL(3,2) NOT NULL DEFAULT 0.9,
+    pub default_forecast_selection_metric: String, // TEXT NOT NULL DEFAULT 'mase',
+    pub default_calibration_reference_dates: i16, // SMALLINT NOT NULL DEFAULT 6,
+    pub default_calibration_step_days: i16, // SMALLINT NOT NULL DEFAULT 30,
+    pub default_calibration_bins: i16,      // SMALLINT NOT NULL DEFAULT 10,
+    pub default_clean_outliers: bool,       // BOOLEAN NOT NULL DEFAULT FALSE,
+    pub default_outlier_method: String,     // TEXT NOT NULL DEFAULT 'mad',
+    pub default_outlier_threshold: BigDecimal, // DECIMAL(4,2) NOT NULL DEFAULT 3.5,
+    pub default_outlier_action: String,     // TEXT NOT NULL DEFAULT 'clip',
+    pub default_history_imputation: String, // TEXT NOT NULL DEFAULT 'neighbouring_weeks',
+    pub default_analog_full_history_days: i16, // SMALLINT NOT NULL DEFAULT 365,
+    pub default_demand_regimes: bool,       // BOOLEAN NOT NULL DEFAULT FALSE,
+    pub default_capacity_search_max_factor: i16, // SMALLINT NOT NULL DEFAULT 8,
                                             //    pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 }
 
@@ -28,7 +54,33 @@ impl GeneralConfRepository {
                 id,
                 default_simulation_forecast_days,
                 default_scenario_random_range_factor,
-                default_maximum_historic_days
+                default_maximum_historic_days,
+                default_day_events_order,
+                default_expiration_inclusive,
+                default_time_step_hours,
+                default_target_service_level,
+                default_replenishment_candidates,
+                default_target_nospace_probability,
+                default_capacity_candidates,
+                default_exclude_promotions_from_history,
+                default_estimate_trend,
+                default_forecast_model,
+                default_intermittent_zero_demand_ratio,
+                default_forecast_backtest_origins,
+                default_forecast_backtest_horizon_days,
+                default_forecast_pinball_quantile,
+                default_forecast_selection_metric,
+                default_calibration_reference_dates,
+                default_calibration_step_days,
+                default_calibration_bins,
+                default_clean_outliers,
+                default_outlier_method,
+                default_outlier_threshold,
+                default_outlier_action,
+                default_history_imputation,
+                default_analog_full_history_days,
+                default_demand_regimes,
+                default_capacity_search_max_factor
             FROM general_conf
             ORDER BY id DESC
             LIMIT 1;
@@ -51,7 +103,33 @@ impl GeneralConfRepository {
                 id,
                 default_simulation_forecast_days,
                 default_scenario_random_range_factor,
-                default_maximum_historic_days
+                default_maximum_historic_days,
+                default_day_events_order,
+                default_expiration_inclusive,
+                default_time_step_hours,
+                default_target_service_level,
+                default_replenishment_candidates,
+                default_target_nospace_probability,
+                default_capacity_candidates,
+                default_exclude_promotions_from_history,
+                default_estimate_trend,
+                default_forecast_model,
+                default_intermittent_zero_demand_ratio,
+                default_forecast_backtest_origins,
+                default_forecast_backtest_horizon_days,
+                default_forecast_pinball_quantile,
+                default_forecast_selection_metric,
+                default_calibration_reference_dates,
+                default_calibration_step_days,
+                default_calibration_bins,
+                default_clean_outliers,
+                default_outlier_method,
+                default_outlier_threshold,
+                default_outlier_action,
+                default_history_imputation,
+                default_analog_full_history_days,
+                default_demand_regimes,
+                default_capacity_search_max_factor
             FROM general_conf
             ORDER BY id ASC;
         ",
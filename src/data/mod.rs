@@ -1,6 +1,27 @@
 pub(crate) mod general_conf;
+pub(crate) mod product_analog;
 pub(crate) mod product_batch;
+pub(crate) mod product_calibration_backtest;
+pub(crate) mod product_calibration_reliability;
+pub(crate) mod product_capacity_curve;
+pub(crate) mod product_capacity_recommendation;
+pub(crate) mod product_forecast_backtest;
+pub(crate) mod product_mov_event;
 pub(crate) mod product_mov_hist;
+pub(crate) mod product_mov_outlier;
+pub(crate) mod product_parameter_sweep;
+pub(crate) mod product_parameter_sweep_point;
+pub(crate) mod product_parameter_sweep_sensitivity;
+pub(crate) mod product_promotion;
 pub(crate) mod product_props;
+pub(crate) mod product_random_range_suggestion;
+pub(crate) mod product_replenishment_recommendation;
+pub(crate) mod product_shelf_life_analysis;
+pub(crate) mod product_shelf_life_sensitivity;
+pub(crate) mod product_simulation_data_quality;
+pub(crate) mod product_simulation_first_loss;
+pub(crate) mod product_simulation_kpi;
+pub(crate) mod product_simulation_stress_result;
 pub(crate) mod product_simulation_summary;
 pub(crate) mod product_simulation_summary_by_day;
+pub(crate) mod stress_test;
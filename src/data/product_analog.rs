@@ -0,0 +1,74 @@
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductAnalog {
+    //pub product_id: Uuid,      // UUID NOT NULL REFERENCES product_props (id),
+    pub analog_product_id: Uuid, // UUID NOT NULL REFERENCES product_props (id),
+    pub scaling_factor: BigDecimal, // NUMERIC NOT NULL DEFAULT 1,
+                                 //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductAnalogRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductAnalogRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductAnalogRepository {
+        ProductAnalogRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductAnalog>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductAnalog>(
+            "
+            SELECT
+                analog_product_id ,
+                scaling_factor
+            FROM product_analog
+            WHERE product_id = $1
+            ORDER BY analog_product_id;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, analogs) = result.unwrap();
+        assert_eq!(analogs.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, analogs);
+    }
+
+    async fn get_db_repo() -> ProductAnalogRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductAnalogRepository::new(pool)
+    }
+}
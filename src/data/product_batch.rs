@@ -14,6 +14,7 @@ pub struct ProductBatch {
     pub finished_date: Option<DateTime<Utc>>, // TIMESTAMPTZ,
     pub is_finished: bool, // BOOLEAN NOT NULL GENERATED ALWAYS AS (finished_date IS NOT NULL) STORED,
     pub quantity: BigDecimal, // NUMERIC NOT NULL CHECK (quantity >= 0) DEFAULT 0,
+    pub qc_release_date: Option<DateTime<Utc>>, // TIMESTAMPTZ,
                            // pub created_at    , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
                            // pub updated_at    , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 } //
@@ -41,7 +42,8 @@ impl ProductBatchRepository {
               deadline_date,
               finished_date,
               is_finished  ,
-              quantity
+              quantity     ,
+              qc_release_date
             FROM product_batch;
         ",
         );
@@ -66,7 +68,8 @@ impl ProductBatchRepository {
               deadline_date,
               finished_date,
               is_finished  ,
-              quantity
+              quantity     ,
+              qc_release_date
             FROM product_batch
             WHERE product_id = $1;
         ",
@@ -76,6 +79,39 @@ impl ProductBatchRepository {
 
         Ok((timer.elapsed(), query_res))
     }
+
+    /// Batches of the product in stock at `date`: entered before it and not yet finished.
+    pub async fn find_all_by_product_at(
+        &self,
+        product_id: Uuid,
+        date: DateTime<Utc>,
+    ) -> Result<(Duration, Vec<ProductBatch>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductBatch>(
+            "
+            SELECT
+              entry_date   ,
+              deadline_date,
+              NULL::TIMESTAMPTZ AS finished_date,
+              FALSE AS is_finished,
+              quantity     ,
+              qc_release_date
+            FROM product_batch
+            WHERE product_id = $1
+            AND   entry_date < $2
+            AND   (finished_date IS NULL OR finished_date >= $2);
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(date)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
 }
 
 #[cfg(test)]
@@ -109,6 +145,19 @@ mod tests {
         //eprintln!("Query took: {:?}, result: {:?}", elapsed, products);
     }
 
+    #[tokio::test]
+    async fn find_all_by_product_at_no_results() {
+        let product_id: Uuid = Uuid::parse_str("d0bd335e-fc46-408d-90fb-209ccc521fa1").unwrap();
+        let repo = get_db_repo().await;
+        let date = DateTime::parse_from_rfc3339("2000-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let result = repo.find_all_by_product_at(product_id, date).await;
+        let (elapsed, batches) = result.unwrap();
+        assert_eq!(batches.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, batches);
+    }
+
     async fn get_db_repo() -> ProductBatchRepository {
         let database_url = env::var("DATABASE_URL").unwrap();
         eprintln!("DATABASE_URL: {:?}", database_url);
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductCalibrationBacktest {
+    pub first_reference_date: NaiveDate, // DATE NOT NULL,
+    pub last_reference_date: NaiveDate,  // DATE NOT NULL,
+    pub loss_kind: String,               // TEXT NOT NULL,
+    pub forecast_days: i32,              // INTEGER NOT NULL,
+    pub loss_days: i32,                  // INTEGER NOT NULL,
+    pub brier_score: BigDecimal,         // NUMERIC NOT NULL,
+    pub hit_rate: Option<BigDecimal>,    // NUMERIC,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductCalibrationBacktest {
+    pub id: i32,                         // SERIAL PRIMARY KEY,
+    pub product_id: Option<Uuid>,        // UUID REFERENCES product_props (id),
+    pub first_reference_date: NaiveDate, // DATE NOT NULL,
+    pub last_reference_date: NaiveDate,  // DATE NOT NULL,
+    pub loss_kind: String,               // TEXT NOT NULL,
+    pub forecast_days: i32,              // INTEGER NOT NULL,
+    pub loss_days: i32,                  // INTEGER NOT NULL,
+    pub brier_score: BigDecimal,         // NUMERIC NOT NULL,
+    pub hit_rate: Option<BigDecimal>,    // NUMERIC,
+                                         //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductCalibrationBacktestRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductCalibrationBacktestRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductCalibrationBacktestRepository {
+        ProductCalibrationBacktestRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductCalibrationBacktest>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductCalibrationBacktest>(
+            "
+            SELECT
+                id                   ,
+                product_id           ,
+                first_reference_date ,
+                last_reference_date  ,
+                loss_kind            ,
+                forecast_days        ,
+                loss_days            ,
+                brier_score          ,
+                hit_rate
+            FROM product_calibration_backtest
+            WHERE product_id = $1;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new backtest, of all the products without `product_id`.
+    pub async fn insert(
+        &self,
+        product_id: Option<Uuid>,
+        backtest: &NewProductCalibrationBacktest,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_calibration_backtest (
+                product_id           ,
+                first_reference_date ,
+                last_reference_date  ,
+                loss_kind            ,
+                forecast_days        ,
+                loss_days            ,
+                brier_score          ,
+                hit_rate
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(backtest.first_reference_date)
+            .bind(backtest.last_reference_date)
+            .bind(&backtest.loss_kind)
+            .bind(backtest.forecast_days)
+            .bind(backtest.loss_days)
+            .bind(&backtest.brier_score)
+            .bind(&backtest.hit_rate)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, backtests) = result.unwrap();
+        assert_eq!(backtests.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, backtests);
+    }
+
+    async fn get_db_repo() -> ProductCalibrationBacktestRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductCalibrationBacktestRepository::new(pool)
+    }
+}
@@ -0,0 +1,129 @@
+use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductCalibrationReliability {
+    pub bin_index: i16,                         // SMALLINT NOT NULL,
+    pub lower_probability: BigDecimal,          // DECIMAL(4,3) NOT NULL,
+    pub upper_probability: BigDecimal,          // DECIMAL(4,3) NOT NULL,
+    pub forecast_days: i32,                     // INTEGER NOT NULL,
+    pub mean_probability: Option<BigDecimal>,   // NUMERIC,
+    pub observed_frequency: Option<BigDecimal>, // NUMERIC,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductCalibrationReliability {
+    pub product_calibration_backtest_id: i32, // INTEGER NOT NULL,
+    pub bin_index: i16,                       // SMALLINT NOT NULL,
+    pub lower_probability: BigDecimal,        // DECIMAL(4,3) NOT NULL,
+    pub upper_probability: BigDecimal,        // DECIMAL(4,3) NOT NULL,
+    pub forecast_days: i32,                   // INTEGER NOT NULL,
+    pub mean_probability: Option<BigDecimal>, // NUMERIC,
+    pub observed_frequency: Option<BigDecimal>, // NUMERIC,
+}
+
+pub struct ProductCalibrationReliabilityRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductCalibrationReliabilityRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductCalibrationReliabilityRepository {
+        ProductCalibrationReliabilityRepository { db }
+    }
+
+    pub async fn find_all_by_product_calibration_backtest(
+        &self,
+        product_calibration_backtest_id: i32,
+    ) -> Result<(Duration, Vec<ProductCalibrationReliability>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductCalibrationReliability>(
+            "
+            SELECT
+                product_calibration_backtest_id ,
+                bin_index                       ,
+                lower_probability               ,
+                upper_probability               ,
+                forecast_days                   ,
+                mean_probability                ,
+                observed_frequency
+            FROM product_calibration_reliability
+            WHERE product_calibration_backtest_id = $1
+            ORDER BY bin_index;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_calibration_backtest_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        product_calibration_backtest_id: i32,
+        reliability: &[NewProductCalibrationReliability],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        for bin in reliability {
+            sqlx::query(
+                "
+                INSERT INTO product_calibration_reliability (
+                    product_calibration_backtest_id ,
+                    bin_index                       ,
+                    lower_probability               ,
+                    upper_probability               ,
+                    forecast_days                   ,
+                    mean_probability                ,
+                    observed_frequency
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7);
+            ",
+            )
+            .bind(product_calibration_backtest_id)
+            .bind(bin.bin_index)
+            .bind(&bin.lower_probability)
+            .bind(&bin.upper_probability)
+            .bind(bin.forecast_days)
+            .bind(&bin.mean_probability)
+            .bind(&bin.observed_frequency)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_calibration_backtest_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_calibration_backtest(0).await;
+        let (elapsed, reliability) = result.unwrap();
+        assert_eq!(reliability.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, reliability);
+    }
+
+    async fn get_db_repo() -> ProductCalibrationReliabilityRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductCalibrationReliabilityRepository::new(pool)
+    }
+}
@@ -0,0 +1,134 @@
+use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductCapacityCurve {
+    pub stock_maximum_quantity: i32,                 // INTEGER NOT NULL,
+    pub probability_losses_by_missing: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_no_losses: BigDecimal,           // DECIMAL(4,3) NOT NULL,
+    pub expected_loss_cost: BigDecimal,              // NUMERIC NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductCapacityCurve {
+    pub product_capacity_recommendation_id: i32, // INTEGER NOT NULL,
+    pub stock_maximum_quantity: i32,             // INTEGER NOT NULL,
+    pub probability_losses_by_missing: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_no_losses: BigDecimal,       // DECIMAL(4,3) NOT NULL,
+    pub expected_loss_cost: BigDecimal,          // NUMERIC NOT NULL,
+}
+
+pub struct ProductCapacityCurveRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductCapacityCurveRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductCapacityCurveRepository {
+        ProductCapacityCurveRepository { db }
+    }
+
+    pub async fn find_all_by_product_capacity_recommendation(
+        &self,
+        product_capacity_recommendation_id: i32,
+    ) -> Result<(Duration, Vec<ProductCapacityCurve>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductCapacityCurve>(
+            "
+            SELECT
+                product_capacity_recommendation_id ,
+                stock_maximum_quantity             ,
+                probability_losses_by_missing      ,
+                probability_losses_by_nospace      ,
+                probability_losses_by_expirat      ,
+                probability_losses_by_rejection    ,
+                probability_no_losses              ,
+                expected_loss_cost
+            FROM product_capacity_curve
+            WHERE product_capacity_recommendation_id = $1
+            ORDER BY stock_maximum_quantity;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_capacity_recommendation_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        product_capacity_recommendation_id: i32,
+        curve: &[NewProductCapacityCurve],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        for point in curve {
+            sqlx::query(
+                "
+                INSERT INTO product_capacity_curve (
+                    product_capacity_recommendation_id ,
+                    stock_maximum_quantity             ,
+                    probability_losses_by_missing      ,
+                    probability_losses_by_nospace      ,
+                    probability_losses_by_expirat      ,
+                    probability_losses_by_rejection    ,
+                    probability_no_losses              ,
+                    expected_loss_cost
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+            ",
+            )
+            .bind(product_capacity_recommendation_id)
+            .bind(point.stock_maximum_quantity)
+            .bind(&point.probability_losses_by_missing)
+            .bind(&point.probability_losses_by_nospace)
+            .bind(&point.probability_losses_by_expirat)
+            .bind(&point.probability_losses_by_rejection)
+            .bind(&point.probability_no_losses)
+            .bind(&point.expected_loss_cost)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_capacity_recommendation_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_capacity_recommendation(-1).await;
+        let (elapsed, curve) = result.unwrap();
+        assert_eq!(curve.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, curve);
+    }
+
+    async fn get_db_repo() -> ProductCapacityCurveRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductCapacityCurveRepository::new(pool)
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductCapacityRecommendation {
+    pub product_id: Uuid,      // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate, // DATE NOT NULL,
+    pub end_date: NaiveDate,   // DATE NOT NULL,
+    pub target_nospace_probability: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub current_maximum_quantity: i32, // INTEGER NOT NULL,
+    pub recommended_maximum_quantity: Option<i32>, // INTEGER,
+    pub candidates: i32,       // INTEGER NOT NULL,
+    pub no_recommendation_reason: Option<String>, // TEXT,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductCapacityRecommendation {
+    pub id: i32,                                   // SERIAL,
+    pub product_id: Uuid,                          // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate,                     // DATE NOT NULL,
+    pub end_date: NaiveDate,                       // DATE NOT NULL,
+    pub target_nospace_probability: BigDecimal,    // DECIMAL(4,3) NOT NULL,
+    pub current_maximum_quantity: i32,             // INTEGER NOT NULL,
+    pub recommended_maximum_quantity: Option<i32>, // INTEGER,
+    pub candidates: i32,                           // INTEGER NOT NULL,
+    pub no_recommendation_reason: Option<String>,  // TEXT,
+                                                   //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductCapacityRecommendationRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductCapacityRecommendationRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductCapacityRecommendationRepository {
+        ProductCapacityRecommendationRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductCapacityRecommendation>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductCapacityRecommendation>(
+            "
+            SELECT
+                id                           ,
+                product_id                   ,
+                start_date                   ,
+                end_date                     ,
+                target_nospace_probability   ,
+                current_maximum_quantity     ,
+                recommended_maximum_quantity ,
+                candidates                   ,
+                no_recommendation_reason
+            FROM product_capacity_recommendation
+            WHERE product_id = $1;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new recommendation.
+    pub async fn insert(
+        &self,
+        recommendation: &NewProductCapacityRecommendation,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_capacity_recommendation (
+                product_id                   ,
+                start_date                   ,
+                end_date                     ,
+                target_nospace_probability   ,
+                current_maximum_quantity     ,
+                recommended_maximum_quantity ,
+                candidates                   ,
+                no_recommendation_reason
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(recommendation.product_id)
+            .bind(recommendation.start_date)
+            .bind(recommendation.end_date)
+            .bind(&recommendation.target_nospace_probability)
+            .bind(recommendation.current_maximum_quantity)
+            .bind(recommendation.recommended_maximum_quantity)
+            .bind(recommendation.candidates)
+            .bind(&recommendation.no_recommendation_reason)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, recommendations) = result.unwrap();
+        assert_eq!(recommendations.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, recommendations);
+    }
+
+    async fn get_db_repo() -> ProductCapacityRecommendationRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductCapacityRecommendationRepository::new(pool)
+    }
+}
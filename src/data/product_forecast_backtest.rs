@@ -0,0 +1,178 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductForecastBacktest {
+    pub model: String,                    // TEXT NOT NULL,
+    pub origins: i16,                     // SMALLINT NOT NULL,
+    pub horizon_days: i16,                // SMALLINT NOT NULL,
+    pub mae: BigDecimal,                  // NUMERIC NOT NULL,
+    pub mase: Option<BigDecimal>,         // NUMERIC,
+    pub pinball_loss: BigDecimal,         // NUMERIC NOT NULL,
+    pub is_selected: bool,                // BOOLEAN NOT NULL,
+    pub alpha: Option<BigDecimal>,        // NUMERIC,
+    pub beta: Option<BigDecimal>,         // NUMERIC,
+    pub gamma_weekly: Option<BigDecimal>, // NUMERIC,
+    pub gamma_yearly: Option<BigDecimal>, // NUMERIC,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductForecastBacktest {
+    pub id: i32,                          // SERIAL PRIMARY KEY,
+    pub product_id: Uuid,                 // UUID NOT NULL REFERENCES product_props (id),
+    pub reference_date: NaiveDate,        // DATE NOT NULL,
+    pub model: String,                    // TEXT NOT NULL,
+    pub origins: i16,                     // SMALLINT NOT NULL,
+    pub horizon_days: i16,                // SMALLINT NOT NULL,
+    pub mae: BigDecimal,                  // NUMERIC NOT NULL,
+    pub mase: Option<BigDecimal>,         // NUMERIC,
+    pub pinball_loss: BigDecimal,         // NUMERIC NOT NULL,
+    pub is_selected: bool,                // BOOLEAN NOT NULL,
+    pub alpha: Option<BigDecimal>,        // NUMERIC,
+    pub beta: Option<BigDecimal>,         // NUMERIC,
+    pub gamma_weekly: Option<BigDecimal>, // NUMERIC,
+    pub gamma_yearly: Option<BigDecimal>, // NUMERIC,
+                                          //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductForecastBacktestRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductForecastBacktestRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductForecastBacktestRepository {
+        ProductForecastBacktestRepository { db }
+    }
+
+    /// Model selected by the latest backtest of the product as of `as_of_date`, if any.
+    /// The backtests of a later reference date are left out, as they saw the later history.
+    pub async fn find_latest_selected_by_product(
+        &self,
+        product_id: Uuid,
+        as_of_date: NaiveDate,
+    ) -> Result<(Duration, Option<ProductForecastBacktest>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductForecastBacktest>(
+            "
+            SELECT
+                id             ,
+                product_id     ,
+                reference_date ,
+                model          ,
+                origins        ,
+                horizon_days   ,
+                mae            ,
+                mase           ,
+                pinball_loss   ,
+                is_selected    ,
+                alpha          ,
+                beta           ,
+                gamma_weekly   ,
+                gamma_yearly
+            FROM product_forecast_backtest
+            WHERE product_id = $1
+            AND   is_selected
+            AND   reference_date <= $2
+            ORDER BY reference_date DESC, created_at DESC, id DESC
+            LIMIT 1;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(as_of_date)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        product_id: Uuid,
+        reference_date: NaiveDate,
+        backtests: &[NewProductForecastBacktest],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        for backtest in backtests {
+            sqlx::query(
+                "
+                INSERT INTO product_forecast_backtest (
+                    product_id     ,
+                    reference_date ,
+                    model          ,
+                    origins        ,
+                    horizon_days   ,
+                    mae            ,
+                    mase           ,
+                    pinball_loss   ,
+                    is_selected    ,
+                    alpha          ,
+                    beta           ,
+                    gamma_weekly   ,
+                    gamma_yearly
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13);
+            ",
+            )
+            .bind(product_id)
+            .bind(reference_date)
+            .bind(&backtest.model)
+            .bind(backtest.origins)
+            .bind(backtest.horizon_days)
+            .bind(&backtest.mae)
+            .bind(&backtest.mase)
+            .bind(&backtest.pinball_loss)
+            .bind(backtest.is_selected)
+            .bind(&backtest.alpha)
+            .bind(&backtest.beta)
+            .bind(&backtest.gamma_weekly)
+            .bind(&backtest.gamma_yearly)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_latest_selected_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .find_latest_selected_by_product(
+                Uuid::from_u128(0),
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            )
+            .await;
+        let (elapsed, backtest) = result.unwrap();
+        assert!(backtest.is_none());
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, backtest);
+    }
+
+    async fn get_db_repo() -> ProductForecastBacktestRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductForecastBacktestRepository::new(pool)
+    }
+}
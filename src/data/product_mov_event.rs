@@ -0,0 +1,84 @@
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductMovHourlyProfile {
+    pub hour_of_day: i16,
+    pub entry_share: BigDecimal,
+    pub withdrawal_share: BigDecimal,
+}
+
+pub struct ProductMovEventRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductMovEventRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductMovEventRepository {
+        ProductMovEventRepository { db }
+    }
+
+    /// Share of the daily entries and withdrawals that happened in each hour of the day (UTC).
+    pub async fn aggregate_hourly_profile_by_product_id(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductMovHourlyProfile>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductMovHourlyProfile>(
+            "
+            SELECT
+                EXTRACT(HOUR FROM mov_timestamp AT TIME ZONE 'UTC')::SMALLINT AS hour_of_day,
+                COALESCE(
+                    SUM(entry_qty)::NUMERIC / NULLIF(SUM(SUM(entry_qty)) OVER (), 0), 0
+                ) AS entry_share,
+                COALESCE(
+                    SUM(withdrawal_qty)::NUMERIC / NULLIF(SUM(SUM(withdrawal_qty)) OVER (), 0), 0
+                ) AS withdrawal_share
+            FROM product_mov_event
+            WHERE product_id = $1
+            GROUP BY hour_of_day
+            ORDER BY hour_of_day;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn aggregate_hourly_profile_by_product_id_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .aggregate_hourly_profile_by_product_id(
+                Uuid::parse_str("d0bd335e-fc46-408d-90fb-000000000000").unwrap(),
+            )
+            .await;
+        let (elapsed, profile) = result.unwrap();
+        assert_eq!(profile.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, profile);
+    }
+
+    async fn get_db_repo() -> ProductMovEventRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductMovEventRepository::new(pool)
+    }
+}
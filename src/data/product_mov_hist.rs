@@ -1,9 +1,12 @@
+use chrono::NaiveDate;
 use sqlx::{
     types::{BigDecimal, Uuid},
     FromRow, Pool, Postgres,
 };
 use std::time::{Duration, Instant};
 
+use crate::data::product_mov_outlier::NewProductMovOutlier;
+
 #[derive(Debug, FromRow, Clone)]
 pub struct ProductMovHist {
     pub product_id: Uuid,
@@ -13,6 +16,22 @@ pub struct ProductMovHist {
     pub day_of_week: i16,
 }
 
+/// Movements of a single day.
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductMovDaily {
+    pub mov_date: NaiveDate,
+    pub entry_qty: BigDecimal,
+    pub withdrawal_qty: BigDecimal,
+}
+
+/// Volumes moved in an ISO year over a range of weeks.
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductMovYearlyVolume {
+    pub year: i16,
+    pub entry_qty: BigDecimal,
+    pub withdrawal_qty: BigDecimal,
+}
+
 pub struct ProductMovHistRepository {
     db: Pool<Postgres>,
 }
@@ -22,27 +41,64 @@ impl ProductMovHistRepository {
         ProductMovHistRepository { db: db }
     }
 
+    /// Averages of the movements before `before_date`, e.g. the reference date of a backtest.
+    /// With `exclude_promotions`, the days of past promotions of the product or of its
+    /// category are left out, so that the baseline is not lifted by them. The `outliers`
+    /// found in the daily history are scaled to their cleaned quantity or excluded, being
+    /// cleaned when all the movements of a day of the year would be excluded.
     pub async fn aggregate_by_product_id_and_week_of_year_and_day_of_week(
         &self,
         product_id: Uuid,
         initial_week: i16,
         final_week: i16,
+        exclude_promotions: bool,
+        before_date: NaiveDate,
+        outliers: &[NewProductMovOutlier],
     ) -> Result<(Duration, Vec<ProductMovHist>), Box<dyn std::error::Error>> {
         let timer = Instant::now();
 
         let query = sqlx::query_as::<_, ProductMovHist>(
             "
-            SELECT 
-                product_id,
-                AVG(entry_qty) AS entry_qty,
-                AVG(withdrawal_qty) AS withdrawal_qty,
+            WITH outlier AS (
+                SELECT *
+                FROM UNNEST($6::DATE[], $7::TEXT[], $8::NUMERIC[], $9::NUMERIC[], $10::BOOLEAN[])
+                    AS outlier (mov_date, quantity, qty, cleaned_qty, is_excluded)
+            )
+            SELECT
+                product_mov_hist.product_id,
+                COALESCE(AVG(CASE WHEN entry_outlier.is_excluded THEN NULL
+                                  ELSE entry_qty * COALESCE(entry_outlier.cleaned_qty
+                                                            / NULLIF(entry_outlier.qty, 0), 1) END),
+                         AVG(entry_qty * COALESCE(entry_outlier.cleaned_qty
+                                                  / NULLIF(entry_outlier.qty, 0), 1))) AS entry_qty,
+                COALESCE(AVG(CASE WHEN withdrawal_outlier.is_excluded THEN NULL
+                                  ELSE withdrawal_qty * COALESCE(withdrawal_outlier.cleaned_qty
+                                                                 / NULLIF(withdrawal_outlier.qty, 0), 1) END),
+                         AVG(withdrawal_qty * COALESCE(withdrawal_outlier.cleaned_qty
+                                                       / NULLIF(withdrawal_outlier.qty, 0), 1))) AS withdrawal_qty,
                 week_of_year,
                 day_of_week
             FROM product_mov_hist
-            WHERE product_id = $1
+            LEFT JOIN outlier AS entry_outlier
+                ON  entry_outlier.mov_date = product_mov_hist.mov_date
+                AND entry_outlier.quantity = 'entry'
+            LEFT JOIN outlier AS withdrawal_outlier
+                ON  withdrawal_outlier.mov_date = product_mov_hist.mov_date
+                AND withdrawal_outlier.quantity = 'withdrawal'
+            WHERE product_mov_hist.product_id = $1
             AND   week_of_year >= $2
             AND   week_of_year <= $3
-            GROUP BY product_id, week_of_year, day_of_week
+            AND   product_mov_hist.mov_date < $5
+            AND   NOT ($4 AND EXISTS (
+                SELECT 1
+                FROM product_promotion
+                WHERE (product_promotion.product_id = $1
+                       OR product_promotion.category = (
+                           SELECT category FROM product_props WHERE id = $1))
+                AND   product_mov_hist.mov_date BETWEEN product_promotion.start_date
+                                                    AND product_promotion.end_date
+            ))
+            GROUP BY product_mov_hist.product_id, week_of_year, day_of_week
             ORDER BY week_of_year, day_of_week;
         ",
         );
@@ -51,6 +107,226 @@ impl ProductMovHistRepository {
             .bind(product_id)
             .bind(initial_week)
             .bind(final_week)
+            .bind(exclude_promotions)
+            .bind(before_date)
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| outlier.mov_date)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| outlier.quantity.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| &outlier.qty)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| &outlier.cleaned_qty)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| outlier.is_excluded)
+                    .collect::<Vec<_>>(),
+            )
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Movements of each day from `start_date` until before `end_date`, in date order.
+    /// With `exclude_promotions`, the movements of the days of past promotions of the product
+    /// or of its category are replaced by the average of the other days with the same day
+    /// of week, so that the daily series keeps its days without being lifted by them.
+    pub async fn find_daily_by_product_id(
+        &self,
+        product_id: Uuid,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        exclude_promotions: bool,
+    ) -> Result<(Duration, Vec<ProductMovDaily>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductMovDaily>(
+            "
+            WITH daily AS (
+                SELECT
+                    mov_date,
+                    SUM(entry_qty) AS entry_qty,
+                    SUM(withdrawal_qty) AS withdrawal_qty,
+                    $4 AND EXISTS (
+                        SELECT 1
+                        FROM product_promotion
+                        WHERE (product_promotion.product_id = $1
+                               OR product_promotion.category = (
+                                   SELECT category FROM product_props WHERE id = $1))
+                        AND   product_mov_hist.mov_date BETWEEN product_promotion.start_date
+                                                            AND product_promotion.end_date
+                    ) AS is_promotion
+                FROM product_mov_hist
+                WHERE product_id = $1
+                AND   mov_date >= $2
+                AND   mov_date <  $3
+                GROUP BY mov_date
+            )
+            SELECT
+                mov_date,
+                (CASE WHEN is_promotion
+                      THEN COALESCE(AVG(entry_qty) FILTER (WHERE NOT is_promotion)
+                                        OVER (PARTITION BY EXTRACT(ISODOW FROM mov_date)), 0)
+                      ELSE entry_qty END)::NUMERIC AS entry_qty,
+                (CASE WHEN is_promotion
+                      THEN COALESCE(AVG(withdrawal_qty) FILTER (WHERE NOT is_promotion)
+                                        OVER (PARTITION BY EXTRACT(ISODOW FROM mov_date)), 0)
+                      ELSE withdrawal_qty END)::NUMERIC AS withdrawal_qty
+            FROM daily
+            ORDER BY mov_date;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(start_date)
+            .bind(end_date)
+            .bind(exclude_promotions)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Days with movements from `start_date` until before `end_date`.
+    pub async fn count_days_by_product_id(
+        &self,
+        product_id: Uuid,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<(Duration, i64), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i64>(
+            "
+            SELECT COUNT(DISTINCT mov_date)
+            FROM product_mov_hist
+            WHERE product_id = $1
+            AND   mov_date >= $2
+            AND   mov_date <  $3;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(start_date)
+            .bind(end_date)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Volumes of each year over the same weeks before `before_date`, e.g. to fit a trend
+    /// for a season. With `exclude_promotions`, the days of past promotions are left out.
+    /// The `outliers` found in the daily history are cleaned or excluded.
+    pub async fn aggregate_yearly_by_product_id_and_week_of_year(
+        &self,
+        product_id: Uuid,
+        initial_week: i16,
+        final_week: i16,
+        exclude_promotions: bool,
+        before_date: NaiveDate,
+        outliers: &[NewProductMovOutlier],
+    ) -> Result<(Duration, Vec<ProductMovYearlyVolume>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductMovYearlyVolume>(
+            "
+            WITH outlier AS (
+                SELECT *
+                FROM UNNEST($6::DATE[], $7::TEXT[], $8::NUMERIC[], $9::NUMERIC[], $10::BOOLEAN[])
+                    AS outlier (mov_date, quantity, qty, cleaned_qty, is_excluded)
+            )
+            SELECT
+                EXTRACT(ISOYEAR FROM product_mov_hist.mov_date)::SMALLINT AS year,
+                COALESCE(SUM(CASE WHEN entry_outlier.is_excluded THEN NULL
+                                  ELSE entry_qty * COALESCE(entry_outlier.cleaned_qty
+                                                            / NULLIF(entry_outlier.qty, 0), 1) END),
+                         0)::NUMERIC AS entry_qty,
+                COALESCE(SUM(CASE WHEN withdrawal_outlier.is_excluded THEN NULL
+                                  ELSE withdrawal_qty * COALESCE(withdrawal_outlier.cleaned_qty
+                                                                 / NULLIF(withdrawal_outlier.qty, 0), 1) END),
+                         0)::NUMERIC AS withdrawal_qty
+            FROM product_mov_hist
+            LEFT JOIN outlier AS entry_outlier
+                ON  entry_outlier.mov_date = product_mov_hist.mov_date
+                AND entry_outlier.quantity = 'entry'
+            LEFT JOIN outlier AS withdrawal_outlier
+                ON  withdrawal_outlier.mov_date = product_mov_hist.mov_date
+                AND withdrawal_outlier.quantity = 'withdrawal'
+            WHERE product_mov_hist.product_id = $1
+            AND   week_of_year >= $2
+            AND   week_of_year <= $3
+            AND   product_mov_hist.mov_date < $5
+            AND   NOT ($4 AND EXISTS (
+                SELECT 1
+                FROM product_promotion
+                WHERE (product_promotion.product_id = $1
+                       OR product_promotion.category = (
+                           SELECT category FROM product_props WHERE id = $1))
+                AND   product_mov_hist.mov_date BETWEEN product_promotion.start_date
+                                                    AND product_promotion.end_date
+            ))
+            GROUP BY year
+            ORDER BY year;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(initial_week)
+            .bind(final_week)
+            .bind(exclude_promotions)
+            .bind(before_date)
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| outlier.mov_date)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| outlier.quantity.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| &outlier.qty)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| &outlier.cleaned_qty)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                outliers
+                    .iter()
+                    .map(|outlier| outlier.is_excluded)
+                    .collect::<Vec<_>>(),
+            )
             .fetch_all(&self.db)
             .await?;
 
@@ -78,9 +354,39 @@ mod tests {
                 Uuid::parse_str("d0bd335e-fc46-408d-90fb-209ccc521fa1").unwrap(),
                 FIRST_WEEK,
                 LAST_WEEK,
+                false,
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+                &[],
+            )
+            .await;
+        let (elapsed, hist) = result.unwrap();
+        assert_eq!(hist.len(), DAYS_IN_THE_PERIOD);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, hist);
+    }
+
+    #[tokio::test]
+    async fn aggregate_by_product_id_and_week_of_year_and_day_of_week_with_outliers() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .aggregate_by_product_id_and_week_of_year_and_day_of_week(
+                Uuid::parse_str("d0bd335e-fc46-408d-90fb-209ccc521fa1").unwrap(),
+                FIRST_WEEK,
+                LAST_WEEK,
+                false,
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+                &[NewProductMovOutlier {
+                    product_id: Uuid::parse_str("d0bd335e-fc46-408d-90fb-209ccc521fa1").unwrap(),
+                    mov_date: NaiveDate::from_ymd_opt(2021, 1, 4).unwrap(),
+                    quantity: "withdrawal".to_owned(),
+                    qty: BigDecimal::from(100),
+                    bound: BigDecimal::from(50),
+                    cleaned_qty: BigDecimal::from(20),
+                    is_excluded: true,
+                }],
             )
             .await;
         let (elapsed, hist) = result.unwrap();
+        // The excluded outliers do not leave a day of the year without average.
         assert_eq!(hist.len(), DAYS_IN_THE_PERIOD);
         eprintln!("Query took: {:?}, result: {:?}", elapsed, hist);
     }
@@ -93,6 +399,9 @@ mod tests {
                 Uuid::parse_str("d0bd335e-fc46-408d-90fb-000000000000").unwrap(),
                 FIRST_WEEK,
                 LAST_WEEK,
+                false,
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+                &[],
             )
             .await;
         let (elapsed, hist) = result.unwrap();
@@ -100,6 +409,55 @@ mod tests {
         eprintln!("Query took: {:?}, result: {:?}", elapsed, hist);
     }
 
+    #[tokio::test]
+    async fn aggregate_yearly_by_product_id_and_week_of_year_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .aggregate_yearly_by_product_id_and_week_of_year(
+                Uuid::parse_str("d0bd335e-fc46-408d-90fb-000000000000").unwrap(),
+                FIRST_WEEK,
+                LAST_WEEK,
+                false,
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+                &[],
+            )
+            .await;
+        let (elapsed, volumes) = result.unwrap();
+        assert_eq!(volumes.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, volumes);
+    }
+
+    #[tokio::test]
+    async fn find_daily_by_product_id_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .find_daily_by_product_id(
+                Uuid::parse_str("d0bd335e-fc46-408d-90fb-000000000000").unwrap(),
+                NaiveDate::from_ymd_opt(2017, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+                true,
+            )
+            .await;
+        let (elapsed, days) = result.unwrap();
+        assert_eq!(days.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, days);
+    }
+
+    #[tokio::test]
+    async fn count_days_by_product_id_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .count_days_by_product_id(
+                Uuid::parse_str("d0bd335e-fc46-408d-90fb-000000000000").unwrap(),
+                NaiveDate::from_ymd_opt(2017, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            )
+            .await;
+        let (elapsed, days) = result.unwrap();
+        assert_eq!(days, 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, days);
+    }
+
     async fn get_db_repo() -> ProductMovHistRepository {
         let database_url = env::var("DATABASE_URL").unwrap();
         eprintln!("DATABASE_URL: {:?}", database_url);
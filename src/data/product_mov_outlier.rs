@@ -0,0 +1,81 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductMovOutlier {
+    pub product_id: Uuid,        // UUID NOT NULL REFERENCES product_props (id),
+    pub mov_date: NaiveDate,     // DATE NOT NULL,
+    pub quantity: String,        // TEXT NOT NULL,
+    pub qty: BigDecimal,         // NUMERIC NOT NULL,
+    pub bound: BigDecimal,       // NUMERIC NOT NULL,
+    pub cleaned_qty: BigDecimal, // NUMERIC NOT NULL,
+    pub is_excluded: bool,       // BOOLEAN NOT NULL,
+}
+
+pub struct ProductMovOutlierRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductMovOutlierRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductMovOutlierRepository {
+        ProductMovOutlierRepository { db }
+    }
+
+    /// Replaces the outliers of the product from `start_date` until before `end_date`,
+    /// the range of the history they were found in.
+    pub async fn replace_all_by_product_and_dates(
+        &self,
+        product_id: Uuid,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        outliers: &[NewProductMovOutlier],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query(
+            "
+            DELETE FROM product_mov_outlier
+            WHERE product_id = $1
+            AND   mov_date >= $2
+            AND   mov_date <  $3;
+        ",
+        )
+        .bind(product_id)
+        .bind(start_date)
+        .bind(end_date)
+        .execute(&mut *tx)
+        .await?;
+        for outlier in outliers {
+            sqlx::query(
+                "
+                INSERT INTO product_mov_outlier (
+                    product_id  ,
+                    mov_date    ,
+                    quantity    ,
+                    qty         ,
+                    bound       ,
+                    cleaned_qty ,
+                    is_excluded
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7);
+            ",
+            )
+            .bind(outlier.product_id)
+            .bind(outlier.mov_date)
+            .bind(&outlier.quantity)
+            .bind(&outlier.qty)
+            .bind(&outlier.bound)
+            .bind(&outlier.cleaned_qty)
+            .bind(outlier.is_excluded)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::NaiveDate;
+use sqlx::{types::Uuid, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductParameterSweep {
+    pub product_id: Uuid,      // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate, // DATE NOT NULL,
+    pub end_date: NaiveDate,   // DATE NOT NULL,
+    pub design: String,        // TEXT NOT NULL,
+    pub axes: String,          // TEXT NOT NULL,
+    pub runs: i32,             // INTEGER NOT NULL,
+    pub points: i32,           // INTEGER NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductParameterSweep {
+    pub id: i32,               // SERIAL,
+    pub product_id: Uuid,      // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate, // DATE NOT NULL,
+    pub end_date: NaiveDate,   // DATE NOT NULL,
+    pub design: String,        // TEXT NOT NULL,
+    pub axes: String,          // TEXT NOT NULL,
+    pub runs: i32,             // INTEGER NOT NULL,
+    pub points: i32,           // INTEGER NOT NULL,
+                               //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductParameterSweepRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductParameterSweepRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductParameterSweepRepository {
+        ProductParameterSweepRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductParameterSweep>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductParameterSweep>(
+            "
+            SELECT
+                id         ,
+                product_id ,
+                start_date ,
+                end_date   ,
+                design     ,
+                axes       ,
+                runs       ,
+                points
+            FROM product_parameter_sweep
+            WHERE product_id = $1;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new sweep.
+    pub async fn insert(
+        &self,
+        sweep: &NewProductParameterSweep,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_parameter_sweep (
+                product_id ,
+                start_date ,
+                end_date   ,
+                design     ,
+                axes       ,
+                runs       ,
+                points
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(sweep.product_id)
+            .bind(sweep.start_date)
+            .bind(sweep.end_date)
+            .bind(&sweep.design)
+            .bind(&sweep.axes)
+            .bind(sweep.runs)
+            .bind(sweep.points)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, sweeps) = result.unwrap();
+        assert_eq!(sweeps.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, sweeps);
+    }
+
+    async fn get_db_repo() -> ProductParameterSweepRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductParameterSweepRepository::new(pool)
+    }
+}
@@ -0,0 +1,175 @@
+use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+/// Parameter values simulated at a sweep point, swept or not, and their results.
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductParameterSweepPoint {
+    pub point_index: i32,                            // INTEGER NOT NULL,
+    pub random_range_factor: BigDecimal,             // NUMERIC NOT NULL,
+    pub entry_trend_factor: BigDecimal,              // NUMERIC NOT NULL,
+    pub withdrawal_trend_factor: BigDecimal,         // NUMERIC NOT NULL,
+    pub stock_maximum_quantity: i32,                 // INTEGER NOT NULL,
+    pub new_batch_default_expiration_days: i16,      // SMALLINT NOT NULL,
+    pub qc_rejection_rate: BigDecimal,               // NUMERIC NOT NULL,
+    pub reorder_point: Option<i32>,                  // INTEGER,
+    pub lead_time_days: Option<i16>,                 // SMALLINT,
+    pub probability_losses_by_missing: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_no_losses: BigDecimal,           // DECIMAL(4,3) NOT NULL,
+    pub expected_loss_cost: BigDecimal,              // NUMERIC NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductParameterSweepPoint {
+    pub product_parameter_sweep_id: i32,        // INTEGER NOT NULL,
+    pub point_index: i32,                       // INTEGER NOT NULL,
+    pub random_range_factor: BigDecimal,        // NUMERIC NOT NULL,
+    pub entry_trend_factor: BigDecimal,         // NUMERIC NOT NULL,
+    pub withdrawal_trend_factor: BigDecimal,    // NUMERIC NOT NULL,
+    pub stock_maximum_quantity: i32,            // INTEGER NOT NULL,
+    pub new_batch_default_expiration_days: i16, // SMALLINT NOT NULL,
+    pub qc_rejection_rate: BigDecimal,          // NUMERIC NOT NULL,
+    pub reorder_point: Option<i32>,             // INTEGER,
+    pub lead_time_days: Option<i16>,            // SMALLINT,
+    pub probability_losses_by_missing: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_no_losses: BigDecimal,      // DECIMAL(4,3) NOT NULL,
+    pub expected_loss_cost: BigDecimal,         // NUMERIC NOT NULL,
+}
+
+pub struct ProductParameterSweepPointRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductParameterSweepPointRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductParameterSweepPointRepository {
+        ProductParameterSweepPointRepository { db }
+    }
+
+    pub async fn find_all_by_product_parameter_sweep(
+        &self,
+        product_parameter_sweep_id: i32,
+    ) -> Result<(Duration, Vec<ProductParameterSweepPoint>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductParameterSweepPoint>(
+            "
+            SELECT
+                product_parameter_sweep_id        ,
+                point_index                       ,
+                random_range_factor               ,
+                entry_trend_factor                ,
+                withdrawal_trend_factor           ,
+                stock_maximum_quantity            ,
+                new_batch_default_expiration_days ,
+                qc_rejection_rate                 ,
+                reorder_point                     ,
+                lead_time_days                    ,
+                probability_losses_by_missing     ,
+                probability_losses_by_nospace     ,
+                probability_losses_by_expirat     ,
+                probability_losses_by_rejection   ,
+                probability_no_losses             ,
+                expected_loss_cost
+            FROM product_parameter_sweep_point
+            WHERE product_parameter_sweep_id = $1
+            ORDER BY point_index;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_parameter_sweep_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        product_parameter_sweep_id: i32,
+        points: &[NewProductParameterSweepPoint],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        for point in points {
+            sqlx::query(
+                "
+                INSERT INTO product_parameter_sweep_point (
+                    product_parameter_sweep_id        ,
+                    point_index                       ,
+                    random_range_factor               ,
+                    entry_trend_factor                ,
+                    withdrawal_trend_factor           ,
+                    stock_maximum_quantity            ,
+                    new_batch_default_expiration_days ,
+                    qc_rejection_rate                 ,
+                    reorder_point                     ,
+                    lead_time_days                    ,
+                    probability_losses_by_missing     ,
+                    probability_losses_by_nospace     ,
+                    probability_losses_by_expirat     ,
+                    probability_losses_by_rejection   ,
+                    probability_no_losses             ,
+                    expected_loss_cost
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16);
+            ",
+            )
+            .bind(product_parameter_sweep_id)
+            .bind(point.point_index)
+            .bind(&point.random_range_factor)
+            .bind(&point.entry_trend_factor)
+            .bind(&point.withdrawal_trend_factor)
+            .bind(point.stock_maximum_quantity)
+            .bind(point.new_batch_default_expiration_days)
+            .bind(&point.qc_rejection_rate)
+            .bind(point.reorder_point)
+            .bind(point.lead_time_days)
+            .bind(&point.probability_losses_by_missing)
+            .bind(&point.probability_losses_by_nospace)
+            .bind(&point.probability_losses_by_expirat)
+            .bind(&point.probability_losses_by_rejection)
+            .bind(&point.probability_no_losses)
+            .bind(&point.expected_loss_cost)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_parameter_sweep_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_parameter_sweep(-1).await;
+        let (elapsed, points) = result.unwrap();
+        assert_eq!(points.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, points);
+    }
+
+    async fn get_db_repo() -> ProductParameterSweepPointRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductParameterSweepPointRepository::new(pool)
+    }
+}
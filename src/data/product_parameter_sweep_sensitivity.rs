@@ -0,0 +1,135 @@
+use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductParameterSweepSensitivity {
+    pub parameter: String,          // TEXT NOT NULL,
+    pub metric: String,             // TEXT NOT NULL,
+    pub low_value: BigDecimal,      // NUMERIC NOT NULL,
+    pub high_value: BigDecimal,     // NUMERIC NOT NULL,
+    pub metric_at_low: BigDecimal,  // NUMERIC NOT NULL,
+    pub metric_at_high: BigDecimal, // NUMERIC NOT NULL,
+    pub swing: BigDecimal,          // NUMERIC NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductParameterSweepSensitivity {
+    pub product_parameter_sweep_id: i32, // INTEGER NOT NULL,
+    pub parameter: String,               // TEXT NOT NULL,
+    pub metric: String,                  // TEXT NOT NULL,
+    pub low_value: BigDecimal,           // NUMERIC NOT NULL,
+    pub high_value: BigDecimal,          // NUMERIC NOT NULL,
+    pub metric_at_low: BigDecimal,       // NUMERIC NOT NULL,
+    pub metric_at_high: BigDecimal,      // NUMERIC NOT NULL,
+    pub swing: BigDecimal,               // NUMERIC NOT NULL,
+}
+
+pub struct ProductParameterSweepSensitivityRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductParameterSweepSensitivityRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductParameterSweepSensitivityRepository {
+        ProductParameterSweepSensitivityRepository { db }
+    }
+
+    /// Tornado chart order: the widest swing first for each metric.
+    pub async fn find_all_by_product_parameter_sweep(
+        &self,
+        product_parameter_sweep_id: i32,
+    ) -> Result<(Duration, Vec<ProductParameterSweepSensitivity>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductParameterSweepSensitivity>(
+            "
+            SELECT
+                product_parameter_sweep_id ,
+                parameter                  ,
+                metric                     ,
+                low_value                  ,
+                high_value                 ,
+                metric_at_low              ,
+                metric_at_high             ,
+                swing
+            FROM product_parameter_sweep_sensitivity
+            WHERE product_parameter_sweep_id = $1
+            ORDER BY metric, swing DESC;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_parameter_sweep_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        product_parameter_sweep_id: i32,
+        sensitivities: &[NewProductParameterSweepSensitivity],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        for sensitivity in sensitivities {
+            sqlx::query(
+                "
+                INSERT INTO product_parameter_sweep_sensitivity (
+                    product_parameter_sweep_id ,
+                    parameter                  ,
+                    metric                     ,
+                    low_value                  ,
+                    high_value                 ,
+                    metric_at_low              ,
+                    metric_at_high             ,
+                    swing
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+            ",
+            )
+            .bind(product_parameter_sweep_id)
+            .bind(&sensitivity.parameter)
+            .bind(&sensitivity.metric)
+            .bind(&sensitivity.low_value)
+            .bind(&sensitivity.high_value)
+            .bind(&sensitivity.metric_at_low)
+            .bind(&sensitivity.metric_at_high)
+            .bind(&sensitivity.swing)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_parameter_sweep_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_parameter_sweep(-1).await;
+        let (elapsed, sensitivities) = result.unwrap();
+        assert_eq!(sensitivities.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, sensitivities);
+    }
+
+    async fn get_db_repo() -> ProductParameterSweepSensitivityRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductParameterSweepSensitivityRepository::new(pool)
+    }
+}
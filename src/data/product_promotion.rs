@@ -0,0 +1,105 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductPromotion {
+    pub id: i32,                                   // SERIAL PRIMARY KEY,
+    pub product_id: Option<Uuid>,                  // UUID REFERENCES product_props (id),
+    pub category: Option<String>,                  // TEXT,
+    pub name: String,                              // TEXT NOT NULL,
+    pub start_date: NaiveDate,                     // DATE NOT NULL,
+    pub end_date: NaiveDate,                       // DATE NOT NULL,
+    pub withdrawal_multiplier: Option<BigDecimal>, // NUMERIC CHECK(withdrawal_multiplier >= 0),
+    pub withdrawal_additive_qty: Option<BigDecimal>, // NUMERIC CHECK(withdrawal_additive_qty >= 0),
+                                                   //pub created_at                    : , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductPromotionRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductPromotionRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductPromotionRepository {
+        ProductPromotionRepository { db }
+    }
+
+    /// Promotions of the product, or of its category, overlapping the dates.
+    pub async fn find_all_by_product_and_dates(
+        &self,
+        product_id: Uuid,
+        category: Option<&str>,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<(Duration, Vec<ProductPromotion>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductPromotion>(
+            "
+            SELECT
+                id                     ,
+                product_id             ,
+                category               ,
+                name                   ,
+                start_date             ,
+                end_date               ,
+                withdrawal_multiplier  ,
+                withdrawal_additive_qty
+            FROM product_promotion
+            WHERE (product_id = $1 OR category = $2)
+            AND   start_date <= $4
+            AND   end_date   >= $3
+            ORDER BY start_date, id;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_id)
+            .bind(category)
+            .bind(start_date)
+            .bind(end_date)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_and_dates() {
+        let repo = get_db_repo().await;
+        let result = repo
+            .find_all_by_product_and_dates(
+                Uuid::from_u128(0),
+                None,
+                NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2022, 1, 31).unwrap(),
+            )
+            .await;
+        let (elapsed, promotions) = result.unwrap();
+        assert!(promotions.is_empty());
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, promotions);
+    }
+
+    async fn get_db_repo() -> ProductPromotionRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductPromotionRepository::new(pool)
+    }
+}
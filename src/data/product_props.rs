@@ -14,6 +14,30 @@ pub struct ProductProps {
     pub minimum_quantity: i32,
     pub new_batch_default_expiration_days: i16,
     pub active: bool,
+    pub day_events_order: Option<String>,
+    pub expiration_inclusive: Option<bool>,
+    pub time_step_hours: Option<i16>,
+    pub qc_hold_days: i16,
+    pub qc_rejection_rate: BigDecimal,
+    pub shortage_mode: String,
+    pub unit_cost: BigDecimal,
+    pub lost_margin_per_unit: BigDecimal,
+    pub disposal_cost_per_unit: BigDecimal,
+    pub overcapacity_cost_per_unit: BigDecimal,
+    pub backorder_cost_per_unit_day: BigDecimal,
+    pub reorder_point: Option<i32>,
+    pub replenishment_lead_time_days: i16,
+    pub target_service_level: Option<BigDecimal>,
+    pub target_nospace_probability: Option<BigDecimal>,
+    pub category: Option<String>,
+    pub exclude_promotions_from_history: Option<bool>,
+    pub estimate_trend: Option<bool>,
+    pub forecast_model: Option<String>,
+    pub clean_outliers: Option<bool>,
+    pub history_imputation: Option<String>,
+    pub analog_full_history_days: Option<i16>,
+    pub demand_regimes: Option<bool>,
+    pub new_batch_expiration_hours: Option<i32>,
     //    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     //    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
 }
@@ -42,7 +66,31 @@ impl ProductPropsRepository {
                 maximum_quantity,
                 minimum_quantity,
                 new_batch_default_expiration_days,
-                active
+                active,
+                day_events_order,
+                expiration_inclusive,
+                time_step_hours,
+                qc_hold_days,
+                qc_rejection_rate,
+                shortage_mode,
+                unit_cost,
+                lost_margin_per_unit,
+                disposal_cost_per_unit,
+                overcapacity_cost_per_unit,
+                backorder_cost_per_unit_day,
+                reorder_point,
+                replenishment_lead_time_days,
+                target_service_level,
+                target_nospace_probability,
+                category,
+                exclude_promotions_from_history,
+                estimate_trend,
+                forecast_model,
+                clean_outliers,
+                history_imputation,
+                analog_full_history_days,
+                demand_regimes,
+                new_batch_expiration_hours
             FROM product_props;
         ",
         );
@@ -68,7 +116,31 @@ impl ProductPropsRepository {
                 maximum_quantity,
                 minimum_quantity,
                 new_batch_default_expiration_days,
-                active
+                active,
+                day_events_order,
+                expiration_inclusive,
+                time_step_hours,
+                qc_hold_days,
+                qc_rejection_rate,
+                shortage_mode,
+                unit_cost,
+                lost_margin_per_unit,
+                disposal_cost_per_unit,
+                overcapacity_cost_per_unit,
+                backorder_cost_per_unit_day,
+                reorder_point,
+                replenishment_lead_time_days,
+                target_service_level,
+                target_nospace_probability,
+                category,
+                exclude_promotions_from_history,
+                estimate_trend,
+                forecast_model,
+                clean_outliers,
+                history_imputation,
+                analog_full_history_days,
+                demand_regimes,
+                new_batch_expiration_hours
             FROM product_props
             WHERE active = $1;
         ",
@@ -95,7 +167,31 @@ impl ProductPropsRepository {
                 maximum_quantity,
                 minimum_quantity,
                 new_batch_default_expiration_days,
-                active
+                active,
+                day_events_order,
+                expiration_inclusive,
+                time_step_hours,
+                qc_hold_days,
+                qc_rejection_rate,
+                shortage_mode,
+                unit_cost,
+                lost_margin_per_unit,
+                disposal_cost_per_unit,
+                overcapacity_cost_per_unit,
+                backorder_cost_per_unit_day,
+                reorder_point,
+                replenishment_lead_time_days,
+                target_service_level,
+                target_nospace_probability,
+                category,
+                exclude_promotions_from_history,
+                estimate_trend,
+                forecast_model,
+                clean_outliers,
+                history_imputation,
+                analog_full_history_days,
+                demand_regimes,
+                new_batch_expiration_hours
             FROM product_props
             WHERE id = $1;
         ",
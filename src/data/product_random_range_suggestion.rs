@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductRandomRangeSuggestion {
+    pub product_id: Uuid,             // UUID NOT NULL REFERENCES product_props (id),
+    pub reference_date: NaiveDate,    // DATE NOT NULL,
+    pub current_factor: BigDecimal,   // DECIMAL(3,2) NOT NULL,
+    pub suggested_factor: BigDecimal, // DECIMAL(3,2) NOT NULL,
+    pub relative_std: BigDecimal,     // NUMERIC NOT NULL,
+    pub observed_days: i32,           // INTEGER NOT NULL,
+    pub brier_score_before: Option<BigDecimal>, // NUMERIC,
+    pub brier_score_after: Option<BigDecimal>, // NUMERIC,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductRandomRangeSuggestion {
+    pub id: i32,                                // SERIAL PRIMARY KEY,
+    pub product_id: Uuid,                       // UUID NOT NULL REFERENCES product_props (id),
+    pub reference_date: NaiveDate,              // DATE NOT NULL,
+    pub current_factor: BigDecimal,             // DECIMAL(3,2) NOT NULL,
+    pub suggested_factor: BigDecimal,           // DECIMAL(3,2) NOT NULL,
+    pub relative_std: BigDecimal,               // NUMERIC NOT NULL,
+    pub observed_days: i32,                     // INTEGER NOT NULL,
+    pub brier_score_before: Option<BigDecimal>, // NUMERIC,
+    pub brier_score_after: Option<BigDecimal>,  // NUMERIC,
+                                                //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductRandomRangeSuggestionRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductRandomRangeSuggestionRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductRandomRangeSuggestionRepository {
+        ProductRandomRangeSuggestionRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductRandomRangeSuggestion>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductRandomRangeSuggestion>(
+            "
+            SELECT
+                id                 ,
+                product_id         ,
+                reference_date     ,
+                current_factor     ,
+                suggested_factor   ,
+                relative_std       ,
+                observed_days      ,
+                brier_score_before ,
+                brier_score_after
+            FROM product_random_range_suggestion
+            WHERE product_id = $1;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new suggestion.
+    pub async fn insert(
+        &self,
+        suggestion: &NewProductRandomRangeSuggestion,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_random_range_suggestion (
+                product_id         ,
+                reference_date     ,
+                current_factor     ,
+                suggested_factor   ,
+                relative_std       ,
+                observed_days      ,
+                brier_score_before ,
+                brier_score_after
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(suggestion.product_id)
+            .bind(suggestion.reference_date)
+            .bind(&suggestion.current_factor)
+            .bind(&suggestion.suggested_factor)
+            .bind(&suggestion.relative_std)
+            .bind(suggestion.observed_days)
+            .bind(&suggestion.brier_score_before)
+            .bind(&suggestion.brier_score_after)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, suggestions) = result.unwrap();
+        assert_eq!(suggestions.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, suggestions);
+    }
+
+    async fn get_db_repo() -> ProductRandomRangeSuggestionRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductRandomRangeSuggestionRepository::new(pool)
+    }
+}
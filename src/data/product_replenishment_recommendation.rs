@@ -0,0 +1,153 @@
+use chrono::NaiveDate;
+use sqlx::{
+    types::{BigDecimal, Uuid},
+    FromRow, Pool, Postgres,
+};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductReplenishmentRecommendation {
+    pub product_id: Uuid,                      // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate,                 // DATE NOT NULL,
+    pub end_date: NaiveDate,                   // DATE NOT NULL,
+    pub target_service_level: BigDecimal,      // DECIMAL(4,3) NOT NULL,
+    pub lead_time_days: i16,                   // SMALLINT NOT NULL,
+    pub minimum_quantity: i32,                 // INTEGER NOT NULL,
+    pub reorder_point: i32,                    // INTEGER NOT NULL,
+    pub expected_service_level: BigDecimal,    // DECIMAL(4,3) NOT NULL,
+    pub expected_expired_quantity: BigDecimal, // NUMERIC NOT NULL,
+    pub is_target_reached: bool,               // BOOLEAN NOT NULL,
+    pub candidates: i32,                       // INTEGER NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductReplenishmentRecommendation {
+    pub id: i32,                               // SERIAL,
+    pub product_id: Uuid,                      // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate,                 // DATE NOT NULL,
+    pub end_date: NaiveDate,                   // DATE NOT NULL,
+    pub target_service_level: BigDecimal,      // DECIMAL(4,3) NOT NULL,
+    pub lead_time_days: i16,                   // SMALLINT NOT NULL,
+    pub minimum_quantity: i32,                 // INTEGER NOT NULL,
+    pub reorder_point: i32,                    // INTEGER NOT NULL,
+    pub expected_service_level: BigDecimal,    // DECIMAL(4,3) NOT NULL,
+    pub expected_expired_quantity: BigDecimal, // NUMERIC NOT NULL,
+    pub is_target_reached: bool,               // BOOLEAN NOT NULL,
+    pub candidates: i32,                       // INTEGER NOT NULL,
+                                               //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductReplenishmentRecommendationRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductReplenishmentRecommendationRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductReplenishmentRecommendationRepository {
+        ProductReplenishmentRecommendationRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductReplenishmentRecommendation>), Box<dyn std::error::Error>>
+    {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductReplenishmentRecommendation>(
+            "
+            SELECT
+                id                        ,
+                product_id                ,
+                start_date                ,
+                end_date                  ,
+                target_service_level      ,
+                lead_time_days            ,
+                minimum_quantity          ,
+                reorder_point             ,
+                expected_service_level    ,
+                expected_expired_quantity ,
+                is_target_reached         ,
+                candidates
+            FROM product_replenishment_recommendation
+            WHERE product_id = $1;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new recommendation.
+    pub async fn insert(
+        &self,
+        recommendation: &NewProductReplenishmentRecommendation,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_replenishment_recommendation (
+                product_id                ,
+                start_date                ,
+                end_date                  ,
+                target_service_level      ,
+                lead_time_days            ,
+                minimum_quantity          ,
+                reorder_point             ,
+                expected_service_level    ,
+                expected_expired_quantity ,
+                is_target_reached         ,
+                candidates
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(recommendation.product_id)
+            .bind(recommendation.start_date)
+            .bind(recommendation.end_date)
+            .bind(&recommendation.target_service_level)
+            .bind(recommendation.lead_time_days)
+            .bind(recommendation.minimum_quantity)
+            .bind(recommendation.reorder_point)
+            .bind(&recommendation.expected_service_level)
+            .bind(&recommendation.expected_expired_quantity)
+            .bind(recommendation.is_target_reached)
+            .bind(recommendation.candidates)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, recommendations) = result.unwrap();
+        assert_eq!(recommendations.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, recommendations);
+    }
+
+    async fn get_db_repo() -> ProductReplenishmentRecommendationRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductReplenishmentRecommendationRepository::new(pool)
+    }
+}
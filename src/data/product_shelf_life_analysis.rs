@@ -0,0 +1,114 @@
+use chrono::NaiveDate;
+use sqlx::{types::Uuid, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductShelfLifeAnalysis {
+    pub product_id: Uuid,             // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate,        // DATE NOT NULL,
+    pub end_date: NaiveDate,          // DATE NOT NULL,
+    pub current_expiration_days: i16, // SMALLINT NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductShelfLifeAnalysis {
+    pub id: i32,               // SERIAL,
+    pub product_id: Uuid,      // UUID REFERENCES product_props (id),
+    pub start_date: NaiveDate, // DATE NOT NULL,
+    pub end_date: NaiveDate,   // DATE NOT NULL,
+    pub current_expiration_days: i16, // SMALLINT NOT NULL,
+                               //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct ProductShelfLifeAnalysisRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductShelfLifeAnalysisRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductShelfLifeAnalysisRepository {
+        ProductShelfLifeAnalysisRepository { db }
+    }
+
+    pub async fn find_all_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<(Duration, Vec<ProductShelfLifeAnalysis>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductShelfLifeAnalysis>(
+            "
+            SELECT
+                id                      ,
+                product_id              ,
+                start_date              ,
+                end_date                ,
+                current_expiration_days
+            FROM product_shelf_life_analysis
+            WHERE product_id = $1;
+        ",
+        );
+
+        let query_res = query.bind(product_id).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new analysis.
+    pub async fn insert(
+        &self,
+        analysis: &NewProductShelfLifeAnalysis,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_shelf_life_analysis (
+                product_id              ,
+                start_date              ,
+                end_date                ,
+                current_expiration_days
+            ) VALUES ($1, $2, $3, $4)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(analysis.product_id)
+            .bind(analysis.start_date)
+            .bind(analysis.end_date)
+            .bind(analysis.current_expiration_days)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product(Uuid::from_u128(0)).await;
+        let (elapsed, analyses) = result.unwrap();
+        assert_eq!(analyses.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, analyses);
+    }
+
+    async fn get_db_repo() -> ProductShelfLifeAnalysisRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductShelfLifeAnalysisRepository::new(pool)
+    }
+}
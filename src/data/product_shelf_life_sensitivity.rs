@@ -0,0 +1,134 @@
+use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductShelfLifeSensitivity {
+    pub new_batch_default_expiration_days: i16, // SMALLINT NOT NULL,
+    pub probability_losses_by_missing: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_no_losses: BigDecimal,      // DECIMAL(4,3) NOT NULL,
+    pub expected_loss_cost: BigDecimal,         // NUMERIC NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductShelfLifeSensitivity {
+    pub product_shelf_life_analysis_id: i32, // INTEGER NOT NULL,
+    pub new_batch_default_expiration_days: i16, // SMALLINT NOT NULL,
+    pub probability_losses_by_missing: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_no_losses: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub expected_loss_cost: BigDecimal,      // NUMERIC NOT NULL,
+}
+
+pub struct ProductShelfLifeSensitivityRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductShelfLifeSensitivityRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductShelfLifeSensitivityRepository {
+        ProductShelfLifeSensitivityRepository { db }
+    }
+
+    pub async fn find_all_by_product_shelf_life_analysis(
+        &self,
+        product_shelf_life_analysis_id: i32,
+    ) -> Result<(Duration, Vec<ProductShelfLifeSensitivity>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductShelfLifeSensitivity>(
+            "
+            SELECT
+                product_shelf_life_analysis_id    ,
+                new_batch_default_expiration_days ,
+                probability_losses_by_missing     ,
+                probability_losses_by_nospace     ,
+                probability_losses_by_expirat     ,
+                probability_losses_by_rejection   ,
+                probability_no_losses             ,
+                expected_loss_cost
+            FROM product_shelf_life_sensitivity
+            WHERE product_shelf_life_analysis_id = $1
+            ORDER BY new_batch_default_expiration_days;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_shelf_life_analysis_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        product_shelf_life_analysis_id: i32,
+        sensitivity: &[NewProductShelfLifeSensitivity],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let mut tx = self.db.begin().await?;
+        for point in sensitivity {
+            sqlx::query(
+                "
+                INSERT INTO product_shelf_life_sensitivity (
+                    product_shelf_life_analysis_id    ,
+                    new_batch_default_expiration_days ,
+                    probability_losses_by_missing     ,
+                    probability_losses_by_nospace     ,
+                    probability_losses_by_expirat     ,
+                    probability_losses_by_rejection   ,
+                    probability_no_losses             ,
+                    expected_loss_cost
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+            ",
+            )
+            .bind(product_shelf_life_analysis_id)
+            .bind(point.new_batch_default_expiration_days)
+            .bind(&point.probability_losses_by_missing)
+            .bind(&point.probability_losses_by_nospace)
+            .bind(&point.probability_losses_by_expirat)
+            .bind(&point.probability_losses_by_rejection)
+            .bind(&point.probability_no_losses)
+            .bind(&point.expected_loss_cost)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_shelf_life_analysis_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_shelf_life_analysis(-1).await;
+        let (elapsed, sensitivity) = result.unwrap();
+        assert_eq!(sensitivity.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, sensitivity);
+    }
+
+    async fn get_db_repo() -> ProductShelfLifeSensitivityRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductShelfLifeSensitivityRepository::new(pool)
+    }
+}
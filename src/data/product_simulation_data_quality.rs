@@ -0,0 +1,115 @@
+use sqlx::{FromRow, PgConnection, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductSimulationDataQuality {
+    pub history_imputation: String, // TEXT NOT NULL,
+    pub simulated_days: i32,        // INTEGER NOT NULL,
+    pub forecast_days: i32,         // INTEGER NOT NULL,
+    pub imputed_days: i32,          // INTEGER NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductSimulationDataQuality {
+    pub product_simulation_summary_id: i32, // INTEGER NOT NULL PRIMARY KEY,
+    pub history_imputation: String,         // TEXT NOT NULL,
+    pub simulated_days: i32,                // INTEGER NOT NULL,
+    pub forecast_days: i32,                 // INTEGER NOT NULL,
+    pub imputed_days: i32,                  // INTEGER NOT NULL,
+}
+
+pub struct ProductSimulationDataQualityRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductSimulationDataQualityRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductSimulationDataQualityRepository {
+        ProductSimulationDataQualityRepository { db }
+    }
+
+    pub async fn find_by_product_simulation_summary(
+        &self,
+        product_simulation_summary_id: i32,
+    ) -> Result<(Duration, Option<ProductSimulationDataQuality>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductSimulationDataQuality>(
+            "
+            SELECT
+                product_simulation_summary_id ,
+                history_imputation            ,
+                simulated_days                ,
+                forecast_days                 ,
+                imputed_days
+            FROM product_simulation_data_quality
+            WHERE product_simulation_summary_id = $1;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_simulation_summary_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert(
+        &self,
+        tx: &mut PgConnection,
+        product_simulation_summary_id: i32,
+        data_quality: &NewProductSimulationDataQuality,
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        sqlx::query(
+            "
+            INSERT INTO product_simulation_data_quality (
+                product_simulation_summary_id ,
+                history_imputation            ,
+                simulated_days                ,
+                forecast_days                 ,
+                imputed_days
+            ) VALUES ($1, $2, $3, $4, $5);
+        ",
+        )
+        .bind(product_simulation_summary_id)
+        .bind(&data_quality.history_imputation)
+        .bind(data_quality.simulated_days)
+        .bind(data_quality.forecast_days)
+        .bind(data_quality.imputed_days)
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_by_product_simulation_summary_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_by_product_simulation_summary(-1).await;
+        let (elapsed, data_quality) = result.unwrap();
+        assert!(data_quality.is_none());
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, data_quality);
+    }
+
+    async fn get_db_repo() -> ProductSimulationDataQualityRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductSimulationDataQualityRepository::new(pool)
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::NaiveDate;
+use sqlx::{FromRow, PgConnection, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductSimulationFirstLoss {
+    pub loss_type: String,              // TEXT NOT NULL,
+    pub runs: i32,                      // INTEGER NOT NULL DEFAULT 0,
+    pub runs_with_loss: i32,            // INTEGER NOT NULL,
+    pub earliest_date: NaiveDate,       // DATE NOT NULL,
+    pub median_date: Option<NaiveDate>, // DATE,
+    pub p90_date: Option<NaiveDate>,    // DATE,
+    pub earliest_days: i32,             // INTEGER NOT NULL,
+    pub median_days: Option<i32>,       // INTEGER,
+    pub p90_days: Option<i32>,          // INTEGER,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductSimulationFirstLoss {
+    pub product_simulation_summary_id: i32, // INTEGER NOT NULL,
+    pub loss_type: String,                  // TEXT NOT NULL,
+    pub runs: i32,                          // INTEGER NOT NULL DEFAULT 0,
+    pub runs_with_loss: i32,                // INTEGER NOT NULL,
+    pub earliest_date: NaiveDate,           // DATE NOT NULL,
+    pub median_date: Option<NaiveDate>,     // DATE,
+    pub p90_date: Option<NaiveDate>,        // DATE,
+    pub earliest_days: i32,                 // INTEGER NOT NULL,
+    pub median_days: Option<i32>,           // INTEGER,
+    pub p90_days: Option<i32>,              // INTEGER,
+}
+
+pub struct ProductSimulationFirstLossRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductSimulationFirstLossRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductSimulationFirstLossRepository {
+        ProductSimulationFirstLossRepository { db }
+    }
+
+    pub async fn find_all_by_product_simulation_summary(
+        &self,
+        product_simulation_summary_id: i32,
+    ) -> Result<(Duration, Vec<ProductSimulationFirstLoss>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductSimulationFirstLoss>(
+            "
+            SELECT
+                product_simulation_summary_id ,
+                loss_type                     ,
+                runs                          ,
+                runs_with_loss                ,
+                earliest_date                 ,
+                median_date                   ,
+                p90_date                      ,
+                earliest_days                 ,
+                median_days                   ,
+                p90_days
+            FROM product_simulation_first_loss
+            WHERE product_simulation_summary_id = $1;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_simulation_summary_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        tx: &mut PgConnection,
+        product_simulation_summary_id: i32,
+        first_losses: &[NewProductSimulationFirstLoss],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        for first_loss in first_losses {
+            sqlx::query(
+                "
+                INSERT INTO product_simulation_first_loss (
+                    product_simulation_summary_id ,
+                    loss_type                     ,
+                    runs                          ,
+                    runs_with_loss                ,
+                    earliest_date                 ,
+                    median_date                   ,
+                    p90_date                      ,
+                    earliest_days                 ,
+                    median_days                   ,
+                    p90_days
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);
+            ",
+            )
+            .bind(product_simulation_summary_id)
+            .bind(&first_loss.loss_type)
+            .bind(first_loss.runs)
+            .bind(first_loss.runs_with_loss)
+            .bind(first_loss.earliest_date)
+            .bind(first_loss.median_date)
+            .bind(first_loss.p90_date)
+            .bind(first_loss.earliest_days)
+            .bind(first_loss.median_days)
+            .bind(first_loss.p90_days)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_simulation_summary_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_simulation_summary(-1).await;
+        let (elapsed, first_losses) = result.unwrap();
+        assert_eq!(first_losses.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, first_losses);
+    }
+
+    async fn get_db_repo() -> ProductSimulationFirstLossRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductSimulationFirstLossRepository::new(pool)
+    }
+}
@@ -0,0 +1,137 @@
+use sqlx::{types::BigDecimal, FromRow, PgConnection, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductSimulationKpi {
+    pub kpi: String,      // TEXT NOT NULL,
+    pub runs: i32,        // INTEGER NOT NULL,
+    pub mean: BigDecimal, // NUMERIC NOT NULL,
+    pub p5: BigDecimal,   // NUMERIC NOT NULL,
+    pub p25: BigDecimal,  // NUMERIC NOT NULL,
+    pub p50: BigDecimal,  // NUMERIC NOT NULL,
+    pub p75: BigDecimal,  // NUMERIC NOT NULL,
+    pub p95: BigDecimal,  // NUMERIC NOT NULL,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductSimulationKpi {
+    pub product_simulation_summary_id: i32, // INTEGER NOT NULL,
+    pub kpi: String,                        // TEXT NOT NULL,
+    pub runs: i32,                          // INTEGER NOT NULL,
+    pub mean: BigDecimal,                   // NUMERIC NOT NULL,
+    pub p5: BigDecimal,                     // NUMERIC NOT NULL,
+    pub p25: BigDecimal,                    // NUMERIC NOT NULL,
+    pub p50: BigDecimal,                    // NUMERIC NOT NULL,
+    pub p75: BigDecimal,                    // NUMERIC NOT NULL,
+    pub p95: BigDecimal,                    // NUMERIC NOT NULL,
+}
+
+pub struct ProductSimulationKpiRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductSimulationKpiRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductSimulationKpiRepository {
+        ProductSimulationKpiRepository { db }
+    }
+
+    pub async fn find_all_by_product_simulation_summary(
+        &self,
+        product_simulation_summary_id: i32,
+    ) -> Result<(Duration, Vec<ProductSimulationKpi>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductSimulationKpi>(
+            "
+            SELECT
+                product_simulation_summary_id ,
+                kpi                           ,
+                runs                          ,
+                mean                          ,
+                p5                            ,
+                p25                           ,
+                p50                           ,
+                p75                           ,
+                p95
+            FROM product_simulation_kpi
+            WHERE product_simulation_summary_id = $1;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_simulation_summary_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        tx: &mut PgConnection,
+        product_simulation_summary_id: i32,
+        kpis: &[NewProductSimulationKpi],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        for kpi in kpis {
+            sqlx::query(
+                "
+                INSERT INTO product_simulation_kpi (
+                    product_simulation_summary_id ,
+                    kpi                           ,
+                    runs                          ,
+                    mean                          ,
+                    p5                            ,
+                    p25                           ,
+                    p50                           ,
+                    p75                           ,
+                    p95
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);
+            ",
+            )
+            .bind(product_simulation_summary_id)
+            .bind(&kpi.kpi)
+            .bind(kpi.runs)
+            .bind(&kpi.mean)
+            .bind(&kpi.p5)
+            .bind(&kpi.p25)
+            .bind(&kpi.p50)
+            .bind(&kpi.p75)
+            .bind(&kpi.p95)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_simulation_summary_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_simulation_summary(-1).await;
+        let (elapsed, kpis) = result.unwrap();
+        assert_eq!(kpis.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, kpis);
+    }
+
+    async fn get_db_repo() -> ProductSimulationKpiRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductSimulationKpiRepository::new(pool)
+    }
+}
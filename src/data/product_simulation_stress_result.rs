@@ -0,0 +1,144 @@
+use chrono::NaiveDate;
+use sqlx::{types::BigDecimal, FromRow, PgConnection, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct NewProductSimulationStressResult {
+    pub stress_test_name: String,                  // TEXT NOT NULL,
+    pub shortage_qty: BigDecimal,                  // NUMERIC NOT NULL,
+    pub nospace_qty: BigDecimal,                   // NUMERIC NOT NULL,
+    pub expired_qty: BigDecimal,                   // NUMERIC NOT NULL,
+    pub rejected_qty: BigDecimal,                  // NUMERIC NOT NULL,
+    pub loss_cost: BigDecimal,                     // NUMERIC NOT NULL,
+    pub minimum_stock_qty: BigDecimal,             // NUMERIC NOT NULL,
+    pub final_stock_qty: BigDecimal,               // NUMERIC NOT NULL,
+    pub first_date_with_losses: Option<NaiveDate>, // DATE,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct ProductSimulationStressResult {
+    pub product_simulation_summary_id: i32, // INTEGER NOT NULL,
+    pub stress_test_name: String,           // TEXT NOT NULL,
+    pub shortage_qty: BigDecimal,           // NUMERIC NOT NULL,
+    pub nospace_qty: BigDecimal,            // NUMERIC NOT NULL,
+    pub expired_qty: BigDecimal,            // NUMERIC NOT NULL,
+    pub rejected_qty: BigDecimal,           // NUMERIC NOT NULL,
+    pub loss_cost: BigDecimal,              // NUMERIC NOT NULL,
+    pub minimum_stock_qty: BigDecimal,      // NUMERIC NOT NULL,
+    pub final_stock_qty: BigDecimal,        // NUMERIC NOT NULL,
+    pub first_date_with_losses: Option<NaiveDate>, // DATE,
+}
+
+pub struct ProductSimulationStressResultRepository {
+    db: Pool<Postgres>,
+}
+
+impl ProductSimulationStressResultRepository {
+    pub fn new(db: Pool<Postgres>) -> ProductSimulationStressResultRepository {
+        ProductSimulationStressResultRepository { db }
+    }
+
+    pub async fn find_all_by_product_simulation_summary(
+        &self,
+        product_simulation_summary_id: i32,
+    ) -> Result<(Duration, Vec<ProductSimulationStressResult>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductSimulationStressResult>(
+            "
+            SELECT
+                product_simulation_summary_id ,
+                stress_test_name              ,
+                shortage_qty                  ,
+                nospace_qty                   ,
+                expired_qty                   ,
+                rejected_qty                  ,
+                loss_cost                     ,
+                minimum_stock_qty             ,
+                final_stock_qty               ,
+                first_date_with_losses
+            FROM product_simulation_stress_result
+            WHERE product_simulation_summary_id = $1
+            ORDER BY loss_cost DESC;
+        ",
+        );
+
+        let query_res = query
+            .bind(product_simulation_summary_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    pub async fn insert_all(
+        &self,
+        tx: &mut PgConnection,
+        product_simulation_summary_id: i32,
+        stress_results: &[NewProductSimulationStressResult],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        for stress_result in stress_results {
+            sqlx::query(
+                "
+                INSERT INTO product_simulation_stress_result (
+                    product_simulation_summary_id ,
+                    stress_test_name              ,
+                    shortage_qty                  ,
+                    nospace_qty                   ,
+                    expired_qty                   ,
+                    rejected_qty                  ,
+                    loss_cost                     ,
+                    minimum_stock_qty             ,
+                    final_stock_qty               ,
+                    first_date_with_losses
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);
+            ",
+            )
+            .bind(product_simulation_summary_id)
+            .bind(&stress_result.stress_test_name)
+            .bind(&stress_result.shortage_qty)
+            .bind(&stress_result.nospace_qty)
+            .bind(&stress_result.expired_qty)
+            .bind(&stress_result.rejected_qty)
+            .bind(&stress_result.loss_cost)
+            .bind(&stress_result.minimum_stock_qty)
+            .bind(&stress_result.final_stock_qty)
+            .bind(stress_result.first_date_with_losses)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        Ok(timer.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_product_simulation_summary_no_results() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_product_simulation_summary(-1).await;
+        let (elapsed, stress_results) = result.unwrap();
+        assert_eq!(stress_results.len(), 0);
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, stress_results);
+    }
+
+    async fn get_db_repo() -> ProductSimulationStressResultRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        ProductSimulationStressResultRepository::new(pool)
+    }
+}
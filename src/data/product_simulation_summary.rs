@@ -1,34 +1,41 @@
 use chrono::NaiveDate;
 use sqlx::{
     types::{BigDecimal, Uuid},
-    FromRow, Pool, Postgres,
+    FromRow, PgConnection, Pool, Postgres,
 };
 use std::time::{Duration, Instant};
 
 #[derive(Debug, FromRow, Clone)]
 pub struct NewProductSimulationSummary {
-    pub id: i32,                                   // SERIAL,
-    pub product_id: Uuid,                          // UUID REFERENCES product_props (id),
-    pub probability_losses_by_missing: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub start_date: NaiveDate,                     // DATE NOT NULL,
-    pub end_date: NaiveDate,                       // DATE NOT NULL,
+    pub product_id: Uuid, // UUID REFERENCES product_props (id),
+    pub probability_losses_by_missing: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0,
+    pub probability_no_losses: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0,
+    pub expected_loss_cost: BigDecimal, // NUMERIC NOT NULL DEFAULT 0,
+    pub start_date: NaiveDate, // DATE NOT NULL,
+    pub end_date: NaiveDate, // DATE NOT NULL,
     pub first_date_with_losses: Option<NaiveDate>, // DATE,
-                                                   //pub created_at                    : , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    pub what_if_label: Option<String>, // TEXT,
+                          //pub created_at                    : , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 }
 
 #[derive(Debug, FromRow, Clone)]
 pub struct ProductSimulationSummary {
-    pub id: i32,                                   // SERIAL,
-    pub product_id: Uuid,                          // UUID REFERENCES product_props (id),
-    pub probability_losses_by_missing: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub start_date: NaiveDate,                     // DATE NOT NULL,
-    pub end_date: NaiveDate,                       // DATE NOT NULL,
-    pub first_date_with_losses: Option<NaiveDate>, // DATE,
-                                                   //pub created_at                    : , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    pub id: i32,                                     // SERIAL,
+    pub product_id: Uuid,                            // UUID REFERENCES product_props (id),
+    pub probability_losses_by_missing: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0,
+    pub probability_no_losses: BigDecimal,           // DECIMAL(4,3) NOT NULL DEFAULT 0,
+    pub expected_loss_cost: BigDecimal,              // NUMERIC NOT NULL DEFAULT 0,
+    pub start_date: NaiveDate,                       // DATE NOT NULL,
+    pub end_date: NaiveDate,                         // DATE NOT NULL,
+    pub first_date_with_losses: Option<NaiveDate>,   // DATE,
+    pub what_if_label: Option<String>,               // TEXT,
+                                                     //pub created_at                    : , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 }
 
 pub struct ProductSimulationSummaryRepository {
@@ -53,9 +60,13 @@ impl ProductSimulationSummaryRepository {
                 probability_losses_by_missing ,
                 probability_losses_by_nospace ,
                 probability_losses_by_expirat ,
+                probability_losses_by_rejection,
+                probability_no_losses         ,
+                expected_loss_cost            ,
                 start_date                    ,
                 end_date                      ,
-                first_date_with_losses        
+                first_date_with_losses        ,
+                what_if_label
             FROM product_simulation_summary;
         ",
         );
@@ -79,9 +90,13 @@ impl ProductSimulationSummaryRepository {
                 probability_losses_by_missing ,
                 probability_losses_by_nospace ,
                 probability_losses_by_expirat ,
+                probability_losses_by_rejection,
+                probability_no_losses         ,
+                expected_loss_cost            ,
                 start_date                    ,
                 end_date                      ,
-                first_date_with_losses
+                first_date_with_losses        ,
+                what_if_label
             FROM product_simulation_summary
             WHERE product_id = $1;
         ",
@@ -91,6 +106,88 @@ impl ProductSimulationSummaryRepository {
 
         Ok((timer.elapsed(), query_res))
     }
+
+    /// Latest summary of each product, leaving out the what-if runs,
+    /// the ones with more money at risk first.
+    pub async fn find_latest_ranked_by_expected_loss_cost(
+        &self,
+    ) -> Result<(Duration, Vec<ProductSimulationSummary>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, ProductSimulationSummary>(
+            "
+            SELECT * FROM (
+                SELECT DISTINCT ON (product_id)
+                    id                            ,
+                    product_id                    ,
+                    probability_losses_by_missing ,
+                    probability_losses_by_nospace ,
+                    probability_losses_by_expirat ,
+                    probability_losses_by_rejection,
+                    probability_no_losses         ,
+                    expected_loss_cost            ,
+                    start_date                    ,
+                    end_date                      ,
+                    first_date_with_losses        ,
+                    what_if_label
+                FROM product_simulation_summary
+                WHERE what_if_label IS NULL
+                ORDER BY product_id, created_at DESC, id DESC
+            ) latest
+            ORDER BY expected_loss_cost DESC;
+        ",
+        );
+
+        let query_res = query.fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+
+    /// Returns the id generated for the new summary. Runs in the transaction of the
+    /// caller, which saves the details of the summary along.
+    pub async fn insert(
+        &self,
+        tx: &mut PgConnection,
+        summary: &NewProductSimulationSummary,
+    ) -> Result<(Duration, i32), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_scalar::<_, i32>(
+            "
+            INSERT INTO product_simulation_summary (
+                product_id                    ,
+                probability_losses_by_missing ,
+                probability_losses_by_nospace ,
+                probability_losses_by_expirat ,
+                probability_losses_by_rejection,
+                probability_no_losses         ,
+                expected_loss_cost            ,
+                start_date                    ,
+                end_date                      ,
+                first_date_with_losses        ,
+                what_if_label
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id;
+        ",
+        );
+
+        let query_res = query
+            .bind(summary.product_id)
+            .bind(&summary.probability_losses_by_missing)
+            .bind(&summary.probability_losses_by_nospace)
+            .bind(&summary.probability_losses_by_expirat)
+            .bind(&summary.probability_losses_by_rejection)
+            .bind(&summary.probability_no_losses)
+            .bind(&summary.expected_loss_cost)
+            .bind(summary.start_date)
+            .bind(summary.end_date)
+            .bind(summary.first_date_with_losses)
+            .bind(&summary.what_if_label)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +217,17 @@ mod tests {
         eprintln!("Query took: {:?}, result: {:?}", elapsed, products);
     }
 
+    #[tokio::test]
+    async fn find_latest_ranked_by_expected_loss_cost() {
+        let repo = get_db_repo().await;
+        let result = repo.find_latest_ranked_by_expected_loss_cost().await;
+        let (elapsed, products) = result.unwrap();
+        assert!(products
+            .windows(2)
+            .all(|pair| pair[0].expected_loss_cost >= pair[1].expected_loss_cost));
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, products);
+    }
+
     async fn get_db_repo() -> ProductSimulationSummaryRepository {
         let database_url = env::var("DATABASE_URL").unwrap();
         eprintln!("DATABASE_URL: {:?}", database_url);
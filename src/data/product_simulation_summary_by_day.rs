@@ -1,23 +1,43 @@
 use chrono::NaiveDate;
-use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use sqlx::{types::BigDecimal, FromRow, PgConnection, Pool, Postgres};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, FromRow, Clone)]
 pub struct NewProductSimulationSummaryByDay {
-    pub date: NaiveDate,                           // DATE NOT NULL,
-    pub probability_losses_by_missing: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(3,3) NOT NULL,
-                                                   //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    pub date: NaiveDate,                             // DATE NOT NULL,
+    pub probability_losses_by_missing: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal,   // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0,
+    pub average_backlog_quantity: BigDecimal,        // NUMERIC NOT NULL DEFAULT 0,
+    pub average_backlog_age_days: BigDecimal,        // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p5: BigDecimal,               // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p25: BigDecimal,              // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p50: BigDecimal,              // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p75: BigDecimal,              // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p95: BigDecimal,              // NUMERIC NOT NULL DEFAULT 0,
+    pub expected_loss_cost: BigDecimal,              // NUMERIC NOT NULL DEFAULT 0,
+    pub loss_cost_p95: BigDecimal,                   // NUMERIC NOT NULL DEFAULT 0,
+                                                     //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 }
 
 #[derive(Debug, FromRow, Clone)]
 pub struct ProductSimulationSummaryByDay {
     pub product_simulation_summary_id: i32, // INTEGER NOT NULL,
     pub date: NaiveDate,                    // DATE NOT NULL,
-    pub probability_losses_by_missing: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(3,3) NOT NULL,
-    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(3,3) NOT NULL,
+    pub probability_losses_by_missing: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_nospace: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_expirat: BigDecimal, // DECIMAL(4,3) NOT NULL,
+    pub probability_losses_by_rejection: BigDecimal, // DECIMAL(4,3) NOT NULL DEFAULT 0,
+    pub average_backlog_quantity: BigDecimal, // NUMERIC NOT NULL DEFAULT 0,
+    pub average_backlog_age_days: BigDecimal, // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p5: BigDecimal,      // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p25: BigDecimal,     // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p50: BigDecimal,     // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p75: BigDecimal,     // NUMERIC NOT NULL DEFAULT 0,
+    pub stock_quantity_p95: BigDecimal,     // NUMERIC NOT NULL DEFAULT 0,
+    pub expected_loss_cost: BigDecimal,     // NUMERIC NOT NULL DEFAULT 0,
+    pub loss_cost_p95: BigDecimal,          // NUMERIC NOT NULL DEFAULT 0,
                                             //pub created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 }
 
@@ -43,7 +63,17 @@ impl ProductSimulationSummaryByDayRepository {
                 date                          ,
                 probability_losses_by_missing ,
                 probability_losses_by_nospace ,
-                probability_losses_by_expirat
+                probability_losses_by_expirat ,
+                probability_losses_by_rejection,
+                average_backlog_quantity      ,
+                average_backlog_age_days      ,
+                stock_quantity_p5             ,
+                stock_quantity_p25            ,
+                stock_quantity_p50            ,
+                stock_quantity_p75            ,
+                stock_quantity_p95            ,
+                expected_loss_cost            ,
+                loss_cost_p95
             FROM product_simulation_summary_by_day
             WHERE product_simulation_summary_id = $1;
         ",
@@ -56,6 +86,58 @@ impl ProductSimulationSummaryByDayRepository {
 
         Ok((timer.elapsed(), query_res))
     }
+
+    pub async fn insert_all(
+        &self,
+        tx: &mut PgConnection,
+        product_simulation_summary_id: i32,
+        summaries: &[NewProductSimulationSummaryByDay],
+    ) -> Result<Duration, Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        for summary in summaries {
+            sqlx::query(
+                "
+                INSERT INTO product_simulation_summary_by_day (
+                    product_simulation_summary_id ,
+                    date                          ,
+                    probability_losses_by_missing ,
+                    probability_losses_by_nospace ,
+                    probability_losses_by_expirat ,
+                    probability_losses_by_rejection,
+                    average_backlog_quantity      ,
+                    average_backlog_age_days      ,
+                    stock_quantity_p5             ,
+                    stock_quantity_p25            ,
+                    stock_quantity_p50            ,
+                    stock_quantity_p75            ,
+                    stock_quantity_p95            ,
+                    expected_loss_cost            ,
+                    loss_cost_p95
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15);
+            ",
+            )
+            .bind(product_simulation_summary_id)
+            .bind(summary.date)
+            .bind(&summary.probability_losses_by_missing)
+            .bind(&summary.probability_losses_by_nospace)
+            .bind(&summary.probability_losses_by_expirat)
+            .bind(&summary.probability_losses_by_rejection)
+            .bind(&summary.average_backlog_quantity)
+            .bind(&summary.average_backlog_age_days)
+            .bind(&summary.stock_quantity_p5)
+            .bind(&summary.stock_quantity_p25)
+            .bind(&summary.stock_quantity_p50)
+            .bind(&summary.stock_quantity_p75)
+            .bind(&summary.stock_quantity_p95)
+            .bind(&summary.expected_loss_cost)
+            .bind(&summary.loss_cost_p95)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        Ok(timer.elapsed())
+    }
 }
 
 #[cfg(test)]
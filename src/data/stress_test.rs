@@ -0,0 +1,80 @@
+use sqlx::{types::BigDecimal, FromRow, Pool, Postgres};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, FromRow, Clone)]
+pub struct StressTest {
+    //pub id                            : i32, // SERIAL PRIMARY KEY,
+    pub name: String,               // TEXT NOT NULL UNIQUE,
+    pub kind: String,               // TEXT NOT NULL,
+    pub start_offset_days: i16,     // SMALLINT NOT NULL DEFAULT 0,
+    pub duration_days: Option<i16>, // SMALLINT,
+    pub factor: BigDecimal,         // NUMERIC NOT NULL,
+                                    //pub active                      : bool, // BOOLEAN NOT NULL DEFAULT TRUE,
+                                    //pub created_at                    : , // TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+}
+
+pub struct StressTestRepository {
+    db: Pool<Postgres>,
+}
+
+impl StressTestRepository {
+    pub fn new(db: Pool<Postgres>) -> StressTestRepository {
+        StressTestRepository { db }
+    }
+
+    pub async fn find_all_by_status(
+        &self,
+        active: bool,
+    ) -> Result<(Duration, Vec<StressTest>), Box<dyn std::error::Error>> {
+        let timer = Instant::now();
+
+        let query = sqlx::query_as::<_, StressTest>(
+            "
+            SELECT
+                name              ,
+                kind              ,
+                start_offset_days ,
+                duration_days     ,
+                factor
+            FROM stress_test
+            WHERE active = $1
+            ORDER BY id;
+        ",
+        );
+
+        let query_res = query.bind(active).fetch_all(&self.db).await?;
+
+        Ok((timer.elapsed(), query_res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use sqlx::postgres::PgPoolOptions;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_all_by_status() {
+        let repo = get_db_repo().await;
+        let result = repo.find_all_by_status(true).await;
+        let (elapsed, stress_tests) = result.unwrap();
+        // The stress tests seeded by the migration.
+        assert_eq!(stress_tests.len(), 3);
+        assert_eq!(stress_tests[2].name, "capacity halved");
+        eprintln!("Query took: {:?}, result: {:?}", elapsed, stress_tests);
+    }
+
+    async fn get_db_repo() -> StressTestRepository {
+        let database_url = env::var("DATABASE_URL").unwrap();
+        eprintln!("DATABASE_URL: {:?}", database_url);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url)
+            .await
+            .unwrap();
+        StressTestRepository::new(pool)
+    }
+}
@@ -1,19 +1,132 @@
 mod data;
 mod simulation;
 
-use std::str::FromStr;
+use std::{env, str::FromStr};
 
+use simulation::coordinator::{ShelfLifeRange, WhatIfOverrides};
 use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reference_date = "2022-01-01T00:00:00Z";
     let product_id = Uuid::from_str("d0bd335e-fc46-408d-90fb-209ccc521fa1")?;
+    let command = env::args().nth(1).unwrap_or("simulate".to_owned());
 
     let sim_coordinator = simulation::coordinator::Orchestrator::new().await?;
-    sim_coordinator
-        .run_by_product(product_id, reference_date)
-        .await?;
+    match command.as_str() {
+        "simulate" => {
+            sim_coordinator
+                .run_by_product(product_id, reference_date)
+                .await?
+        }
+        "report" => {
+            let report = sim_coordinator.report_by_product(product_id).await?;
+            println!("{:?}", report.summary);
+            for day in &report.by_day {
+                println!("{:?}", day);
+            }
+            for kpi in &report.kpis {
+                println!("{:?}", kpi);
+            }
+            for first_loss in &report.first_losses {
+                println!("{:?}", first_loss);
+            }
+            for stress_result in &report.stress_results {
+                println!("{:?}", stress_result);
+            }
+            println!("{:?}", report.data_quality);
+        }
+        "rank-loss-cost" => {
+            for summary in sim_coordinator.rank_by_expected_loss_cost().await? {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    summary.product_id,
+                    summary.expected_loss_cost,
+                    summary.start_date,
+                    summary.end_date
+                );
+            }
+        }
+        "recommend-replenishment" => {
+            sim_coordinator
+                .recommend_replenishment(reference_date)
+                .await?
+        }
+        "optimize-capacity" => sim_coordinator.optimize_capacity(reference_date).await?,
+        "backtest-calibration" => {
+            let args = env::args()
+                .skip(2)
+                .map(|arg| arg.parse::<u64>())
+                .collect::<Result<Vec<u64>, _>>()?;
+            sim_coordinator
+                .backtest_calibration(reference_date, args.first().copied(), args.get(1).copied())
+                .await?
+        }
+        "calibrate-random-range" => {
+            sim_coordinator
+                .calibrate_random_range(reference_date)
+                .await?
+        }
+        "select-forecast-model" => {
+            sim_coordinator
+                .select_forecast_models(reference_date)
+                .await?
+        }
+        "shelf-life-sensitivity" => {
+            sim_coordinator
+                .analyze_shelf_life_by_product(
+                    product_id,
+                    reference_date,
+                    parse_shelf_life_range()?,
+                )
+                .await?
+        }
+        "sweep" => {
+            let axes = env::args()
+                .nth(2)
+                .ok_or("Expected <axes> [samples], e.g. random_range_factor=0.05,0.1,0.2")?;
+            let samples = env::args()
+                .nth(3)
+                .map(|arg| arg.parse::<u64>())
+                .transpose()?;
+            sim_coordinator
+                .sweep_parameters_by_product(product_id, reference_date, &axes, samples)
+                .await?
+        }
+        "what-if" => {
+            let overrides = env::args()
+                .nth(2)
+                .ok_or("Expected <overrides> [label], e.g. stock_maximum_quantity=300")?;
+            let what_if_label = env::args().nth(3);
+            let summary = sim_coordinator
+                .run_what_if_by_product(
+                    product_id,
+                    reference_date,
+                    WhatIfOverrides::from_str(&overrides)?,
+                    what_if_label.as_deref(),
+                )
+                .await?;
+            println!("{:?}", summary.summary);
+        }
+        other => return Err(format!("Unknown command: {:?}", other).into()),
+    }
 
     Ok(())
 }
+
+/// Optional `<min_days> <max_days> <step_days>` arguments after the command.
+fn parse_shelf_life_range() -> Result<Option<ShelfLifeRange>, Box<dyn std::error::Error>> {
+    let days = env::args()
+        .skip(2)
+        .map(|arg| arg.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>()?;
+    match days[..] {
+        [] => Ok(None),
+        [min_days, max_days, step_days] => Ok(Some(ShelfLifeRange {
+            min_days,
+            max_days,
+            step_days,
+        })),
+        _ => Err("Expected <min_days> <max_days> <step_days>".into()),
+    }
+}
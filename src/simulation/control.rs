@@ -1,19 +1,61 @@
-mod parameter;
+pub(crate) mod analog;
+pub(crate) mod calibration;
+pub(crate) mod capacity;
+pub(crate) mod cost;
+mod first_loss;
+pub(crate) mod forecast;
+mod kpi;
+pub(crate) mod model_selection;
+pub(crate) mod outlier;
+pub(crate) mod parameter;
 mod per_day;
+pub(crate) mod range_factor;
+pub(crate) mod regime;
+pub(crate) mod replenishment;
+pub(crate) mod shelf_life;
+pub(crate) mod statistics;
+pub(crate) mod stress;
+pub(crate) mod sweep;
+pub(crate) mod trend;
+pub(crate) mod what_if;
 
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::data::product_simulation_data_quality::NewProductSimulationDataQuality;
+use crate::data::product_simulation_first_loss::NewProductSimulationFirstLoss;
+use crate::data::product_simulation_kpi::NewProductSimulationKpi;
+use crate::data::product_simulation_stress_result::NewProductSimulationStressResult;
+use crate::data::product_simulation_summary::NewProductSimulationSummary;
 use crate::data::product_simulation_summary_by_day::NewProductSimulationSummaryByDay;
 use crate::data::{product_batch::ProductBatch, product_mov_hist::ProductMovHist};
 
-use crate::simulation::control::{parameter::SimulationParameters, per_day::SimulationDay};
+use crate::simulation::control::{
+    cost::LossCost,
+    first_loss::FirstLossCounter,
+    kpi::KpiCounter,
+    parameter::{DateHistSource, SimulationParameters},
+    per_day::SimulationDay,
+    statistics::{percentile, sorted},
+};
 
-use chrono::{DateTime, Utc};
+use bigdecimal::{FromPrimitive, ToPrimitive};
+
+use chrono::{DateTime, Days, TimeDelta, Utc};
 
 use sqlx::types::BigDecimal;
 use uuid::Uuid;
 
+pub(crate) struct SimulationSummary {
+    pub(crate) summary: NewProductSimulationSummary,
+    pub(crate) by_day: Vec<NewProductSimulationSummaryByDay>,
+    pub(crate) kpis: Vec<NewProductSimulationKpi>,
+    pub(crate) first_losses: Vec<NewProductSimulationFirstLoss>,
+    /// Filled by the stress tests, when run.
+    pub(crate) stress_results: Vec<NewProductSimulationStressResult>,
+    pub(crate) data_quality: NewProductSimulationDataQuality,
+}
+
 pub(crate) struct SimulationControl {
     pub(crate) product_id: Uuid,
     pub(crate) first_day: SimulationDay,
@@ -41,7 +83,14 @@ impl SimulationControl {
             batches: product_batches,
             stock_time_limit_exceeded: None,
             stock_shortage: None,
+            backordered: None,
             stock_limit_exceeded: None,
+            qc_rejected: None,
+            backlog: Vec::new(),
+            pending_orders: Vec::new(),
+            demand_qty: BigDecimal::from(0),
+            withdrawn_qty: BigDecimal::from(0),
+            withdrawn_age_days: 0.0,
             is_calculated: false,
         };
         SimulationControl {
@@ -52,20 +101,33 @@ impl SimulationControl {
         }
     }
 
-    pub(crate) fn has_next_date(&self, days: &Vec<SimulationDay>) -> bool {
-        days.last()
-            .is_some_and(|day| day.is_calculated && day.date < self.final_date)
+    /// The final date is simulated up to its last step.
+    pub(crate) fn has_next_date(&self, days: &[SimulationDay]) -> bool {
+        let step = TimeDelta::hours(self.sim_param.time_step_hours as i64);
+        days.last().is_some_and(|day| {
+            day.is_calculated
+                && self
+                    .final_date
+                    .checked_add_days(Days::new(1))
+                    .is_some_and(|end| day.date + step < end)
+        })
     }
 
     pub(crate) fn run_once(&self) -> Vec<SimulationDay> {
+        let sim_param = &self.sim_param;
+        let draws = sim_param.draw_run(
+            self.first_day.date.date_naive(),
+            self.final_date.date_naive(),
+            &mut rand::thread_rng(),
+        );
         let mut first_day = self.first_day.clone();
-        let mut is_last_calculated = first_day.calculate(&self.sim_param);
+        let mut is_last_calculated = first_day.calculate(sim_param, &draws);
         let mut days = vec![first_day];
         while is_last_calculated && self.has_next_date(&days) {
             is_last_calculated = days
                 .last()
-                .and_then(|last_day| last_day.create_next())
-                .map(|mut next_day| (next_day.calculate(&self.sim_param), next_day))
+                .and_then(|last_day| last_day.create_next(sim_param.time_step_hours))
+                .map(|mut next_day| (next_day.calculate(sim_param, &draws), next_day))
                 .map(|(is_calculated, next_day)| {
                     if is_calculated {
                         days.push(next_day)
@@ -79,10 +141,19 @@ impl SimulationControl {
         days
     }
 
-    pub(crate) fn run_n_times(&self, n_times: u64) {
+    pub(crate) fn run_n_times(
+        &self,
+        n_times: u64,
+    ) -> Result<SimulationSummary, Box<dyn std::error::Error>> {
         let mut group_by_date: HashMap<DateTime<Utc>, SimulationDayCounter> = HashMap::new();
+        let mut summary_counter = SimulationSummaryCounter::default();
+        let mut kpi_counter = KpiCounter::default();
+        let mut first_loss_counter = FirstLossCounter::new(self.first_day.date);
         for _n in 0..n_times {
-            let days: Vec<SimulationDay> = self.run_once();
+            let days: Vec<SimulationDay> = SimulationDay::roll_up_daily(self.run_once());
+            summary_counter.add(&days, &self.sim_param.loss_cost);
+            kpi_counter.add(&days, &self.sim_param.loss_cost);
+            first_loss_counter.add(&days);
             for day in days {
                 group_by_date
                     .entry(day.date)
@@ -90,11 +161,132 @@ impl SimulationControl {
                     .add(day);
             }
         }
-        let daily_summaries_map: HashMap<DateTime<Utc>, Option<NewProductSimulationSummaryByDay>> =
-            group_by_date
-                .into_iter()
-                .map(|(date, counter)| (date, counter.summarize()))
-                .collect();
+        let mut by_day: Vec<NewProductSimulationSummaryByDay> = group_by_date
+            .into_values()
+            .filter_map(|counter| counter.summarize(&self.sim_param.loss_cost))
+            .collect();
+        by_day.sort_by_key(|day| day.date);
+        let summary = summary_counter.summarize(
+            self.product_id,
+            self.first_day.date,
+            self.final_date,
+            &by_day,
+        )?;
+        Ok(SimulationSummary {
+            summary,
+            by_day,
+            kpis: kpi_counter.summarize()?,
+            first_losses: first_loss_counter.summarize()?,
+            stress_results: Vec::new(),
+            data_quality: self.summarize_data_quality()?,
+        })
+    }
+
+    /// Counts the simulated days by the source of their expected movements.
+    fn summarize_data_quality(
+        &self,
+    ) -> Result<NewProductSimulationDataQuality, Box<dyn std::error::Error>> {
+        let mut days_by_source: HashMap<DateHistSource, i32> = HashMap::new();
+        let mut date = self.first_day.date;
+        while date.date_naive() <= self.final_date.date_naive() {
+            *days_by_source
+                .entry(self.sim_param.get_date_hist_source(&date))
+                .or_default() += 1;
+            date = date.checked_add_days(Days::new(1)).ok_or("Invalid date")?;
+        }
+        let days_of = |source| days_by_source.get(&source).copied().unwrap_or(0);
+        Ok(NewProductSimulationDataQuality {
+            history_imputation: self.sim_param.get_history_imputation().name().to_owned(),
+            simulated_days: days_by_source.values().sum(),
+            forecast_days: days_of(DateHistSource::Forecast),
+            imputed_days: days_of(DateHistSource::Imputed),
+        })
+    }
+}
+
+/// Counts the runs with at least one loss of each type over the whole period.
+#[derive(Default)]
+struct SimulationSummaryCounter {
+    runs: usize,
+    with_losses_by_missing: usize,
+    with_losses_by_nospace: usize,
+    with_losses_by_expirat: usize,
+    with_losses_by_rejection: usize,
+    without_losses: usize,
+    total_loss_cost: f64,
+}
+
+impl SimulationSummaryCounter {
+    fn add(&mut self, days: &[SimulationDay], loss_cost: &LossCost) {
+        self.runs += 1;
+        self.total_loss_cost += loss_cost.of_days(days);
+        if days.iter().any(|day| day.stock_shortage.is_some()) {
+            self.with_losses_by_missing += 1;
+        }
+        if days.iter().any(|day| day.stock_limit_exceeded.is_some()) {
+            self.with_losses_by_nospace += 1;
+        }
+        if days
+            .iter()
+            .any(|day| day.stock_time_limit_exceeded.is_some())
+        {
+            self.with_losses_by_expirat += 1;
+        }
+        if days.iter().any(|day| day.qc_rejected.is_some()) {
+            self.with_losses_by_rejection += 1;
+        }
+        if days.iter().all(|day| {
+            day.stock_shortage.is_none()
+                && day.stock_limit_exceeded.is_none()
+                && day.stock_time_limit_exceeded.is_none()
+                && day.qc_rejected.is_none()
+        }) {
+            self.without_losses += 1;
+        }
+    }
+
+    fn probability(&self, count: usize) -> Result<BigDecimal, Box<dyn std::error::Error>> {
+        if self.runs == 0 {
+            return Err("No runs. Division by zero is not allowed!"
+                .to_owned()
+                .into());
+        }
+        Ok(BigDecimal::from_str(
+            &(count as f64 / self.runs as f64).to_string(),
+        )?)
+    }
+
+    fn summarize(
+        &self,
+        product_id: Uuid,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        by_day: &[NewProductSimulationSummaryByDay],
+    ) -> Result<NewProductSimulationSummary, Box<dyn std::error::Error>> {
+        let zero = BigDecimal::from(0);
+        let first_date_with_losses = by_day
+            .iter()
+            .find(|day| {
+                day.probability_losses_by_missing > zero
+                    || day.probability_losses_by_nospace > zero
+                    || day.probability_losses_by_expirat > zero
+                    || day.probability_losses_by_rejection > zero
+            })
+            .map(|day| day.date);
+        Ok(NewProductSimulationSummary {
+            product_id,
+            probability_losses_by_missing: self.probability(self.with_losses_by_missing)?,
+            probability_losses_by_nospace: self.probability(self.with_losses_by_nospace)?,
+            probability_losses_by_expirat: self.probability(self.with_losses_by_expirat)?,
+            probability_losses_by_rejection: self.probability(self.with_losses_by_rejection)?,
+            probability_no_losses: self.probability(self.without_losses)?,
+            expected_loss_cost: BigDecimal::from_f64(self.total_loss_cost / self.runs as f64)
+                .ok_or("Invalid expected loss cost")?,
+            start_date: start_date.date_naive(),
+            end_date: end_date.date_naive(),
+            first_date_with_losses,
+            what_if_label: None,
+        })
     }
 }
 
@@ -104,6 +296,7 @@ struct SimulationDayCounter {
     with_losses_by_missing: Vec<usize>, // Vec<&'a SimulationDay>,
     with_losses_by_nospace: Vec<usize>, // Vec<&'a SimulationDay>,
     with_losses_by_expirat: Vec<usize>, // Vec<&'a SimulationDay>,
+    with_losses_by_rejection: Vec<usize>,
 }
 
 impl SimulationDayCounter {
@@ -114,6 +307,7 @@ impl SimulationDayCounter {
             with_losses_by_missing: Vec::new(),
             with_losses_by_nospace: Vec::new(),
             with_losses_by_expirat: Vec::new(),
+            with_losses_by_rejection: Vec::new(),
         }
     }
 
@@ -132,11 +326,14 @@ impl SimulationDayCounter {
             self.with_losses_by_expirat.push(i);
             // self.with_losses_by_expirat.push(&self.all[i]);
         }
+        if self.all[i].qc_rejected.is_some() {
+            self.with_losses_by_rejection.push(i);
+        }
     }
 
-    fn summarize(&self) -> Option<NewProductSimulationSummaryByDay> {
+    fn summarize(&self, loss_cost: &LossCost) -> Option<NewProductSimulationSummaryByDay> {
         //TODO improve error handling
-        match self.try_summarize() {
+        match self.try_summarize(loss_cost) {
             Ok(s) => Some(s),
             Err(err) => {
                 eprintln!(
@@ -150,12 +347,20 @@ impl SimulationDayCounter {
 
     fn try_summarize(
         &self,
+        loss_cost: &LossCost,
     ) -> Result<NewProductSimulationSummaryByDay, Box<dyn std::error::Error>> {
         if self.all.len() == 0 {
             return Err("Empty vec. Division by zero is not allowed!"
                 .to_owned()
                 .into());
         }
+        let stock_qty_values = sorted(
+            self.all
+                .iter()
+                .filter_map(|day| day.stock_qty().to_f64())
+                .collect(),
+        );
+        let loss_cost_values = sorted(self.all.iter().map(|day| loss_cost.of_day(day)).collect());
         Ok(NewProductSimulationSummaryByDay {
             date: self.date.date_naive(),
             probability_losses_by_missing: BigDecimal::from_str(
@@ -167,16 +372,66 @@ impl SimulationDayCounter {
             probability_losses_by_expirat: BigDecimal::from_str(
                 &(self.with_losses_by_expirat.len() as f64 / self.all.len() as f64).to_string(),
             )?,
+            probability_losses_by_rejection: BigDecimal::from_str(
+                &(self.with_losses_by_rejection.len() as f64 / self.all.len() as f64).to_string(),
+            )?,
+            average_backlog_quantity: self
+                .all
+                .iter()
+                .fold(BigDecimal::from(0), |acc, day| acc + day.backlog_qty())
+                / BigDecimal::from(self.all.len() as u64),
+            average_backlog_age_days: BigDecimal::from_str(
+                &(self
+                    .all
+                    .iter()
+                    .map(|day| day.backlog_age_days())
+                    .sum::<f64>()
+                    / self.all.len() as f64)
+                    .to_string(),
+            )?,
+            stock_quantity_p5: self.stock_qty_percentile(&stock_qty_values, 5.0)?,
+            stock_quantity_p25: self.stock_qty_percentile(&stock_qty_values, 25.0)?,
+            stock_quantity_p50: self.stock_qty_percentile(&stock_qty_values, 50.0)?,
+            stock_quantity_p75: self.stock_qty_percentile(&stock_qty_values, 75.0)?,
+            stock_quantity_p95: self.stock_qty_percentile(&stock_qty_values, 95.0)?,
+            expected_loss_cost: BigDecimal::from_f64(
+                loss_cost_values.iter().sum::<f64>() / self.all.len() as f64,
+            )
+            .ok_or("Invalid expected loss cost")?,
+            loss_cost_p95: percentile(&loss_cost_values, 95.0)
+                .and_then(BigDecimal::from_f64)
+                .ok_or("Invalid loss cost percentile p95")?,
         })
     }
+
+    fn stock_qty_percentile(
+        &self,
+        stock_qty_values: &[f64],
+        p: f64,
+    ) -> Result<BigDecimal, Box<dyn std::error::Error>> {
+        percentile(stock_qty_values, p)
+            .and_then(BigDecimal::from_f64)
+            .ok_or(format!("Invalid stock quantity percentile p{}", p).into())
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use bigdecimal::Signed;
     use sqlx::types::BigDecimal;
 
     use super::*;
+    use crate::simulation::control::capacity::CapacitySearch;
+    use crate::simulation::control::kpi::RunKpis;
+    use crate::simulation::control::parameter::{
+        DayEvent, ExpirationComparison, ReplenishmentPolicy, ShortageMode, StressShock,
+    };
+    use crate::simulation::control::replenishment::ReplenishmentSearch;
+    use crate::simulation::control::shelf_life::{ShelfLifeRange, ShelfLifeSensitivity};
+    use crate::simulation::control::stress::{StressKind, StressTest, StressTestSuite};
+    use crate::simulation::control::sweep::{ParameterSweep, SweepAxis, SweepDesign};
+    use crate::simulation::control::what_if::WhatIfOverrides;
 
     #[test]
     fn should_finish_with_batch_len_10_and_batches_qty_sum_100() {
@@ -192,16 +447,16 @@ mod tests {
             11,
             mock_product_batches(),
             vec![
-                mock_historic(10, 10, 0, 1), // 2024-01-01 mon
-                mock_historic(10, 10, 0, 2), // 2024-01-02 tur
-                mock_historic(10, 10, 0, 3), // 2024-01-03 wed
-                mock_historic(10, 10, 0, 4), // 2024-01-04 thu
-                mock_historic(10, 10, 0, 5), // 2024-01-05 fry
-                mock_historic(10, 10, 0, 6), // 2024-01-06 sat
-                mock_historic(10, 10, 0, 0), // 2024-01-07 sun
-                mock_historic(10, 10, 1, 1), // 2024-01-08 mon
-                mock_historic(10, 10, 1, 2), // 2024-01-09 tur
-                mock_historic(10, 10, 1, 3), // 2024-01-10 wed
+                mock_historic(10, 10, 1, 1), // 2024-01-01 mon
+                mock_historic(10, 10, 1, 2), // 2024-01-02 tur
+                mock_historic(10, 10, 1, 3), // 2024-01-03 wed
+                mock_historic(10, 10, 1, 4), // 2024-01-04 thu
+                mock_historic(10, 10, 1, 5), // 2024-01-05 fry
+                mock_historic(10, 10, 1, 6), // 2024-01-06 sat
+                mock_historic(10, 10, 1, 0), // 2024-01-07 sun
+                mock_historic(10, 10, 2, 1), // 2024-01-08 mon
+                mock_historic(10, 10, 2, 2), // 2024-01-09 tur
+                mock_historic(10, 10, 2, 3), // 2024-01-10 wed
             ],
         );
 
@@ -231,16 +486,16 @@ mod tests {
             5,
             mock_product_batches(),
             vec![
-                mock_historic(10, 10, 0, 0), // 2024-01-01 mon
-                mock_historic(10, 10, 0, 1), // 2024-01-02 tur
-                mock_historic(10, 10, 0, 2), // 2024-01-03 wed
-                mock_historic(10, 10, 0, 3), // 2024-01-04 thu
-                mock_historic(10, 10, 0, 4), // 2024-01-05 fry
-                mock_historic(10, 10, 0, 5), // 2024-01-06 sat
-                mock_historic(10, 10, 0, 6), // 2024-01-07 sun
-                mock_historic(10, 10, 1, 1), // 2024-01-08 mon
-                mock_historic(10, 10, 1, 2), // 2024-01-09 tur
-                mock_historic(10, 10, 1, 3), // 2024-01-10 wed
+                mock_historic(10, 10, 1, 0), // 2024-01-01 mon
+                mock_historic(10, 10, 1, 1), // 2024-01-02 tur
+                mock_historic(10, 10, 1, 2), // 2024-01-03 wed
+                mock_historic(10, 10, 1, 3), // 2024-01-04 thu
+                mock_historic(10, 10, 1, 4), // 2024-01-05 fry
+                mock_historic(10, 10, 1, 5), // 2024-01-06 sat
+                mock_historic(10, 10, 1, 6), // 2024-01-07 sun
+                mock_historic(10, 10, 2, 1), // 2024-01-08 mon
+                mock_historic(10, 10, 2, 2), // 2024-01-09 tur
+                mock_historic(10, 10, 2, 3), // 2024-01-10 wed
             ],
         );
 
@@ -270,16 +525,16 @@ mod tests {
             5,
             mock_product_batches(),
             vec![
-                mock_historic(10, 5, 0, 0), // 2024-01-01 mon
-                mock_historic(10, 5, 0, 1), // 2024-01-02 tur
-                mock_historic(10, 5, 0, 2), // 2024-01-03 wed
-                mock_historic(10, 5, 0, 3), // 2024-01-04 thu
-                mock_historic(10, 5, 0, 4), // 2024-01-05 fry
-                mock_historic(10, 5, 0, 5), // 2024-01-06 sat
-                mock_historic(10, 5, 0, 6), // 2024-01-07 sun
-                mock_historic(10, 5, 1, 1), // 2024-01-08 mon
-                mock_historic(10, 5, 1, 2), // 2024-01-09 tur
-                mock_historic(10, 5, 1, 3), // 2024-01-10 wed
+                mock_historic(10, 5, 1, 0), // 2024-01-01 mon
+                mock_historic(10, 5, 1, 1), // 2024-01-02 tur
+                mock_historic(10, 5, 1, 2), // 2024-01-03 wed
+                mock_historic(10, 5, 1, 3), // 2024-01-04 thu
+                mock_historic(10, 5, 1, 4), // 2024-01-05 fry
+                mock_historic(10, 5, 1, 5), // 2024-01-06 sat
+                mock_historic(10, 5, 1, 6), // 2024-01-07 sun
+                mock_historic(10, 5, 2, 1), // 2024-01-08 mon
+                mock_historic(10, 5, 2, 2), // 2024-01-09 tur
+                mock_historic(10, 5, 2, 3), // 2024-01-10 wed
             ],
         );
 
@@ -309,16 +564,16 @@ mod tests {
             5,
             mock_product_batches(),
             vec![
-                mock_historic(5, 10, 0, 0), // 2024-01-01 mon
-                mock_historic(5, 10, 0, 1), // 2024-01-02 tur
-                mock_historic(5, 10, 0, 2), // 2024-01-03 wed
-                mock_historic(5, 10, 0, 3), // 2024-01-04 thu
-                mock_historic(5, 10, 0, 4), // 2024-01-05 fry
-                mock_historic(5, 10, 0, 5), // 2024-01-06 sat
-                mock_historic(5, 10, 0, 6), // 2024-01-07 sun
-                mock_historic(5, 10, 1, 1), // 2024-01-08 mon
-                mock_historic(5, 10, 1, 2), // 2024-01-09 tur
-                mock_historic(5, 10, 1, 3), // 2024-01-10 wed
+                mock_historic(5, 10, 1, 0), // 2024-01-01 mon
+                mock_historic(5, 10, 1, 1), // 2024-01-02 tur
+                mock_historic(5, 10, 1, 2), // 2024-01-03 wed
+                mock_historic(5, 10, 1, 3), // 2024-01-04 thu
+                mock_historic(5, 10, 1, 4), // 2024-01-05 fry
+                mock_historic(5, 10, 1, 5), // 2024-01-06 sat
+                mock_historic(5, 10, 1, 6), // 2024-01-07 sun
+                mock_historic(5, 10, 2, 1), // 2024-01-08 mon
+                mock_historic(5, 10, 2, 2), // 2024-01-09 tur
+                mock_historic(5, 10, 2, 3), // 2024-01-10 wed
             ],
         );
 
@@ -334,6 +589,847 @@ mod tests {
         assert_eq!(total_qty, BigDecimal::from(30));
     }
 
+    #[test]
+    fn should_remove_expired_batch_before_withdraw_when_configured() {
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            date,
+            date,
+            1000,
+            5,
+            vec![ProductBatch {
+                deadline_date: date,
+                ..mock_product_batches().remove(0)
+            }],
+            vec![mock_historic(10, 10, 1, 1)],
+        );
+
+        let days = simulation.run_once();
+        let day = days.last().unwrap();
+        assert_eq!(day.batches.len(), 2);
+        assert_eq!(day.stock_shortage, None);
+        assert_eq!(day.stock_time_limit_exceeded, None);
+
+        simulation.sim_param.day_events_order =
+            vec![DayEvent::RmExpired, DayEvent::Withdraw, DayEvent::Entry];
+        simulation.sim_param.expiration_comparison = ExpirationComparison::Inclusive;
+
+        let days = simulation.run_once();
+        let day = days.last().unwrap();
+        assert_eq!(day.batches.len(), 1);
+        assert_eq!(day.batches[0].quantity, BigDecimal::from(10));
+        assert_eq!(day.stock_shortage, Some(BigDecimal::from(10)));
+        assert_eq!(day.stock_time_limit_exceeded, Some(BigDecimal::from(100)));
+    }
+
+    #[test]
+    fn should_roll_up_sub_day_steps_to_the_daily_result() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-10T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(10, 10, 1, 1), // 2024-01-01 mon
+                mock_historic(10, 10, 1, 2), // 2024-01-02 tur
+                mock_historic(10, 10, 1, 3), // 2024-01-03 wed
+                mock_historic(10, 10, 1, 4), // 2024-01-04 thu
+                mock_historic(10, 10, 1, 5), // 2024-01-05 fry
+                mock_historic(10, 10, 1, 6), // 2024-01-06 sat
+                mock_historic(10, 10, 1, 0), // 2024-01-07 sun
+                mock_historic(10, 10, 2, 1), // 2024-01-08 mon
+                mock_historic(10, 10, 2, 2), // 2024-01-09 tur
+                mock_historic(10, 10, 2, 3), // 2024-01-10 wed
+            ],
+        );
+        simulation.sim_param.time_step_hours = 6;
+
+        let steps = simulation.run_once();
+        assert_eq!(steps.len(), 40);
+        assert_eq!(
+            steps.last().unwrap().date,
+            DateTime::parse_from_rfc3339("2024-01-10T18:00:00Z")
+                .unwrap()
+                .to_utc()
+        );
+
+        let days = SimulationDay::roll_up_daily(steps);
+        assert_eq!(days.len(), 10);
+        let total_qty = days
+            .last()
+            .unwrap()
+            .batches
+            .iter()
+            .map(|batch| batch.quantity.clone())
+            .reduce(|acc, batch_qty| acc + batch_qty)
+            .unwrap();
+        assert_eq!(total_qty, BigDecimal::from(100));
+    }
+
+    #[test]
+    fn should_not_withdraw_batches_on_qc_hold() {
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            date,
+            DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            100,
+            11,
+            vec![],
+            vec![
+                mock_historic(40, 0, 1, 1),  // 2024-01-01 mon
+                mock_historic(40, 30, 1, 2), // 2024-01-02 tur
+                mock_historic(0, 0, 1, 3),   // 2024-01-03 wed
+            ],
+        );
+        simulation.sim_param.qc_hold_days = 2;
+        simulation.sim_param.qc_rejection_rate = 0.5;
+
+        let days = simulation.run_once();
+        assert_eq!(days.len(), 3);
+        // held batches take up capacity but can't be withdrawn
+        assert_eq!(days[1].stock_shortage, Some(BigDecimal::from(30)));
+        assert_eq!(days[1].batches.len(), 2);
+        // the first batch is released on the third day, minus the rejected share
+        let released = &days[2].batches[0];
+        assert_eq!(released.qc_release_date, None);
+        let rejected = days[2].qc_rejected.clone().unwrap_or(BigDecimal::from(0));
+        assert_eq!(&released.quantity + &rejected, BigDecimal::from(40));
+        assert!(days[2].batches[1].qc_release_date.is_some());
+    }
+
+    #[test]
+    fn should_fill_backlog_from_the_next_entries_in_backorder_mode() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            vec![],
+            vec![
+                mock_historic(0, 30, 1, 1),  // 2024-01-01 mon
+                mock_historic(20, 10, 1, 2), // 2024-01-02 tur
+                mock_historic(50, 0, 1, 3),  // 2024-01-03 wed
+            ],
+        );
+        simulation.sim_param.shortage_mode = ShortageMode::Backorder;
+
+        let days = simulation.run_once();
+        assert_eq!(days[0].stock_shortage, None);
+        assert_eq!(days[0].backordered, Some(BigDecimal::from(30)));
+        assert_eq!(days[0].backlog_qty(), BigDecimal::from(30));
+        assert_eq!(days[1].backordered, Some(BigDecimal::from(10)));
+        assert_eq!(days[1].backlog_qty(), BigDecimal::from(20));
+        assert_eq!(days[1].backlog_age_days(), 1.0);
+        assert_eq!(days[2].backlog_qty(), BigDecimal::from(0));
+        assert_eq!(
+            days[2].batches.last().unwrap().quantity,
+            BigDecimal::from(30)
+        );
+
+        simulation.sim_param.shortage_mode = ShortageMode::LostSales;
+        let days = simulation.run_once();
+        assert_eq!(days[0].stock_shortage, Some(BigDecimal::from(30)));
+        assert_eq!(days[0].backordered, None);
+        assert_eq!(days[1].backlog_qty(), BigDecimal::from(0));
+        assert_eq!(
+            days[2].batches.last().unwrap().quantity,
+            BigDecimal::from(50)
+        );
+    }
+
+    #[test]
+    fn should_summarize_n_runs_by_day() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(10, 10, 1, 1),  // 2024-01-01 mon
+                mock_historic(10, 10, 1, 2),  // 2024-01-02 tur
+                mock_historic(10, 200, 1, 3), // 2024-01-03 wed
+            ],
+        );
+        simulation.sim_param.random_range_factor = 0.1;
+
+        let SimulationSummary {
+            summary,
+            by_day,
+            data_quality,
+            ..
+        } = simulation.run_n_times(20).unwrap();
+        assert_eq!(by_day.len(), 3);
+        assert_eq!(by_day[0].date.to_string(), "2024-01-01");
+        assert_eq!(by_day[0].probability_losses_by_missing, BigDecimal::from(0));
+        assert_eq!(by_day[2].probability_losses_by_missing, BigDecimal::from(1));
+        assert!(by_day[0].stock_quantity_p5 <= by_day[0].stock_quantity_p50);
+        assert!(by_day[0].stock_quantity_p50 <= by_day[0].stock_quantity_p95);
+        assert!(by_day[0].stock_quantity_p5.to_f64().unwrap() >= 98.0);
+        assert!(by_day[0].stock_quantity_p95.to_f64().unwrap() <= 102.0);
+        assert!(by_day[2].stock_quantity_p95.to_f64().unwrap() <= 11.0);
+
+        assert_eq!(summary.probability_losses_by_missing, BigDecimal::from(1));
+        assert_eq!(summary.probability_losses_by_expirat, BigDecimal::from(0));
+        assert_eq!(summary.start_date.to_string(), "2024-01-01");
+        assert_eq!(summary.end_date.to_string(), "2024-01-03");
+        assert_eq!(
+            summary.first_date_with_losses.map(|date| date.to_string()),
+            Some("2024-01-03".to_owned())
+        );
+
+        assert_eq!(data_quality.history_imputation, "zero");
+        assert_eq!(data_quality.simulated_days, 3);
+        assert_eq!(data_quality.forecast_days, 0);
+        assert_eq!(data_quality.imputed_days, 0);
+    }
+
+    #[test]
+    fn should_calculate_run_kpis() {
+        let simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(0, 30, 1, 1), // 2024-01-01 mon
+                mock_historic(0, 30, 1, 2), // 2024-01-02 tur
+                mock_historic(0, 30, 1, 3), // 2024-01-03 wed
+                mock_historic(0, 30, 1, 4), // 2024-01-04 thu
+            ],
+        );
+
+        let kpis = RunKpis::from_days(&simulation.run_once(), &simulation.sim_param.loss_cost);
+        assert_eq!(kpis.fill_rate, Some(100.0 / 120.0));
+        assert_eq!(kpis.cycle_service_level, Some(0.75));
+        assert_eq!(kpis.average_stock, Some(30.0));
+        assert_eq!(kpis.days_of_cover, Some(1.0));
+        assert_eq!(kpis.stock_turnover, Some(100.0 / 30.0));
+        assert_eq!(kpis.average_batch_age_at_withdrawal, Some(2.2));
+    }
+
+    #[test]
+    fn should_summarize_first_loss_dates() {
+        let simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(0, 30, 1, 1), // 2024-01-01 mon
+                mock_historic(0, 30, 1, 2), // 2024-01-02 tur
+                mock_historic(0, 30, 1, 3), // 2024-01-03 wed
+                mock_historic(0, 30, 1, 4), // 2024-01-04 thu
+            ],
+        );
+
+        let SimulationSummary {
+            summary,
+            first_losses,
+            ..
+        } = simulation.run_n_times(5).unwrap();
+        assert_eq!(summary.probability_no_losses, BigDecimal::from(0));
+        assert_eq!(first_losses.len(), 2);
+        for first_loss in first_losses {
+            assert!(["missing", "any"].contains(&first_loss.loss_type.as_str()));
+            assert_eq!(first_loss.runs, 5);
+            assert_eq!(first_loss.runs_with_loss, 5);
+            assert_eq!(first_loss.earliest_days, 3);
+            assert_eq!(first_loss.median_days, Some(3));
+            assert_eq!(first_loss.p90_days, Some(3));
+            assert_eq!(
+                first_loss.median_date.map(|date| date.to_string()),
+                Some("2024-01-04".to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn should_summarize_loss_cost() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(0, 30, 1, 1), // 2024-01-01 mon
+                mock_historic(0, 30, 1, 2), // 2024-01-02 tur
+                mock_historic(0, 30, 1, 3), // 2024-01-03 wed
+                mock_historic(0, 30, 1, 4), // 2024-01-04 thu
+            ],
+        );
+        simulation.sim_param.loss_cost = LossCost {
+            unit_cost: 5.0,
+            lost_margin_per_unit: 2.0,
+            disposal_cost_per_unit: 1.0,
+            overcapacity_cost_per_unit: 3.0,
+            backorder_cost_per_unit_day: 0.5,
+        };
+
+        let SimulationSummary {
+            summary,
+            by_day,
+            kpis,
+            ..
+        } = simulation.run_n_times(5).unwrap();
+        assert_eq!(summary.expected_loss_cost, BigDecimal::from(40));
+        assert_eq!(by_day[0].expected_loss_cost, BigDecimal::from(0));
+        assert_eq!(by_day[3].expected_loss_cost, BigDecimal::from(40));
+        assert_eq!(by_day[3].loss_cost_p95, BigDecimal::from(40));
+        let loss_cost_kpi = kpis.iter().find(|kpi| kpi.kpi == "loss_cost").unwrap();
+        assert_eq!(loss_cost_kpi.mean, BigDecimal::from(40));
+        assert_eq!(loss_cost_kpi.p95, BigDecimal::from(40));
+
+        // Backordered demand has no lost margin, only a delay cost on the backlog.
+        simulation.sim_param.shortage_mode = ShortageMode::Backorder;
+        let SimulationSummary {
+            summary, by_day, ..
+        } = simulation.run_n_times(5).unwrap();
+        assert_eq!(by_day[3].expected_loss_cost, BigDecimal::from(10));
+        assert_eq!(summary.expected_loss_cost, BigDecimal::from(10));
+    }
+
+    #[test]
+    fn should_replenish_at_reorder_point_instead_of_historic_entries() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            100,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(1000, 30, 1, 1), // 2024-01-01 mon
+                mock_historic(1000, 30, 1, 2), // 2024-01-02 tur
+                mock_historic(1000, 30, 1, 3), // 2024-01-03 wed
+                mock_historic(1000, 30, 1, 4), // 2024-01-04 thu
+            ],
+        );
+        simulation.sim_param.replenishment = Some(ReplenishmentPolicy {
+            reorder_point: 50,
+            lead_time_days: 1,
+        });
+
+        let days = simulation.run_once();
+        assert_eq!(days.len(), 4);
+        assert!(days.iter().all(|day| day.stock_shortage.is_none()));
+        assert!(days.iter().all(|day| day.stock_limit_exceeded.is_none()));
+        assert_eq!(days[1].stock_qty(), BigDecimal::from(40));
+        assert_eq!(days[1].pending_orders_qty(), BigDecimal::from(60));
+        assert_eq!(days[2].stock_qty(), BigDecimal::from(70));
+        assert_eq!(days[2].pending_orders_qty(), BigDecimal::from(0));
+        assert_eq!(days[3].stock_qty(), BigDecimal::from(40));
+        assert_eq!(days[3].pending_orders_qty(), BigDecimal::from(60));
+
+        // Half of the order due during a partial outage waits for the supply to resume.
+        simulation.sim_param.stress_shock = Some(StressShock {
+            start_date: DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            end_date: DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            entry_factor: 0.5,
+            withdrawal_factor: 1.0,
+            capacity_factor: 1.0,
+        });
+        let days = simulation.run_once();
+        assert_eq!(days[2].stock_qty(), BigDecimal::from(40));
+        assert_eq!(days[2].pending_orders_qty(), BigDecimal::from(30));
+        assert_eq!(days[3].stock_qty(), BigDecimal::from(40));
+    }
+
+    #[test]
+    fn should_recommend_lowest_loss_replenishment_reaching_target() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            100,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(0, 30, 1, 1), // 2024-01-01 mon
+                mock_historic(0, 30, 1, 2), // 2024-01-02 tur
+                mock_historic(0, 30, 1, 3), // 2024-01-03 wed
+                mock_historic(0, 30, 1, 4), // 2024-01-04 thu
+            ],
+        );
+        let search = ReplenishmentSearch {
+            target_service_level: 0.95,
+            lead_time_days: 1,
+            candidates: 3,
+            runs: 2,
+        };
+
+        let candidates = search.evaluate_candidates(&mut simulation);
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].reorder_point, 30);
+        assert_eq!(candidates[0].service_level, 0.75);
+        assert_eq!(candidates[1].reorder_point, 80);
+        assert_eq!(candidates[1].service_level, 1.0);
+
+        let recommendation = search.recommend(&mut simulation).unwrap();
+        assert_eq!(recommendation.minimum_quantity, 50);
+        assert_eq!(recommendation.reorder_point, 80);
+        assert!(recommendation.is_target_reached);
+        assert_eq!(recommendation.candidates, 3);
+        assert!(simulation.sim_param.replenishment.is_none());
+    }
+
+    #[test]
+    fn should_recommend_smallest_capacity_under_nospace_target() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            100,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(30, 10, 1, 1), // 2024-01-01 mon
+                mock_historic(30, 10, 1, 2), // 2024-01-02 tur
+                mock_historic(30, 10, 1, 3), // 2024-01-03 wed
+            ],
+        );
+        let search = CapacitySearch {
+            target_nospace_probability: 0.05,
+            candidates: 5,
+            runs: 2,
+            max_upper_factor: 2,
+        };
+
+        let (recommendation, curve) = search.recommend(&mut simulation).unwrap();
+        assert_eq!(
+            curve
+                .iter()
+                .map(|point| point.stock_maximum_quantity)
+                .collect::<Vec<i32>>(),
+            vec![100, 125, 150, 175, 200]
+        );
+        assert_eq!(curve[2].probability_losses_by_nospace, BigDecimal::from(1));
+        assert_eq!(curve[3].probability_losses_by_nospace, BigDecimal::from(0));
+        assert_eq!(recommendation.current_maximum_quantity, 100);
+        assert_eq!(recommendation.recommended_maximum_quantity, Some(175));
+        assert_eq!(recommendation.candidates, 5);
+        assert_eq!(recommendation.no_recommendation_reason, None);
+        assert_eq!(simulation.sim_param.stock_maximum_quantity, 100);
+
+        // Out of the first range: widened up to the maximum factor.
+        simulation.sim_param.stock_maximum_quantity = 60;
+        let search = CapacitySearch {
+            max_upper_factor: 4,
+            ..search
+        };
+        let (recommendation, curve) = search.recommend(&mut simulation).unwrap();
+        assert_eq!(
+            curve
+                .iter()
+                .map(|point| point.stock_maximum_quantity)
+                .collect::<Vec<i32>>(),
+            vec![100, 105, 110, 115, 120, 150, 180, 210, 240]
+        );
+        assert_eq!(recommendation.recommended_maximum_quantity, Some(180));
+        assert_eq!(simulation.sim_param.stock_maximum_quantity, 60);
+
+        let search = CapacitySearch {
+            max_upper_factor: 2,
+            ..search
+        };
+        let (recommendation, curve) = search.recommend(&mut simulation).unwrap();
+        assert_eq!(curve.len(), 5);
+        assert_eq!(recommendation.recommended_maximum_quantity, None);
+        assert!(recommendation
+            .no_recommendation_reason
+            .unwrap()
+            .contains("up to 120"));
+    }
+
+    #[test]
+    fn should_analyze_shelf_life_sensitivity() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-05T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(10, 0, 1, 1), // 2024-01-01 mon
+                mock_historic(10, 0, 1, 2), // 2024-01-02 tur
+                mock_historic(10, 0, 1, 3), // 2024-01-03 wed
+                mock_historic(10, 0, 1, 4), // 2024-01-04 thu
+                mock_historic(10, 0, 1, 5), // 2024-01-05 fri
+            ],
+        );
+        let sensitivity = ShelfLifeSensitivity {
+            range: ShelfLifeRange {
+                min_days: 1,
+                max_days: 5,
+                step_days: 2,
+            },
+            runs: 2,
+        };
+
+        let (analysis, points) = sensitivity.analyze(&mut simulation).unwrap();
+        assert_eq!(analysis.current_expiration_days, 11);
+        assert_eq!(
+            points
+                .iter()
+                .map(|point| point.new_batch_default_expiration_days)
+                .collect::<Vec<i16>>(),
+            vec![1, 3, 5]
+        );
+        assert_eq!(points[0].probability_losses_by_expirat, BigDecimal::from(1));
+        assert_eq!(points[1].probability_losses_by_expirat, BigDecimal::from(1));
+        assert_eq!(points[2].probability_losses_by_expirat, BigDecimal::from(0));
+        assert_eq!(simulation.sim_param.new_batch_default_expiration_days, 11);
+    }
+
+    #[test]
+    fn should_sweep_parameters_with_sensitivities() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            100,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(30, 10, 1, 1), // 2024-01-01 mon
+                mock_historic(30, 10, 1, 2), // 2024-01-02 tur
+                mock_historic(30, 10, 1, 3), // 2024-01-03 wed
+            ],
+        );
+        let sweep = ParameterSweep {
+            axes: SweepAxis::parse_all(
+                "stock_maximum_quantity=100,200;withdrawal_trend_factor=1,2",
+            )
+            .unwrap(),
+            design: SweepDesign::Grid,
+            runs: 2,
+        };
+
+        let (parameter_sweep, points, sensitivities) = sweep.run(&mut simulation).unwrap();
+        assert_eq!(parameter_sweep.design, "grid");
+        assert_eq!(
+            parameter_sweep.axes,
+            "stock_maximum_quantity,withdrawal_trend_factor"
+        );
+        assert_eq!(parameter_sweep.points, 4);
+        assert_eq!(points[1].stock_maximum_quantity, 100);
+        assert_eq!(points[1].withdrawal_trend_factor, BigDecimal::from(2));
+        assert_eq!(points[1].new_batch_default_expiration_days, 11);
+        assert_eq!(points[1].probability_losses_by_nospace, BigDecimal::from(1));
+        assert_eq!(points[2].probability_losses_by_nospace, BigDecimal::from(0));
+
+        let nospace_by_capacity = sensitivities
+            .iter()
+            .find(|s| {
+                s.parameter == "stock_maximum_quantity"
+                    && s.metric == "probability_losses_by_nospace"
+            })
+            .unwrap();
+        assert_eq!(nospace_by_capacity.low_value, BigDecimal::from(100));
+        assert_eq!(nospace_by_capacity.high_value, BigDecimal::from(200));
+        assert_eq!(nospace_by_capacity.swing, BigDecimal::from(1));
+        let nospace_by_trend = sensitivities
+            .iter()
+            .find(|s| {
+                s.parameter == "withdrawal_trend_factor"
+                    && s.metric == "probability_losses_by_nospace"
+            })
+            .unwrap();
+        assert_eq!(nospace_by_trend.swing, BigDecimal::from(0));
+        assert_eq!(simulation.sim_param.stock_maximum_quantity, 100);
+        assert_eq!(simulation.sim_param.withdrawal_trend_factor, 1.0);
+    }
+
+    #[test]
+    fn should_reject_sweep_capacities_below_the_initial_stock() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            200,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(30, 10, 1, 1), // 2024-01-01 mon
+                mock_historic(30, 10, 1, 2), // 2024-01-02 tur
+            ],
+        );
+        let mut sweep = ParameterSweep {
+            axes: SweepAxis::parse_all("stock_maximum_quantity=50,200").unwrap(),
+            design: SweepDesign::Grid,
+            runs: 1,
+        };
+        assert!(sweep.run(&mut simulation).is_err());
+        sweep.design = SweepDesign::RandomSample { samples: 2 };
+        assert!(sweep.run(&mut simulation).is_err());
+        assert_eq!(simulation.sim_param.stock_maximum_quantity, 200);
+
+        sweep.axes = SweepAxis::parse_all("stock_maximum_quantity=100,200").unwrap();
+        sweep.design = SweepDesign::Grid;
+        let (_, points, _) = sweep.run(&mut simulation).unwrap();
+        assert_eq!(points.len(), 2);
+    }
+
+    #[test]
+    fn should_spread_the_sweep_results_by_the_random_range_factor() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            200,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(0, 50, 1, 1), // 2024-01-01 mon
+                mock_historic(0, 50, 1, 2), // 2024-01-02 tur
+            ],
+        );
+        let sweep = ParameterSweep {
+            axes: SweepAxis::parse_all("random_range_factor=0,0.5").unwrap(),
+            design: SweepDesign::Grid,
+            runs: 200,
+        };
+
+        let (_, points, _) = sweep.run(&mut simulation).unwrap();
+        // Without a random range each run withdraws exactly the stock of 100.
+        assert_eq!(points[0].probability_losses_by_missing, BigDecimal::from(0));
+        assert!(points[1].probability_losses_by_missing.is_positive());
+        assert!(points[1].probability_losses_by_missing < 1);
+    }
+
+    #[test]
+    fn should_apply_what_if_overrides() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-03T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            1000,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(0, 40, 1, 1), // 2024-01-01 mon
+                mock_historic(0, 40, 1, 2), // 2024-01-02 tur
+                mock_historic(0, 40, 1, 3), // 2024-01-03 wed
+            ],
+        );
+        let baseline = simulation.run_n_times(1).unwrap().summary;
+        assert_eq!(baseline.probability_losses_by_missing, BigDecimal::from(1));
+
+        let overrides = WhatIfOverrides::from_str(
+            "batch=2023-12-30T00:00:00Z/2024-01-20T00:00:00Z/50;\
+             scheduled=2024-01-02T10:00:00Z/0/20",
+        )
+        .unwrap();
+        overrides.apply(&mut simulation).unwrap();
+        assert_eq!(
+            simulation.first_day.batches[0].quantity,
+            BigDecimal::from(50)
+        );
+        let late_batch =
+            WhatIfOverrides::from_str("batch=2024-01-02T00:00:00Z/2024-01-20T00:00:00Z/50")
+                .unwrap();
+        assert!(late_batch.apply(&mut simulation).is_err());
+
+        let what_if = simulation.run_n_times(1).unwrap();
+        assert_eq!(
+            what_if.summary.probability_losses_by_missing,
+            BigDecimal::from(0)
+        );
+        assert_eq!(
+            what_if.by_day.last().unwrap().stock_quantity_p50,
+            BigDecimal::from(10)
+        );
+        let below_the_stock = WhatIfOverrides::from_str("stock_maximum_quantity=149").unwrap();
+        assert!(below_the_stock.apply(&mut simulation).is_err());
+        assert_eq!(simulation.sim_param.stock_maximum_quantity, 1000);
+        let at_the_stock = WhatIfOverrides::from_str(
+            "stock_maximum_quantity=200;batch=2023-12-31T00:00:00Z/2024-01-20T00:00:00Z/50",
+        )
+        .unwrap();
+        at_the_stock.apply(&mut simulation).unwrap();
+        assert_eq!(simulation.sim_param.stock_maximum_quantity, 200);
+    }
+
+    #[test]
+    fn should_run_stress_tests_deterministically() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-05T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            200,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(20, 20, 1, 1), // 2024-01-01 mon
+                mock_historic(20, 20, 1, 2), // 2024-01-02 tur
+                mock_historic(20, 20, 1, 3), // 2024-01-03 wed
+                mock_historic(20, 20, 1, 4), // 2024-01-04 thu
+                mock_historic(20, 20, 1, 5), // 2024-01-05 fri
+            ],
+        );
+        simulation.sim_param.random_range_factor = 0.5;
+        let stress_test = |name: &str, kind: StressKind, factor: f64| StressTest {
+            name: name.to_owned(),
+            kind,
+            start_offset_days: 0,
+            duration_days: None,
+            factor,
+        };
+        let suite = StressTestSuite {
+            tests: vec![
+                stress_test("outage", StressKind::SupplierOutage, 0.0),
+                stress_test("demand", StressKind::DemandShock, 1.5),
+                stress_test("capacity", StressKind::CapacityCut, 0.4),
+            ],
+        };
+
+        let results = suite.run(&mut simulation).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].final_stock_qty, BigDecimal::from(0));
+        assert_eq!(results[0].shortage_qty, BigDecimal::from(0));
+        assert_eq!(results[1].final_stock_qty, BigDecimal::from(50));
+        assert_eq!(results[2].nospace_qty, BigDecimal::from(20));
+        assert_eq!(results[2].minimum_stock_qty, BigDecimal::from(80));
+        assert_eq!(
+            results[2]
+                .first_date_with_losses
+                .map(|date| date.to_string()),
+            Some("2024-01-01".to_owned())
+        );
+        let rerun = suite.run(&mut simulation).unwrap();
+        assert_eq!(rerun[1].final_stock_qty, results[1].final_stock_qty);
+        assert!(simulation.sim_param.stress_shock.is_none());
+        assert!(!simulation.sim_param.deterministic);
+    }
+
+    #[test]
+    fn should_reject_the_entries_when_a_capacity_cut_is_below_the_stock() {
+        let mut simulation = SimulationControl::new(
+            Uuid::from_u128(0),
+            DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z")
+                .unwrap()
+                .to_utc(),
+            200,
+            11,
+            mock_product_batches(),
+            vec![
+                mock_historic(10, 5, 1, 1), // 2024-01-01 mon
+                mock_historic(10, 5, 1, 2), // 2024-01-02 tur
+            ],
+        );
+        let suite = StressTestSuite {
+            tests: vec![StressTest {
+                name: "capacity halved".to_owned(),
+                kind: StressKind::CapacityCut,
+                start_offset_days: 0,
+                duration_days: None,
+                factor: 0.4,
+            }],
+        };
+
+        // The stock of 95 after the first withdrawal is above the capacity of 80.
+        let results = suite.run(&mut simulation).unwrap();
+        assert_eq!(results[0].nospace_qty, BigDecimal::from(20));
+        assert_eq!(results[0].minimum_stock_qty, BigDecimal::from(90));
+        assert_eq!(results[0].final_stock_qty, BigDecimal::from(90));
+
+        simulation.sim_param.stress_shock = Some(
+            suite.tests[0]
+                .shock(simulation.first_day.date, simulation.final_date)
+                .unwrap(),
+        );
+        let days = simulation.run_once();
+        assert_eq!(days[0].batches.len(), 1);
+        assert_eq!(days[0].batches[0].quantity, BigDecimal::from(95));
+        assert_eq!(days[0].stock_limit_exceeded, Some(BigDecimal::from(10)));
+    }
+
     fn mock_product_batches() -> Vec<ProductBatch> {
         vec![ProductBatch {
             quantity: BigDecimal::from(100),
@@ -345,6 +1441,7 @@ mod tests {
                 .to_utc(),
             finished_date: None,
             is_finished: false,
+            qc_release_date: None,
         }]
     }
 
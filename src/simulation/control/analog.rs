@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use bigdecimal::FromPrimitive;
+use sqlx::types::BigDecimal;
+use uuid::Uuid;
+
+use crate::data::product_mov_hist::ProductMovHist;
+
+/// History of an analog product, scaled to the new product.
+#[derive(Debug, Clone)]
+pub struct AnalogHistory {
+    pub scaling_factor: f64,
+    pub historic: Vec<ProductMovHist>,
+}
+
+/// Borrows the history of analog products until a product has enough of its own.
+#[derive(Debug, Clone)]
+pub struct AnalogBlend {
+    pub analogs: Vec<AnalogHistory>,
+    /// Weight of the own history, from 0 for a new product to 1 with enough history.
+    pub own_weight: f64,
+}
+
+impl AnalogBlend {
+    /// Weight of the own history, growing linearly with its days up to `full_history_days`.
+    pub fn own_weight(own_days: u64, full_history_days: u64) -> f64 {
+        if full_history_days == 0 {
+            return 1.0;
+        }
+        (own_days as f64 / full_history_days as f64).min(1.0)
+    }
+
+    /// Historic averages of each week of year and day of week: the own average weighted with
+    /// the average of the scaled analogs. A day of the year missing from either side is
+    /// taken from the other one.
+    pub fn blend(&self, product_id: Uuid, own: Vec<ProductMovHist>) -> Vec<ProductMovHist> {
+        if self.analogs.is_empty() || self.own_weight >= 1.0 {
+            return own;
+        }
+        let decimal = |value: f64| BigDecimal::from_f64(value).unwrap_or(BigDecimal::from(0));
+        let mut analog_by_woy_and_dow: BTreeMap<(i16, i16), Vec<(BigDecimal, BigDecimal)>> =
+            BTreeMap::new();
+        for analog in &self.analogs {
+            let scaling_factor = decimal(analog.scaling_factor);
+            for hist in &analog.historic {
+                analog_by_woy_and_dow
+                    .entry((hist.week_of_year, hist.day_of_week))
+                    .or_default()
+                    .push((
+                        &hist.entry_qty * &scaling_factor,
+                        &hist.withdrawal_qty * &scaling_factor,
+                    ));
+            }
+        }
+        let mut blended: BTreeMap<(i16, i16), ProductMovHist> = analog_by_woy_and_dow
+            .into_iter()
+            .map(|((week_of_year, day_of_week), quantities)| {
+                let count = BigDecimal::from(quantities.len() as u64);
+                let (entry_qty, withdrawal_qty) = quantities.into_iter().fold(
+                    (BigDecimal::from(0), BigDecimal::from(0)),
+                    |(entry_sum, withdrawal_sum), (entry_qty, withdrawal_qty)| {
+                        (entry_sum + entry_qty, withdrawal_sum + withdrawal_qty)
+                    },
+                );
+                let hist = ProductMovHist {
+                    product_id,
+                    entry_qty: entry_qty / &count,
+                    withdrawal_qty: withdrawal_qty / &count,
+                    week_of_year,
+                    day_of_week,
+                };
+                ((week_of_year, day_of_week), hist)
+            })
+            .collect();
+        let own_weight = decimal(self.own_weight);
+        let analog_weight = decimal(1.0 - self.own_weight);
+        for hist in own {
+            let key = (hist.week_of_year, hist.day_of_week);
+            let hist = match blended.remove(&key) {
+                Some(analog) => ProductMovHist {
+                    entry_qty: &hist.entry_qty * &own_weight + &analog.entry_qty * &analog_weight,
+                    withdrawal_qty: &hist.withdrawal_qty * &own_weight
+                        + &analog.withdrawal_qty * &analog_weight,
+                    ..hist
+                },
+                None => hist,
+            };
+            blended.insert(key, hist);
+        }
+        blended.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hist(product_id: u128, week_of_year: i16, withdrawal_qty: i32) -> ProductMovHist {
+        ProductMovHist {
+            product_id: Uuid::from_u128(product_id),
+            entry_qty: BigDecimal::from(0),
+            withdrawal_qty: BigDecimal::from(withdrawal_qty),
+            week_of_year,
+            day_of_week: 1,
+        }
+    }
+
+    #[test]
+    fn should_weight_the_own_history_by_its_days() {
+        assert_eq!(AnalogBlend::own_weight(0, 365), 0.0);
+        assert_eq!(AnalogBlend::own_weight(73, 365), 0.2);
+        assert_eq!(AnalogBlend::own_weight(400, 365), 1.0);
+    }
+
+    #[test]
+    fn should_blend_the_scaled_analogs_with_the_own_history() {
+        let blend = AnalogBlend {
+            analogs: vec![
+                AnalogHistory {
+                    scaling_factor: 0.5,
+                    historic: vec![hist(1, 1, 100), hist(1, 2, 100)],
+                },
+                AnalogHistory {
+                    scaling_factor: 1.0,
+                    historic: vec![hist(2, 1, 30)],
+                },
+            ],
+            own_weight: 0.25,
+        };
+        let blended = blend.blend(Uuid::from_u128(0), vec![hist(0, 1, 20), hist(0, 3, 8)]);
+        assert_eq!(blended.len(), 3);
+        assert!(blended.iter().all(|h| h.product_id == Uuid::from_u128(0)));
+        // Week 1: analogs average (50 + 30) / 2 = 40, blended 0.25 * 20 + 0.75 * 40.
+        assert_eq!(blended[0].withdrawal_qty, BigDecimal::from(35));
+        // Week 2: analogs only, week 3: own only.
+        assert_eq!(blended[1].withdrawal_qty, BigDecimal::from(50));
+        assert_eq!(blended[2].withdrawal_qty, BigDecimal::from(8));
+    }
+
+    #[test]
+    fn should_keep_the_own_history_when_complete() {
+        let blend = AnalogBlend {
+            analogs: vec![AnalogHistory {
+                scaling_factor: 1.0,
+                historic: vec![hist(1, 2, 100)],
+            }],
+            own_weight: 1.0,
+        };
+        let blended = blend.blend(Uuid::from_u128(0), vec![hist(0, 1, 20)]);
+        assert_eq!(blended.len(), 1);
+        assert_eq!(blended[0].withdrawal_qty, BigDecimal::from(20));
+    }
+}
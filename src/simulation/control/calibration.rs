@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use bigdecimal::{FromPrimitive, ToPrimitive};
+use chrono::{Days, NaiveDate};
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::{
+        product_calibration_backtest::NewProductCalibrationBacktest,
+        product_calibration_reliability::NewProductCalibrationReliability,
+        product_mov_hist::ProductMovDaily,
+        product_simulation_summary_by_day::NewProductSimulationSummaryByDay,
+    },
+    simulation::control::{
+        forecast::DailyForecast, per_day::SimulationDay, statistics::mean, SimulationControl,
+    },
+};
+
+/// Predicted probability from which a day is flagged as a loss day, for the hit rates.
+pub const HIT_PROBABILITY: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LossKind {
+    Shortage,
+    Nospace,
+    Expiration,
+}
+
+/// Scores of a loss kind with its reliability diagram.
+pub type CalibrationResult = (
+    NewProductCalibrationBacktest,
+    Vec<NewProductCalibrationReliability>,
+);
+
+pub const LOSS_KINDS: [LossKind; 3] = [LossKind::Shortage, LossKind::Nospace, LossKind::Expiration];
+
+impl LossKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LossKind::Shortage => "shortage",
+            LossKind::Nospace => "nospace",
+            LossKind::Expiration => "expiration",
+        }
+    }
+
+    fn predicted(&self, day: &NewProductSimulationSummaryByDay) -> f64 {
+        match self {
+            LossKind::Shortage => &day.probability_losses_by_missing,
+            LossKind::Nospace => &day.probability_losses_by_nospace,
+            LossKind::Expiration => &day.probability_losses_by_expirat,
+        }
+        .to_f64()
+        .unwrap_or(0.0)
+    }
+
+    fn occurred(&self, day: &SimulationDay) -> bool {
+        match self {
+            LossKind::Shortage => day.stock_shortage.is_some(),
+            LossKind::Nospace => day.stock_limit_exceeded.is_some(),
+            LossKind::Expiration => day.stock_time_limit_exceeded.is_some(),
+        }
+    }
+}
+
+/// Losses of each day when replaying the observed movements of the product, from the
+/// first simulated day with its stock at that date, instead of drawing scenarios.
+/// The simulation parameters are restored afterwards.
+pub fn replay_observed(
+    simulation: &mut SimulationControl,
+    observed: &[ProductMovDaily],
+) -> Vec<SimulationDay> {
+    let current_param = simulation.sim_param.clone();
+    let sim_param = &mut simulation.sim_param;
+    sim_param.deterministic = true;
+    sim_param.random_range_factor = 0.0;
+    sim_param.entry_trend_factor = 1.0;
+    sim_param.withdrawal_trend_factor = 1.0;
+    sim_param.stress_shock = None;
+    sim_param.intermittent_demand = None;
+    // The observed movements already hold the deliveries, promotions and scheduled movements.
+    sim_param.replenishment = None;
+    sim_param.promotions.clear();
+    sim_param.scheduled_movements.clear();
+    let quantities: HashMap<NaiveDate, &ProductMovDaily> =
+        observed.iter().map(|day| (day.mov_date, day)).collect();
+    let first_date = simulation.first_day.date.date_naive();
+    let final_date = simulation.final_date.date_naive();
+    sim_param.set_daily_forecast(
+        first_date
+            .iter_days()
+            .take_while(|date| *date <= final_date)
+            .map(|date| DailyForecast {
+                date,
+                entry_qty: quantities
+                    .get(&date)
+                    .and_then(|day| day.entry_qty.to_f64())
+                    .unwrap_or(0.0),
+                withdrawal_qty: quantities
+                    .get(&date)
+                    .and_then(|day| day.withdrawal_qty.to_f64())
+                    .unwrap_or(0.0),
+            })
+            .collect(),
+    );
+    let days = SimulationDay::roll_up_daily(simulation.run_once());
+    simulation.sim_param = current_param;
+    days
+}
+
+/// Predicted probabilities of each loss kind with whether the loss occurred, day by day.
+#[derive(Debug, Default)]
+pub struct CalibrationCounter {
+    pairs: HashMap<LossKind, Vec<(f64, bool)>>,
+}
+
+impl CalibrationCounter {
+    /// Only the days before `observed_until` are counted, the later ones being unknown.
+    pub fn add(
+        &mut self,
+        predicted: &[NewProductSimulationSummaryByDay],
+        observed: &[SimulationDay],
+        observed_until: NaiveDate,
+    ) {
+        let observed_by_date: HashMap<NaiveDate, &SimulationDay> = observed
+            .iter()
+            .map(|day| (day.date.date_naive(), day))
+            .collect();
+        for day in predicted.iter().filter(|day| day.date < observed_until) {
+            let Some(observed_day) = observed_by_date.get(&day.date) else {
+                continue;
+            };
+            for kind in LOSS_KINDS {
+                self.pairs
+                    .entry(kind)
+                    .or_default()
+                    .push((kind.predicted(day), kind.occurred(observed_day)));
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &CalibrationCounter) {
+        for (kind, pairs) in other.pairs.iter() {
+            self.pairs.entry(*kind).or_default().extend(pairs);
+        }
+    }
+
+    /// Brier score of all the loss kinds together, `None` without any day.
+    pub fn brier_score(&self) -> Option<f64> {
+        let squared_errors: Vec<f64> = self
+            .pairs
+            .values()
+            .flatten()
+            .map(|(probability, occurred)| (probability - outcome(*occurred)).powi(2))
+            .collect();
+        Some(mean(&squared_errors)).filter(|_| !squared_errors.is_empty())
+    }
+
+    /// Brier score and hit rate of each loss kind, with its reliability diagram
+    /// over `bins` equal ranges of predicted probability.
+    pub fn summarize(
+        &self,
+        first_reference_date: NaiveDate,
+        last_reference_date: NaiveDate,
+        bins: usize,
+    ) -> Result<Vec<CalibrationResult>, Box<dyn std::error::Error>> {
+        let decimal = |value: f64| {
+            BigDecimal::from_f64(value)
+                .map(|value| value.round(4))
+                .ok_or("Invalid calibration score")
+        };
+        let mut results = Vec::new();
+        for kind in LOSS_KINDS {
+            let Some(pairs) = self.pairs.get(&kind).filter(|pairs| !pairs.is_empty()) else {
+                continue;
+            };
+            let loss_days = pairs.iter().filter(|(_, occurred)| *occurred).count();
+            let hits = pairs
+                .iter()
+                .filter(|(probability, occurred)| *occurred && *probability >= HIT_PROBABILITY)
+                .count();
+            let brier_score = mean(
+                &pairs
+                    .iter()
+                    .map(|(probability, occurred)| (probability - outcome(*occurred)).powi(2))
+                    .collect::<Vec<f64>>(),
+            );
+            let backtest = NewProductCalibrationBacktest {
+                first_reference_date,
+                last_reference_date,
+                loss_kind: kind.name().to_owned(),
+                forecast_days: i32::try_from(pairs.len())?,
+                loss_days: i32::try_from(loss_days)?,
+                brier_score: decimal(brier_score)?,
+                hit_rate: if loss_days > 0 {
+                    Some(decimal(hits as f64 / loss_days as f64)?)
+                } else {
+                    None
+                },
+            };
+            let reliability = (0..bins)
+                .map(|bin| {
+                    let lower = bin as f64 / bins as f64;
+                    let upper = (bin + 1) as f64 / bins as f64;
+                    let in_bin: Vec<&(f64, bool)> = pairs
+                        .iter()
+                        .filter(|(probability, _)| {
+                            *probability >= lower && (*probability < upper || bin + 1 == bins)
+                        })
+                        .collect();
+                    let average = |value: fn(&(f64, bool)) -> f64| {
+                        if in_bin.is_empty() {
+                            return Ok(None);
+                        }
+                        decimal(mean(
+                            &in_bin.iter().map(|pair| value(pair)).collect::<Vec<f64>>(),
+                        ))
+                        .map(Some)
+                    };
+                    Ok(NewProductCalibrationReliability {
+                        bin_index: i16::try_from(bin)?,
+                        lower_probability: decimal(lower)?,
+                        upper_probability: decimal(upper)?,
+                        forecast_days: i32::try_from(in_bin.len())?,
+                        mean_probability: average(|(probability, _)| *probability)?,
+                        observed_frequency: average(|(_, occurred)| outcome(*occurred))?,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+            results.push((backtest, reliability));
+        }
+        Ok(results)
+    }
+}
+
+fn outcome(occurred: bool) -> f64 {
+    if occurred {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Past reference dates, every `step_days` before `reference_date`, the oldest first.
+pub fn reference_dates(reference_date: NaiveDate, count: u64, step_days: u64) -> Vec<NaiveDate> {
+    (1..=count)
+        .rev()
+        .filter_map(|k| reference_date.checked_sub_days(Days::new(k * step_days)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, Utc};
+
+    use crate::data::product_batch::ProductBatch;
+
+    use super::*;
+
+    fn predicted_day(
+        date: NaiveDate,
+        probability_missing: &str,
+    ) -> NewProductSimulationSummaryByDay {
+        let zero = BigDecimal::from(0);
+        NewProductSimulationSummaryByDay {
+            date,
+            probability_losses_by_missing: BigDecimal::from_str(probability_missing).unwrap(),
+            probability_losses_by_nospace: zero.clone(),
+            probability_losses_by_expirat: zero.clone(),
+            probability_losses_by_rejection: zero.clone(),
+            average_backlog_quantity: zero.clone(),
+            average_backlog_age_days: zero.clone(),
+            stock_quantity_p5: zero.clone(),
+            stock_quantity_p25: zero.clone(),
+            stock_quantity_p50: zero.clone(),
+            stock_quantity_p75: zero.clone(),
+            stock_quantity_p95: zero.clone(),
+            expected_loss_cost: zero.clone(),
+            loss_cost_p95: zero,
+        }
+    }
+
+    fn observed_day(date: NaiveDate, shortage: bool) -> SimulationDay {
+        SimulationDay {
+            date: date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            batches: Vec::new(),
+            stock_time_limit_exceeded: None,
+            stock_shortage: shortage.then(|| BigDecimal::from(1)),
+            backordered: None,
+            stock_limit_exceeded: None,
+            qc_rejected: None,
+            backlog: Vec::new(),
+            pending_orders: Vec::new(),
+            demand_qty: BigDecimal::from(0),
+            withdrawn_qty: BigDecimal::from(0),
+            withdrawn_age_days: 0.0,
+            is_calculated: true,
+        }
+    }
+
+    #[test]
+    fn should_score_the_predicted_probabilities() {
+        let date = |day: u32| NaiveDate::from_ymd_opt(2021, 6, day).unwrap();
+        let predicted = vec![
+            predicted_day(date(1), "0.9"),
+            predicted_day(date(2), "0.1"),
+            predicted_day(date(3), "0.2"),
+            predicted_day(date(4), "0.9"),
+        ];
+        let observed = vec![
+            observed_day(date(1), true),
+            observed_day(date(2), false),
+            observed_day(date(3), true),
+            observed_day(date(4), true),
+        ];
+        let mut counter = CalibrationCounter::default();
+        counter.add(&predicted, &observed, date(4));
+        let results = counter.summarize(date(1), date(1), 2).unwrap();
+        let (shortage, reliability) = &results[0];
+        assert_eq!(shortage.loss_kind, "shortage");
+        assert_eq!(shortage.forecast_days, 3);
+        assert_eq!(shortage.loss_days, 2);
+        // ((0.9 - 1)^2 + 0.1^2 + (0.2 - 1)^2) / 3
+        assert_eq!(shortage.brier_score, BigDecimal::from_str("0.22").unwrap());
+        assert_eq!(
+            shortage.hit_rate,
+            Some(BigDecimal::from_str("0.5").unwrap())
+        );
+        assert_eq!(reliability.len(), 2);
+        assert_eq!(reliability[0].forecast_days, 2);
+        assert_eq!(
+            reliability[0].observed_frequency,
+            Some(BigDecimal::from_str("0.5").unwrap())
+        );
+        assert_eq!(reliability[1].forecast_days, 1);
+
+        let (nospace, _) = &results[1];
+        assert_eq!(nospace.brier_score, BigDecimal::from(0));
+        assert_eq!(nospace.hit_rate, None);
+        // The shortage errors over the 9 days of the 3 loss kinds.
+        assert!((counter.brier_score().unwrap() - 0.22 / 3.0).abs() < 1e-9);
+        assert_eq!(CalibrationCounter::default().brier_score(), None);
+    }
+
+    #[test]
+    fn should_replay_the_observed_movements() {
+        let initial_date: DateTime<Utc> = DateTime::parse_from_rfc3339("2021-06-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let final_date = initial_date.checked_add_days(Days::new(2)).unwrap();
+        let mut simulation = SimulationControl::new(
+            uuid::Uuid::from_u128(0),
+            initial_date,
+            final_date,
+            100,
+            30,
+            vec![ProductBatch {
+                entry_date: initial_date,
+                deadline_date: initial_date.checked_add_days(Days::new(30)).unwrap(),
+                finished_date: None,
+                is_finished: false,
+                quantity: BigDecimal::from(10),
+                qc_release_date: None,
+            }],
+            vec![],
+        );
+        simulation.sim_param.random_range_factor = 0.5;
+        let observed = vec![ProductMovDaily {
+            mov_date: NaiveDate::from_ymd_opt(2021, 6, 2).unwrap(),
+            entry_qty: BigDecimal::from(0),
+            withdrawal_qty: BigDecimal::from(15),
+        }];
+        let days = replay_observed(&mut simulation, &observed);
+        assert_eq!(days.len(), 3);
+        assert!(days[0].stock_shortage.is_none());
+        assert_eq!(days[1].stock_shortage, Some(BigDecimal::from(5)));
+        assert_eq!(simulation.sim_param.random_range_factor, 0.5);
+        assert!(!simulation.sim_param.deterministic);
+    }
+
+    #[test]
+    fn should_list_the_past_reference_dates() {
+        let date = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap();
+        assert_eq!(
+            reference_dates(date, 2, 30),
+            vec![
+                NaiveDate::from_ymd_opt(2021, 11, 2).unwrap(),
+                NaiveDate::from_ymd_opt(2021, 12, 2).unwrap()
+            ]
+        );
+    }
+}
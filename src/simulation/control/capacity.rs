@@ -0,0 +1,137 @@
+use bigdecimal::{FromPrimitive, ToPrimitive};
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::{
+        product_capacity_curve::NewProductCapacityCurve,
+        product_capacity_recommendation::NewProductCapacityRecommendation,
+    },
+    simulation::control::SimulationControl,
+};
+
+/// The first candidates go up to this factor times the current stock maximum quantity.
+pub const CAPACITY_SEARCH_UPPER_FACTOR: u64 = 2;
+
+/// Sweeps the stock maximum quantity, simulating each candidate, to find the
+/// smallest capacity keeping the "no space" loss probability under a target.
+pub struct CapacitySearch {
+    pub target_nospace_probability: f64,
+    pub candidates: u64,
+    pub runs: u64,
+    /// While no candidate reaches the target, the search range is doubled up to this
+    /// factor times the current stock maximum quantity.
+    pub max_upper_factor: u64,
+}
+
+impl CapacitySearch {
+    /// The recommended capacity is `None` when no candidate reaches the target,
+    /// with the reason.
+    pub fn recommend(
+        &self,
+        simulation: &mut SimulationControl,
+    ) -> Result<
+        (
+            NewProductCapacityRecommendation,
+            Vec<NewProductCapacityCurve>,
+        ),
+        Box<dyn std::error::Error>,
+    > {
+        let current_maximum_quantity = simulation.sim_param.stock_maximum_quantity;
+        let target = BigDecimal::from_f64(self.target_nospace_probability)
+            .ok_or("Invalid target no space probability")?;
+        let curve = self.evaluate_candidates(simulation, &target)?;
+        let recommended_maximum_quantity = curve
+            .iter()
+            .find(|point| point.probability_losses_by_nospace <= target)
+            .map(|point| point.stock_maximum_quantity);
+        let no_recommendation_reason = match (recommended_maximum_quantity, curve.last()) {
+            (Some(_), _) => None,
+            (None, Some(point)) => Some(format!(
+                "No stock maximum quantity up to {} keeps the no space probability under the target",
+                point.stock_maximum_quantity
+            )),
+            (None, None) => Some("No stock maximum quantity simulated".to_owned()),
+        };
+        let recommendation = NewProductCapacityRecommendation {
+            product_id: simulation.product_id,
+            start_date: simulation.first_day.date.date_naive(),
+            end_date: simulation.final_date.date_naive(),
+            target_nospace_probability: target,
+            current_maximum_quantity: i32::try_from(current_maximum_quantity)?,
+            recommended_maximum_quantity,
+            candidates: i32::try_from(curve.len())?,
+            no_recommendation_reason,
+        };
+        Ok((recommendation, curve))
+    }
+
+    /// Candidates are spread evenly from the initial stock quantity to the upper bound,
+    /// ordered by capacity. While none reaches the `target`, the candidates go on up to
+    /// twice the upper bound, as far as `max_upper_factor`.
+    /// The stock maximum quantity of the simulation is restored afterwards.
+    pub fn evaluate_candidates(
+        &self,
+        simulation: &mut SimulationControl,
+        target: &BigDecimal,
+    ) -> Result<Vec<NewProductCapacityCurve>, Box<dyn std::error::Error>> {
+        let current_maximum_quantity = simulation.sim_param.stock_maximum_quantity;
+        let mut lower = simulation
+            .first_day
+            .stock_qty()
+            .to_f64()
+            .unwrap_or(0.0)
+            .ceil() as u64;
+        let max_upper_factor = self.max_upper_factor.max(CAPACITY_SEARCH_UPPER_FACTOR);
+        let mut upper_factor = CAPACITY_SEARCH_UPPER_FACTOR;
+        let mut curve: Vec<NewProductCapacityCurve> = Vec::new();
+        loop {
+            let upper = current_maximum_quantity
+                .saturating_mul(upper_factor)
+                .max(lower);
+            self.evaluate_range(simulation, lower, upper, &mut curve)?;
+            let is_target_met = curve
+                .iter()
+                .any(|point| point.probability_losses_by_nospace <= *target);
+            if is_target_met || upper_factor >= max_upper_factor || upper <= lower {
+                break;
+            }
+            lower = upper;
+            upper_factor = upper_factor.saturating_mul(2).min(max_upper_factor);
+        }
+        Ok(curve)
+    }
+
+    fn evaluate_range(
+        &self,
+        simulation: &mut SimulationControl,
+        lower: u64,
+        upper: u64,
+        curve: &mut Vec<NewProductCapacityCurve>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let current_maximum_quantity = simulation.sim_param.stock_maximum_quantity;
+        let steps = self.candidates.max(2) - 1;
+        for i in 0..=steps {
+            let stock_maximum_quantity = lower + (upper - lower) * i / steps;
+            if curve
+                .last()
+                .is_some_and(|point| point.stock_maximum_quantity as u64 == stock_maximum_quantity)
+            {
+                continue;
+            }
+            simulation.sim_param.stock_maximum_quantity = stock_maximum_quantity;
+            let evaluated = simulation.run_n_times(self.runs.max(1));
+            simulation.sim_param.stock_maximum_quantity = current_maximum_quantity;
+            let summary = evaluated?.summary;
+            curve.push(NewProductCapacityCurve {
+                stock_maximum_quantity: i32::try_from(stock_maximum_quantity)?,
+                probability_losses_by_missing: summary.probability_losses_by_missing,
+                probability_losses_by_nospace: summary.probability_losses_by_nospace,
+                probability_losses_by_expirat: summary.probability_losses_by_expirat,
+                probability_losses_by_rejection: summary.probability_losses_by_rejection,
+                probability_no_losses: summary.probability_no_losses,
+                expected_loss_cost: summary.expected_loss_cost,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use bigdecimal::ToPrimitive;
+use sqlx::types::BigDecimal;
+
+use crate::simulation::control::per_day::SimulationDay;
+
+/// Money lost per unit of each loss type.
+/// Expired and QC rejected units lose their unit cost, expired units also have to be disposed of.
+#[derive(Debug, Clone, Default)]
+pub struct LossCost {
+    pub unit_cost: f64,
+    /// Margin lost for each unit of demand not served.
+    pub lost_margin_per_unit: f64,
+    pub disposal_cost_per_unit: f64,
+    /// Cost of each entry unit rejected because the stock is over its capacity.
+    pub overcapacity_cost_per_unit: f64,
+    /// Cost of each backordered unit for each day it waits in the backlog.
+    pub backorder_cost_per_unit_day: f64,
+}
+
+impl LossCost {
+    /// The lost margin is only charged on the lost sales, the backordered demand
+    /// being served later at a delay cost on the backlog of the day.
+    pub fn of_day(&self, day: &SimulationDay) -> f64 {
+        Self::quantity(&day.stock_shortage) * self.lost_margin_per_unit
+            + day.backlog_qty().to_f64().unwrap_or(0.0) * self.backorder_cost_per_unit_day
+            + Self::quantity(&day.stock_limit_exceeded) * self.overcapacity_cost_per_unit
+            + Self::quantity(&day.stock_time_limit_exceeded)
+                * (self.unit_cost + self.disposal_cost_per_unit)
+            + Self::quantity(&day.qc_rejected) * self.unit_cost
+    }
+
+    pub fn of_days(&self, days: &[SimulationDay]) -> f64 {
+        days.iter().map(|day| self.of_day(day)).sum()
+    }
+
+    fn quantity(loss: &Option<BigDecimal>) -> f64 {
+        loss.as_ref()
+            .and_then(|quantity| quantity.to_f64())
+            .unwrap_or(0.0)
+    }
+}
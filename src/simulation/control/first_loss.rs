@@ -0,0 +1,161 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
+use crate::{
+    data::product_simulation_first_loss::NewProductSimulationFirstLoss,
+    simulation::control::{
+        per_day::SimulationDay,
+        statistics::{percentile, sorted},
+    },
+};
+
+type HasLoss = fn(&SimulationDay) -> bool;
+
+const LOSS_TYPES: [(&str, HasLoss); 5] = [
+    ("missing", |day| day.stock_shortage.is_some()),
+    ("nospace", |day| day.stock_limit_exceeded.is_some()),
+    ("expirat", |day| day.stock_time_limit_exceeded.is_some()),
+    ("rejection", |day| day.qc_rejected.is_some()),
+    ("any", |day| {
+        day.stock_shortage.is_some()
+            || day.stock_limit_exceeded.is_some()
+            || day.stock_time_limit_exceeded.is_some()
+            || day.qc_rejected.is_some()
+    }),
+];
+
+/// Collects, for each loss type, the days from the start date until the
+/// first loss of each run. Runs without the loss are censored at the horizon:
+/// they count in the percentiles as a first loss after the end date.
+pub struct FirstLossCounter {
+    start_date: NaiveDate,
+    runs: usize,
+    days_until_first_loss: Vec<Vec<f64>>,
+}
+
+impl FirstLossCounter {
+    pub fn new(start_date: DateTime<Utc>) -> Self {
+        Self {
+            start_date: start_date.date_naive(),
+            runs: 0,
+            days_until_first_loss: vec![Vec::new(); LOSS_TYPES.len()],
+        }
+    }
+
+    pub fn add(&mut self, days: &[SimulationDay]) {
+        self.runs += 1;
+        for (i, (_, has_loss)) in LOSS_TYPES.iter().enumerate() {
+            if let Some(day) = days.iter().find(|day| has_loss(day)) {
+                let days_until = (day.date.date_naive() - self.start_date).num_days();
+                self.days_until_first_loss[i].push(days_until as f64);
+            }
+        }
+    }
+
+    pub fn summarize(
+        &self,
+    ) -> Result<Vec<NewProductSimulationFirstLoss>, Box<dyn std::error::Error>> {
+        let mut summaries = Vec::new();
+        for (i, (loss_type, _)) in LOSS_TYPES.iter().enumerate() {
+            let runs_with_loss = self.days_until_first_loss[i].len();
+            if runs_with_loss == 0 {
+                continue;
+            }
+            let mut values = sorted(self.days_until_first_loss[i].clone());
+            values.resize(self.runs.max(runs_with_loss), f64::INFINITY);
+            let earliest_days = self
+                .days_percentile(&values, 0.0)
+                .ok_or("No first loss for the earliest date")?;
+            let median_days = self.days_percentile(&values, 50.0);
+            let p90_days = self.days_percentile(&values, 90.0);
+            summaries.push(NewProductSimulationFirstLoss {
+                loss_type: loss_type.to_string(),
+                runs: i32::try_from(values.len())?,
+                runs_with_loss: i32::try_from(runs_with_loss)?,
+                earliest_date: self.date_after(earliest_days)?,
+                median_date: median_days.map(|days| self.date_after(days)).transpose()?,
+                p90_date: p90_days.map(|days| self.date_after(days)).transpose()?,
+                earliest_days,
+                median_days,
+                p90_days,
+            });
+        }
+        Ok(summaries)
+    }
+
+    /// `None` when the percentile falls on the runs censored at the horizon.
+    fn days_percentile(&self, values: &[f64], p: f64) -> Option<i32> {
+        percentile(values, p)
+            .filter(|days| days.is_finite())
+            .map(|days| days.round() as i32)
+    }
+
+    fn date_after(&self, days: i32) -> Result<NaiveDate, Box<dyn std::error::Error>> {
+        self.start_date
+            .checked_add_days(Days::new(u64::try_from(days)?))
+            .ok_or("Failure to determine first loss date".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::BigDecimal;
+
+    use super::*;
+
+    fn run(first_shortage_day: Option<u64>) -> Vec<SimulationDay> {
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        (0..10)
+            .map(|t| SimulationDay {
+                date: (start_date + Days::new(t))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc(),
+                batches: Vec::new(),
+                stock_shortage: (first_shortage_day == Some(t)).then(|| BigDecimal::from(1)),
+                backordered: None,
+                stock_limit_exceeded: None,
+                stock_time_limit_exceeded: None,
+                qc_rejected: None,
+                backlog: Vec::new(),
+                pending_orders: Vec::new(),
+                demand_qty: BigDecimal::from(0),
+                withdrawn_qty: BigDecimal::from(0),
+                withdrawn_age_days: 0.0,
+                is_calculated: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_censor_the_runs_without_loss_at_the_horizon() {
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        let mut counter = FirstLossCounter::new(start_date);
+        for first_shortage_day in [Some(2), Some(4), Some(6), None, None, None, None, None] {
+            counter.add(&run(first_shortage_day));
+        }
+        let first_losses = counter.summarize().unwrap();
+        assert_eq!(first_losses.len(), 2);
+        let missing = &first_losses[0];
+        assert_eq!(missing.loss_type, "missing");
+        assert_eq!(missing.runs, 8);
+        assert_eq!(missing.runs_with_loss, 3);
+        assert_eq!(missing.earliest_days, 2);
+        // Most of the runs have no shortage before the end date.
+        assert_eq!(missing.median_days, None);
+        assert_eq!(missing.median_date, None);
+        assert_eq!(missing.p90_days, None);
+
+        let mut counter = FirstLossCounter::new(start_date);
+        for first_shortage_day in [Some(2), Some(4), Some(6), None] {
+            counter.add(&run(first_shortage_day));
+        }
+        let missing = &counter.summarize().unwrap()[0];
+        assert_eq!(missing.median_days, Some(5));
+        assert_eq!(missing.median_date, NaiveDate::from_ymd_opt(2024, 1, 6));
+        assert_eq!(missing.p90_days, None);
+    }
+}
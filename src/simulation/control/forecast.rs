@@ -0,0 +1,652 @@
+use std::str::FromStr;
+
+use bigdecimal::ToPrimitive;
+use chrono::{Datelike, NaiveDate};
+
+use crate::{data::product_mov_hist::ProductMovDaily, simulation::control::statistics::mean};
+
+pub const WEEKLY_PERIOD: usize = 7;
+pub const YEARLY_PERIOD: usize = 365;
+
+const ALPHA_GRID: [f64; 3] = [0.1, 0.3, 0.5];
+const BETA_GRID: [f64; 3] = [0.01, 0.05, 0.1];
+const GAMMA_GRID: [f64; 3] = [0.05, 0.1, 0.3];
+
+/// Smoothing factor of the demand sizes and intervals, low as the demands are rare.
+pub const CROSTON_ALPHA: f64 = 0.1;
+
+/// Source of the expected movements of each simulated day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForecastModel {
+    /// Average of the same week of year and day of week across the historic years.
+    Average,
+    /// Triple exponential smoothing of the daily history.
+    HoltWinters,
+    /// Intermittent demand, its sizes and intervals smoothed separately.
+    Croston,
+    /// Croston with the Syntetos-Boylan bias correction of the demand size.
+    Sba,
+    /// SBA when the share of days without demand reaches a threshold, else the average.
+    Auto,
+}
+
+impl FromStr for ForecastModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "average" => Ok(ForecastModel::Average),
+            "holt_winters" => Ok(ForecastModel::HoltWinters),
+            "croston" => Ok(ForecastModel::Croston),
+            "sba" => Ok(ForecastModel::Sba),
+            "auto" => Ok(ForecastModel::Auto),
+            other => Err(format!("Unknown forecast model: {:?}", other)),
+        }
+    }
+}
+
+/// Expected movements of a day, the scenarios are drawn around them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyForecast {
+    pub date: NaiveDate,
+    pub entry_qty: f64,
+    pub withdrawal_qty: f64,
+}
+
+/// Smoothing factors of a model, e.g. kept from its selection by backtest.
+/// The Holt-Winters factors are searched on the history when not set.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ForecastParams {
+    pub holt_winters: Option<HoltWintersParams>,
+    pub croston_alpha: Option<f64>,
+}
+
+/// Demand occurring on a day with `probability`, of `size` units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntermittentDemand {
+    pub probability: f64,
+    pub size: f64,
+}
+
+impl ForecastModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ForecastModel::Average => "average",
+            ForecastModel::HoltWinters => "holt_winters",
+            ForecastModel::Croston => "croston",
+            ForecastModel::Sba => "sba",
+            ForecastModel::Auto => "auto",
+        }
+    }
+
+    /// The model to fit: `Auto` resolves, from the withdrawals of the daily history,
+    /// to `Sba` when their share of days without demand reaches `zero_demand_ratio`.
+    pub fn resolve(&self, history: &[ProductMovDaily], zero_demand_ratio: f64) -> Self {
+        if *self != ForecastModel::Auto {
+            return *self;
+        }
+        match daily_series(history, |day| &day.withdrawal_qty) {
+            Some((_, series)) if Croston::zero_demand_ratio(&series) >= zero_demand_ratio => {
+                ForecastModel::Sba
+            }
+            _ => ForecastModel::Average,
+        }
+    }
+
+    /// Withdrawal occurrences and sizes fitted on the daily history by the intermittent models.
+    pub fn intermittent_demand(
+        &self,
+        history: &[ProductMovDaily],
+        params: &ForecastParams,
+    ) -> Option<IntermittentDemand> {
+        let (_, series) = daily_series(history, |day| &day.withdrawal_qty)?;
+        self.fit_intermittent(&series, params)
+    }
+
+    fn fit_intermittent(
+        &self,
+        series: &[f64],
+        params: &ForecastParams,
+    ) -> Option<IntermittentDemand> {
+        let is_bias_corrected = match self {
+            ForecastModel::Croston => false,
+            ForecastModel::Sba => true,
+            _ => return None,
+        };
+        let alpha = params.croston_alpha.unwrap_or(CROSTON_ALPHA);
+        Croston::fit(series, alpha).map(|fit| fit.demand(is_bias_corrected))
+    }
+
+    fn fit_holt_winters(series: &[f64], params: &ForecastParams) -> Option<HoltWinters> {
+        match params.holt_winters {
+            Some(holt_winters) => HoltWinters::fit_with(series, holt_winters),
+            None => HoltWinters::fit(series),
+        }
+    }
+
+    /// Expected values of the `horizon` days after the daily `series` starting at `first_date`.
+    /// `None` for `Auto`, which is resolved first, or when the series is too short for the model.
+    pub fn forecast_series(
+        &self,
+        first_date: NaiveDate,
+        series: &[f64],
+        horizon: usize,
+        params: &ForecastParams,
+    ) -> Option<Vec<f64>> {
+        match self {
+            ForecastModel::Average => seasonal_average(first_date, series, horizon),
+            ForecastModel::HoltWinters => {
+                Some(Self::fit_holt_winters(series, params)?.forecast(horizon))
+            }
+            ForecastModel::Croston | ForecastModel::Sba => {
+                let demand = self.fit_intermittent(series, params)?;
+                Some(vec![demand.probability * demand.size; horizon])
+            }
+            ForecastModel::Auto => None,
+        }
+    }
+
+    /// Smoothing factors of the model fitted on the whole series.
+    pub fn fitted_params(&self, series: &[f64]) -> ForecastParams {
+        match self {
+            ForecastModel::HoltWinters => ForecastParams {
+                holt_winters: HoltWinters::fit(series).map(|model| model.params),
+                croston_alpha: None,
+            },
+            ForecastModel::Croston | ForecastModel::Sba => ForecastParams {
+                holt_winters: None,
+                croston_alpha: Some(CROSTON_ALPHA),
+            },
+            ForecastModel::Average | ForecastModel::Auto => ForecastParams::default(),
+        }
+    }
+
+    /// Expected movements of each day from `first_date` to `last_date`, fitted on the daily
+    /// history, the days without movements up to the day before `first_date` at zero.
+    /// The `params` are selected by backtest on the withdrawals, so they only apply to
+    /// them: the entries are fitted on their own.
+    /// `None` for the models without a daily forecast, or when the history is too short.
+    pub fn forecast_daily(
+        &self,
+        history: &[ProductMovDaily],
+        first_date: NaiveDate,
+        last_date: NaiveDate,
+        params: &ForecastParams,
+    ) -> Option<Vec<DailyForecast>> {
+        if *self != ForecastModel::HoltWinters {
+            return None;
+        }
+        let (series_start, mut entries) = daily_series(history, |day| &day.entry_qty)?;
+        let (_, mut withdrawals) = daily_series(history, |day| &day.withdrawal_qty)?;
+        if first_date <= series_start + chrono::Days::new(entries.len() as u64 - 1) {
+            return None;
+        }
+        let series_end = first_date.pred_opt()?;
+        let series_len = (series_end - series_start).num_days() as usize + 1;
+        entries.resize(series_len, 0.0);
+        withdrawals.resize(series_len, 0.0);
+        let horizon = (last_date - series_end).num_days().max(0) as usize;
+        let entry_forecast = HoltWinters::fit(&entries)?.forecast(horizon);
+        let withdrawal_forecast = Self::fit_holt_winters(&withdrawals, params)?.forecast(horizon);
+        Some(
+            first_date
+                .iter_days()
+                .take_while(|date| *date <= last_date)
+                .map(|date| {
+                    let i = (date - series_end).num_days() as usize - 1;
+                    DailyForecast {
+                        date,
+                        entry_qty: entry_forecast[i],
+                        withdrawal_qty: withdrawal_forecast[i],
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Quantities of each day from the first to the last date of the history,
+/// the days without movements at zero.
+pub fn daily_series(
+    history: &[ProductMovDaily],
+    quantity: fn(&ProductMovDaily) -> &sqlx::types::BigDecimal,
+) -> Option<(NaiveDate, Vec<f64>)> {
+    let first_date = history.iter().map(|day| day.mov_date).min()?;
+    let last_date = history.iter().map(|day| day.mov_date).max()?;
+    let mut series = vec![0.0; (last_date - first_date).num_days() as usize + 1];
+    for day in history {
+        let i = (day.mov_date - first_date).num_days() as usize;
+        series[i] += quantity(day).to_f64().unwrap_or(0.0);
+    }
+    Some((first_date, series))
+}
+
+/// Average of the days of the series with the same week of year and day of week as each
+/// forecast day, as the historic averages of the simulation, or else with the same day of week.
+fn seasonal_average(first_date: NaiveDate, series: &[f64], horizon: usize) -> Option<Vec<f64>> {
+    if series.len() < WEEKLY_PERIOD {
+        return None;
+    }
+    let date_at = |i: usize| first_date + chrono::Days::new(i as u64);
+    Some(
+        (series.len()..series.len() + horizon)
+            .map(|t| {
+                let date = date_at(t);
+                let same_weekday: Vec<usize> = (t % WEEKLY_PERIOD..series.len())
+                    .step_by(WEEKLY_PERIOD)
+                    .collect();
+                let same_week: Vec<f64> = same_weekday
+                    .iter()
+                    .filter(|i| date_at(**i).iso_week().week() == date.iso_week().week())
+                    .map(|i| series[*i])
+                    .collect();
+                if same_week.is_empty() {
+                    mean(
+                        &same_weekday
+                            .iter()
+                            .map(|i| series[*i])
+                            .collect::<Vec<f64>>(),
+                    )
+                } else {
+                    mean(&same_week)
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Smoothing factors of the level, trend and seasonalities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoltWintersParams {
+    pub alpha: f64,
+    pub beta: f64,
+    pub gamma_weekly: f64,
+    pub gamma_yearly: f64,
+}
+
+/// Additive Holt-Winters model with weekly seasonality, and yearly seasonality
+/// when the series covers at least two years.
+#[derive(Debug, Clone)]
+pub struct HoltWinters {
+    pub params: HoltWintersParams,
+    level: f64,
+    trend: f64,
+    weekly: Vec<f64>,
+    yearly: Option<Vec<f64>>,
+    /// Index of the next day after the series.
+    next_index: usize,
+    /// Sum of the squared one-step-ahead errors over the fitted days.
+    pub sse: f64,
+}
+
+impl HoltWinters {
+    /// Fits the series with the smoothing factors of the grid giving the lowest squared errors.
+    pub fn fit(series: &[f64]) -> Option<Self> {
+        let mut best: Option<Self> = None;
+        for alpha in ALPHA_GRID {
+            for beta in BETA_GRID {
+                for gamma_weekly in GAMMA_GRID {
+                    for gamma_yearly in GAMMA_GRID {
+                        let params = HoltWintersParams {
+                            alpha,
+                            beta,
+                            gamma_weekly,
+                            gamma_yearly,
+                        };
+                        let model = Self::fit_with(series, params)?;
+                        if best.as_ref().is_none_or(|best| model.sse < best.sse) {
+                            best = Some(model);
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// The first season, a year or else two weeks, initializes the components
+    /// which are then smoothed over the remaining days.
+    pub fn fit_with(series: &[f64], params: HoltWintersParams) -> Option<Self> {
+        if series.len() < 2 * WEEKLY_PERIOD {
+            return None;
+        }
+        let has_yearly = series.len() >= 2 * YEARLY_PERIOD;
+        let season = if has_yearly {
+            YEARLY_PERIOD
+        } else {
+            WEEKLY_PERIOD
+        };
+        let first = &series[..season];
+        let second = &series[season..(2 * season).min(series.len())];
+        let level = mean(first);
+        let trend = (mean(second) - level) / season as f64;
+        let (weekly, yearly) = if has_yearly {
+            let smoothed: Vec<f64> = (0..season)
+                .map(|i| mean(&first[i.saturating_sub(3)..(i + 4).min(season)]))
+                .collect();
+            let weekly = (0..WEEKLY_PERIOD)
+                .map(|d| {
+                    let deviations: Vec<f64> = (d..season)
+                        .step_by(WEEKLY_PERIOD)
+                        .map(|i| first[i] - smoothed[i])
+                        .collect();
+                    mean(&deviations)
+                })
+                .collect();
+            let yearly = smoothed.iter().map(|value| value - level).collect();
+            (weekly, Some(yearly))
+        } else {
+            (first.iter().map(|value| value - level).collect(), None)
+        };
+
+        let mut model = Self {
+            params,
+            level,
+            trend,
+            weekly,
+            yearly,
+            next_index: season,
+            sse: 0.0,
+        };
+        for &value in &series[season..] {
+            model.update(value);
+        }
+        Some(model)
+    }
+
+    fn update(&mut self, value: f64) {
+        let t = self.next_index;
+        let HoltWintersParams {
+            alpha,
+            beta,
+            gamma_weekly,
+            gamma_yearly,
+        } = self.params;
+        let weekly = self.weekly[t % WEEKLY_PERIOD];
+        let yearly = self.yearly_at(t);
+        let predicted = self.level + self.trend + weekly + yearly;
+        self.sse += (value - predicted).powi(2);
+
+        let level = alpha * (value - weekly - yearly) + (1.0 - alpha) * (self.level + self.trend);
+        self.trend = beta * (level - self.level) + (1.0 - beta) * self.trend;
+        self.weekly[t % WEEKLY_PERIOD] =
+            gamma_weekly * (value - level - yearly) + (1.0 - gamma_weekly) * weekly;
+        if let Some(seasonal) = self.yearly.as_mut() {
+            seasonal[t % YEARLY_PERIOD] =
+                gamma_yearly * (value - level - weekly) + (1.0 - gamma_yearly) * yearly;
+        }
+        self.level = level;
+        self.next_index += 1;
+    }
+
+    fn yearly_at(&self, t: usize) -> f64 {
+        self.yearly
+            .as_ref()
+            .map(|seasonal| seasonal[t % YEARLY_PERIOD])
+            .unwrap_or(0.0)
+    }
+
+    /// Expected values of the `horizon` days after the series, never negative.
+    pub fn forecast(&self, horizon: usize) -> Vec<f64> {
+        (1..=horizon)
+            .map(|h| {
+                let t = self.next_index + h - 1;
+                (self.level
+                    + h as f64 * self.trend
+                    + self.weekly[t % WEEKLY_PERIOD]
+                    + self.yearly_at(t))
+                .max(0.0)
+            })
+            .collect()
+    }
+}
+
+/// Croston's method: the sizes of the demands and the intervals between them
+/// are exponentially smoothed, updated on the days with demand only.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Croston {
+    pub alpha: f64,
+    /// Smoothed size of a demand.
+    pub size: f64,
+    /// Smoothed number of days between two demands.
+    pub interval: f64,
+}
+
+impl Croston {
+    /// `None` when there is no demand in the series.
+    pub fn fit(series: &[f64], alpha: f64) -> Option<Self> {
+        let mut fit: Option<Self> = None;
+        let mut days_since_demand = 0.0;
+        for &value in series {
+            days_since_demand += 1.0;
+            if value <= 0.0 {
+                continue;
+            }
+            fit = Some(match fit {
+                None => Self {
+                    alpha,
+                    size: value,
+                    interval: days_since_demand,
+                },
+                Some(fit) => Self {
+                    alpha,
+                    size: alpha * value + (1.0 - alpha) * fit.size,
+                    interval: alpha * days_since_demand + (1.0 - alpha) * fit.interval,
+                },
+            });
+            days_since_demand = 0.0;
+        }
+        fit
+    }
+
+    pub fn zero_demand_ratio(series: &[f64]) -> f64 {
+        if series.is_empty() {
+            return 0.0;
+        }
+        series.iter().filter(|value| **value <= 0.0).count() as f64 / series.len() as f64
+    }
+
+    /// The SBA bias correction, `1 - alpha / 2`, is applied to the size.
+    pub fn demand(&self, is_bias_corrected: bool) -> IntermittentDemand {
+        let correction = if is_bias_corrected {
+            1.0 - self.alpha / 2.0
+        } else {
+            1.0
+        };
+        IntermittentDemand {
+            probability: (1.0 / self.interval).min(1.0),
+            size: self.size * correction,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::BigDecimal;
+
+    use super::*;
+
+    fn weekly_pattern(day: usize) -> f64 {
+        [10.0, 20.0, 30.0, 40.0, 50.0, 5.0, 0.0][day % WEEKLY_PERIOD]
+    }
+
+    #[test]
+    fn should_forecast_weekly_seasonality_and_trend() {
+        let series: Vec<f64> = (0..8 * WEEKLY_PERIOD)
+            .map(|t| weekly_pattern(t) + 0.5 * t as f64)
+            .collect();
+        let model = HoltWinters::fit(&series).unwrap();
+        let forecast = model.forecast(WEEKLY_PERIOD);
+        for (h, value) in forecast.iter().enumerate() {
+            let t = series.len() + h;
+            let expected = weekly_pattern(t) + 0.5 * t as f64;
+            assert!(
+                (value - expected).abs() < 2.0,
+                "day {}: {} != {}",
+                t,
+                value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn should_fit_yearly_seasonality_with_two_years() {
+        let yearly = |t: usize| {
+            50.0 + 20.0 * (2.0 * std::f64::consts::PI * (t % YEARLY_PERIOD) as f64 / 365.0).sin()
+        };
+        let series: Vec<f64> = (0..3 * YEARLY_PERIOD).map(yearly).collect();
+        let model = HoltWinters::fit(&series).unwrap();
+        assert!(model.yearly.is_some());
+        let forecast = model.forecast(90);
+        let t = series.len() + 89;
+        assert!((forecast[89] - yearly(t)).abs() < 5.0);
+    }
+
+    #[test]
+    fn should_not_fit_short_series() {
+        assert!(HoltWinters::fit(&[1.0; 10]).is_none());
+    }
+
+    #[test]
+    fn should_fit_intermittent_demand_sizes_and_intervals() {
+        let series = [0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0];
+        let fit = Croston::fit(&series, CROSTON_ALPHA).unwrap();
+        assert_eq!(fit.size, 4.0);
+        assert!((fit.interval - 4.0).abs() < 1e-9);
+        assert_eq!(
+            fit.demand(false),
+            IntermittentDemand {
+                probability: 0.25,
+                size: 4.0,
+            }
+        );
+        assert!((fit.demand(true).size - 3.8).abs() < 1e-9);
+        assert!(Croston::fit(&[0.0; 5], CROSTON_ALPHA).is_none());
+    }
+
+    #[test]
+    fn should_resolve_auto_from_the_zero_demand_ratio() {
+        let first_date = NaiveDate::from_ymd_opt(2021, 11, 1).unwrap();
+        let history = |withdrawal: fn(u64) -> i32| -> Vec<ProductMovDaily> {
+            (0..20)
+                .map(|t| ProductMovDaily {
+                    mov_date: first_date + chrono::Days::new(t),
+                    entry_qty: BigDecimal::from(0),
+                    withdrawal_qty: BigDecimal::from(withdrawal(t)),
+                })
+                .collect()
+        };
+        let slow_mover = history(|t| if t % 5 == 0 { 3 } else { 0 });
+        let fast_mover = history(|_| 3);
+        assert_eq!(
+            ForecastModel::Auto.resolve(&slow_mover, 0.5),
+            ForecastModel::Sba
+        );
+        assert_eq!(
+            ForecastModel::Auto.resolve(&fast_mover, 0.5),
+            ForecastModel::Average
+        );
+        assert_eq!(
+            ForecastModel::Croston.resolve(&fast_mover, 0.5),
+            ForecastModel::Croston
+        );
+        let demand = ForecastModel::Sba
+            .intermittent_demand(&slow_mover, &ForecastParams::default())
+            .unwrap();
+        assert!(demand.probability > 0.0 && demand.probability < 1.0);
+        assert!(ForecastModel::HoltWinters
+            .intermittent_demand(&slow_mover, &ForecastParams::default())
+            .is_none());
+    }
+
+    #[test]
+    fn should_forecast_daily_after_the_history() {
+        let first_date = NaiveDate::from_ymd_opt(2021, 11, 1).unwrap();
+        let history: Vec<ProductMovDaily> = (0..8 * WEEKLY_PERIOD as u64)
+            .filter(|t| t % 3 != 0)
+            .map(|t| ProductMovDaily {
+                mov_date: first_date + chrono::Days::new(t),
+                entry_qty: BigDecimal::from(10),
+                withdrawal_qty: BigDecimal::from(30),
+            })
+            .collect();
+        let simulated_from = first_date + chrono::Days::new(8 * WEEKLY_PERIOD as u64 + 3);
+        let forecast = ForecastModel::HoltWinters
+            .forecast_daily(
+                &history,
+                simulated_from,
+                simulated_from + chrono::Days::new(4),
+                &ForecastParams::default(),
+            )
+            .unwrap();
+        assert_eq!(forecast.len(), 5);
+        assert_eq!(forecast[0].date, simulated_from);
+        assert!(forecast.iter().all(|day| day.withdrawal_qty >= 0.0));
+        // The params selected on the withdrawals do not apply to the entries.
+        let selected = ForecastModel::HoltWinters
+            .forecast_daily(
+                &history,
+                simulated_from,
+                simulated_from + chrono::Days::new(4),
+                &ForecastParams {
+                    holt_winters: Some(HoltWintersParams {
+                        alpha: 0.9,
+                        beta: 0.0,
+                        gamma_weekly: 0.0,
+                        gamma_yearly: 0.0,
+                    }),
+                    croston_alpha: None,
+                },
+            )
+            .unwrap();
+        assert!(selected
+            .iter()
+            .zip(&forecast)
+            .all(|(selected, fitted)| selected.entry_qty == fitted.entry_qty));
+        // The weeks without movements before the simulation lower the forecast.
+        let after_a_pause = ForecastModel::HoltWinters
+            .forecast_daily(
+                &history,
+                simulated_from + chrono::Days::new(28),
+                simulated_from + chrono::Days::new(32),
+                &ForecastParams::default(),
+            )
+            .unwrap();
+        let total = |forecast: &[DailyForecast]| -> f64 {
+            forecast.iter().map(|day| day.withdrawal_qty).sum()
+        };
+        assert!(total(&after_a_pause) < total(&forecast));
+        assert!(ForecastModel::Average
+            .forecast_daily(
+                &history,
+                simulated_from,
+                simulated_from,
+                &ForecastParams::default()
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn should_forecast_series_with_the_same_week_average() {
+        let first_date = NaiveDate::from_ymd_opt(2019, 1, 7).unwrap();
+        let week_of = |t: u64| (first_date + chrono::Days::new(t)).iso_week().week() as f64;
+        let series: Vec<f64> = (0..742).map(week_of).collect();
+        let forecast = ForecastModel::Average
+            .forecast_series(first_date, &series, 7, &ForecastParams::default())
+            .unwrap();
+        assert_eq!(forecast, vec![week_of(742); 7]);
+        assert!(ForecastModel::Auto
+            .forecast_series(first_date, &series, 7, &ForecastParams::default())
+            .is_none());
+        let params = ForecastModel::Sba.fitted_params(&series);
+        assert_eq!(params.croston_alpha, Some(CROSTON_ALPHA));
+        assert_eq!(
+            ForecastModel::Sba
+                .forecast_series(first_date, &series, 3, &params)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
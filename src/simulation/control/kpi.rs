@@ -0,0 +1,123 @@
+use bigdecimal::{FromPrimitive, ToPrimitive};
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::product_simulation_kpi::NewProductSimulationKpi,
+    simulation::control::{
+        cost::LossCost,
+        per_day::SimulationDay,
+        statistics::{percentile, sorted},
+    },
+};
+
+/// Inventory KPIs of a single simulation run.
+/// A KPI is `None` when it is undefined for the run, e.g. fill rate without demand.
+#[derive(Debug, Clone, Default)]
+pub struct RunKpis {
+    /// Share of the demand served from the stock.
+    pub fill_rate: Option<f64>,
+    /// Share of the days without stock shortage or backorder.
+    pub cycle_service_level: Option<f64>,
+    pub average_stock: Option<f64>,
+    /// Average stock divided by the average daily demand.
+    pub days_of_cover: Option<f64>,
+    /// Withdrawn quantity over the simulated period divided by the average stock.
+    pub stock_turnover: Option<f64>,
+    /// Age in days of the withdrawn batches, weighted by the withdrawn quantity.
+    pub average_batch_age_at_withdrawal: Option<f64>,
+    /// Money lost over the simulated period.
+    pub loss_cost: Option<f64>,
+}
+
+impl RunKpis {
+    pub fn from_days(days: &[SimulationDay], loss_cost: &LossCost) -> Self {
+        if days.is_empty() {
+            return Self::default();
+        }
+        let days_len = days.len() as f64;
+        let demand = Self::sum(days.iter().map(|day| &day.demand_qty));
+        let withdrawn = Self::sum(days.iter().map(|day| &day.withdrawn_qty));
+        let withdrawn_age_days: f64 = days.iter().map(|day| day.withdrawn_age_days).sum();
+        let days_without_shortage = days
+            .iter()
+            .filter(|day| day.stock_shortage.is_none() && day.backordered.is_none())
+            .count() as f64;
+        let average_stock = days
+            .iter()
+            .filter_map(|day| day.stock_qty().to_f64())
+            .sum::<f64>()
+            / days_len;
+
+        Self {
+            fill_rate: Self::ratio(withdrawn, demand),
+            cycle_service_level: Some(days_without_shortage / days_len),
+            average_stock: Some(average_stock),
+            days_of_cover: Self::ratio(average_stock, demand / days_len),
+            stock_turnover: Self::ratio(withdrawn, average_stock),
+            average_batch_age_at_withdrawal: Self::ratio(withdrawn_age_days, withdrawn),
+            loss_cost: Some(loss_cost.of_days(days)),
+        }
+    }
+
+    fn sum<'a>(values: impl Iterator<Item = &'a BigDecimal>) -> f64 {
+        values.filter_map(|value| value.to_f64()).sum()
+    }
+
+    fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+        if denominator > 0.0 {
+            Some(numerator / denominator)
+        } else {
+            None
+        }
+    }
+}
+
+type KpiGetter = fn(&RunKpis) -> Option<f64>;
+
+/// Collects the KPIs of each run to summarize their distribution.
+#[derive(Default)]
+pub struct KpiCounter {
+    all: Vec<RunKpis>,
+}
+
+impl KpiCounter {
+    pub fn add(&mut self, days: &[SimulationDay], loss_cost: &LossCost) {
+        self.all.push(RunKpis::from_days(days, loss_cost));
+    }
+
+    pub fn summarize(&self) -> Result<Vec<NewProductSimulationKpi>, Box<dyn std::error::Error>> {
+        let kpis: [(&str, KpiGetter); 7] = [
+            ("fill_rate", |kpis| kpis.fill_rate),
+            ("cycle_service_level", |kpis| kpis.cycle_service_level),
+            ("average_stock", |kpis| kpis.average_stock),
+            ("days_of_cover", |kpis| kpis.days_of_cover),
+            ("stock_turnover", |kpis| kpis.stock_turnover),
+            ("average_batch_age_at_withdrawal", |kpis| {
+                kpis.average_batch_age_at_withdrawal
+            }),
+            ("loss_cost", |kpis| kpis.loss_cost),
+        ];
+        let mut summaries = Vec::new();
+        for (kpi, get) in kpis {
+            let values = sorted(self.all.iter().filter_map(get).collect());
+            if values.is_empty() {
+                continue;
+            }
+            summaries.push(NewProductSimulationKpi {
+                kpi: kpi.to_owned(),
+                runs: values.len() as i32,
+                mean: Self::to_decimal(values.iter().sum::<f64>() / values.len() as f64)?,
+                p5: Self::to_decimal(percentile(&values, 5.0).unwrap_or_default())?,
+                p25: Self::to_decimal(percentile(&values, 25.0).unwrap_or_default())?,
+                p50: Self::to_decimal(percentile(&values, 50.0).unwrap_or_default())?,
+                p75: Self::to_decimal(percentile(&values, 75.0).unwrap_or_default())?,
+                p95: Self::to_decimal(percentile(&values, 95.0).unwrap_or_default())?,
+            });
+        }
+        Ok(summaries)
+    }
+
+    fn to_decimal(value: f64) -> Result<BigDecimal, Box<dyn std::error::Error>> {
+        BigDecimal::from_f64(value).ok_or(format!("Invalid KPI value: {}", value).into())
+    }
+}
@@ -0,0 +1,300 @@
+use std::str::FromStr;
+
+use bigdecimal::FromPrimitive;
+use chrono::NaiveDate;
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::{
+        product_forecast_backtest::NewProductForecastBacktest, product_mov_hist::ProductMovDaily,
+    },
+    simulation::control::{
+        forecast::{daily_series, ForecastModel, ForecastParams, WEEKLY_PERIOD},
+        statistics::{mean, percentile, sorted},
+    },
+};
+
+/// Candidates of the backtest, `Auto` being a rule over them rather than a model.
+pub const CANDIDATE_MODELS: [ForecastModel; 4] = [
+    ForecastModel::Average,
+    ForecastModel::HoltWinters,
+    ForecastModel::Croston,
+    ForecastModel::Sba,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMetric {
+    Mae,
+    Mase,
+    PinballLoss,
+}
+
+impl FromStr for SelectionMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "mae" => Ok(SelectionMetric::Mae),
+            "mase" => Ok(SelectionMetric::Mase),
+            "pinball_loss" => Ok(SelectionMetric::PinballLoss),
+            other => Err(format!("Unknown selection metric: {:?}", other)),
+        }
+    }
+}
+
+/// Errors of the withdrawal forecasts of a model over the backtest origins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForecastScore {
+    pub mae: f64,
+    /// MAE scaled by the MAE of the weekly naive forecast in the training days,
+    /// averaged over the origins. Not set when the training days never vary.
+    pub mase: Option<f64>,
+    pub pinball_loss: f64,
+}
+
+impl ForecastScore {
+    fn of(&self, metric: SelectionMetric) -> Option<f64> {
+        match metric {
+            SelectionMetric::Mae => Some(self.mae),
+            SelectionMetric::Mase => self.mase,
+            SelectionMetric::PinballLoss => Some(self.pinball_loss),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelBacktest {
+    pub model: ForecastModel,
+    pub score: ForecastScore,
+    /// Fitted on the whole history, to be used by the simulation when selected.
+    pub params: ForecastParams,
+    pub is_selected: bool,
+}
+
+impl ModelBacktest {
+    pub fn to_new(
+        &self,
+        selection: &ModelSelection,
+    ) -> Result<NewProductForecastBacktest, Box<dyn std::error::Error>> {
+        let decimal =
+            |value: f64| BigDecimal::from_f64(value).ok_or("Invalid forecast backtest score");
+        let holt_winters = self.params.holt_winters;
+        Ok(NewProductForecastBacktest {
+            model: self.model.name().to_owned(),
+            origins: i16::try_from(selection.origins)?,
+            horizon_days: i16::try_from(selection.horizon_days)?,
+            mae: decimal(self.score.mae)?,
+            mase: self.score.mase.map(decimal).transpose()?,
+            pinball_loss: decimal(self.score.pinball_loss)?,
+            is_selected: self.is_selected,
+            alpha: holt_winters
+                .map(|params| params.alpha)
+                .or(self.params.croston_alpha)
+                .map(decimal)
+                .transpose()?,
+            beta: holt_winters
+                .map(|params| decimal(params.beta))
+                .transpose()?,
+            gamma_weekly: holt_winters
+                .map(|params| decimal(params.gamma_weekly))
+                .transpose()?,
+            gamma_yearly: holt_winters
+                .map(|params| decimal(params.gamma_yearly))
+                .transpose()?,
+        })
+    }
+}
+
+/// Backtests the candidate models over rolling origins: each origin forecasts the
+/// `horizon_days` after it from the history before it, the last origin ending the history.
+pub struct ModelSelection {
+    pub candidates: Vec<ForecastModel>,
+    pub origins: usize,
+    pub horizon_days: usize,
+    /// Quantile of the pinball loss, e.g. 0.9 to score the upper withdrawals.
+    pub pinball_quantile: f64,
+    pub metric: SelectionMetric,
+}
+
+impl ModelSelection {
+    /// Scores of the candidates with a forecast at every origin, the best one by the
+    /// metric selected; falling back to the MAE when the metric is not set for every one.
+    pub fn run(&self, history: &[ProductMovDaily]) -> Vec<ModelBacktest> {
+        let Some((first_date, series)) = daily_series(history, |day| &day.withdrawal_qty) else {
+            return Vec::new();
+        };
+        let mut backtests: Vec<ModelBacktest> = self
+            .candidates
+            .iter()
+            .filter_map(|model| {
+                Some(ModelBacktest {
+                    model: *model,
+                    score: self.score(*model, first_date, &series)?,
+                    params: model.fitted_params(&series),
+                    is_selected: false,
+                })
+            })
+            .collect();
+        let metric = if backtests
+            .iter()
+            .all(|backtest| backtest.score.of(self.metric).is_some())
+        {
+            self.metric
+        } else {
+            SelectionMetric::Mae
+        };
+        let best = backtests
+            .iter()
+            .enumerate()
+            .filter_map(|(i, backtest)| Some((i, backtest.score.of(metric)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i);
+        if let Some(best) = best {
+            backtests[best].is_selected = true;
+        }
+        backtests
+    }
+
+    /// `None` when the model cannot forecast at some origin, e.g. a too short history.
+    /// The quantile forecast of the pinball loss is the forecast plus the quantile
+    /// of the errors of the model over the horizon before the origin.
+    pub fn score(
+        &self,
+        model: ForecastModel,
+        first_date: NaiveDate,
+        series: &[f64],
+    ) -> Option<ForecastScore> {
+        let params = ForecastParams::default();
+        let mut absolute_errors: Vec<f64> = Vec::new();
+        let mut pinball_losses: Vec<f64> = Vec::new();
+        let mut scaled_maes: Vec<f64> = Vec::new();
+        for k in (1..=self.origins).rev() {
+            let origin = series.len().checked_sub(k * self.horizon_days)?;
+            let calibration_origin = origin.checked_sub(self.horizon_days)?;
+            let actual = &series[origin..origin + self.horizon_days];
+            let forecast =
+                model.forecast_series(first_date, &series[..origin], self.horizon_days, &params)?;
+            let calibration_errors: Vec<f64> = model
+                .forecast_series(
+                    first_date,
+                    &series[..calibration_origin],
+                    self.horizon_days,
+                    &params,
+                )?
+                .iter()
+                .zip(&series[calibration_origin..origin])
+                .map(|(forecast, actual)| actual - forecast)
+                .collect();
+            let error_quantile =
+                percentile(&sorted(calibration_errors), 100.0 * self.pinball_quantile)?;
+
+            let errors: Vec<f64> = actual
+                .iter()
+                .zip(&forecast)
+                .map(|(actual, forecast)| (actual - forecast).abs())
+                .collect();
+            if let Some(scale) = naive_scale(&series[..origin]) {
+                scaled_maes.push(mean(&errors) / scale);
+            }
+            absolute_errors.extend(errors);
+            pinball_losses.extend(actual.iter().zip(&forecast).map(|(actual, forecast)| {
+                pinball_loss(
+                    *actual,
+                    (forecast + error_quantile).max(0.0),
+                    self.pinball_quantile,
+                )
+            }));
+        }
+        if absolute_errors.is_empty() {
+            return None;
+        }
+        Some(ForecastScore {
+            mae: mean(&absolute_errors),
+            mase: if scaled_maes.len() == self.origins {
+                Some(mean(&scaled_maes))
+            } else {
+                None
+            },
+            pinball_loss: mean(&pinball_losses),
+        })
+    }
+}
+
+/// Mean absolute error of the weekly naive forecast, the value of the week before.
+fn naive_scale(series: &[f64]) -> Option<f64> {
+    let errors: Vec<f64> = series
+        .windows(WEEKLY_PERIOD + 1)
+        .map(|window| (window[WEEKLY_PERIOD] - window[0]).abs())
+        .collect();
+    Some(mean(&errors)).filter(|scale| *scale > 0.0)
+}
+
+pub fn pinball_loss(actual: f64, quantile_forecast: f64, quantile: f64) -> f64 {
+    if actual >= quantile_forecast {
+        quantile * (actual - quantile_forecast)
+    } else {
+        (1.0 - quantile) * (quantile_forecast - actual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::BigDecimal;
+
+    use super::*;
+
+    fn selection(metric: SelectionMetric) -> ModelSelection {
+        ModelSelection {
+            candidates: CANDIDATE_MODELS.to_vec(),
+            origins: 3,
+            horizon_days: 14,
+            pinball_quantile: 0.9,
+            metric,
+        }
+    }
+
+    fn history(withdrawal: fn(u64) -> u64) -> Vec<ProductMovDaily> {
+        let first_date = NaiveDate::from_ymd_opt(2021, 1, 4).unwrap();
+        (0..140)
+            .map(|t| ProductMovDaily {
+                mov_date: first_date + chrono::Days::new(t),
+                entry_qty: BigDecimal::from(0),
+                withdrawal_qty: BigDecimal::from(withdrawal(t)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_compute_the_pinball_loss() {
+        assert_eq!(pinball_loss(10.0, 8.0, 0.9), 0.9 * 2.0);
+        assert!((pinball_loss(8.0, 10.0, 0.9) - 0.1 * 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn should_select_the_seasonal_model_for_a_trending_weekly_pattern() {
+        let backtests = selection(SelectionMetric::Mase).run(&history(|t| {
+            [10, 20, 30, 40, 50, 5, 0][(t % 7) as usize] + t / 2
+        }));
+        assert_eq!(backtests.len(), CANDIDATE_MODELS.len());
+        let selected: Vec<&ModelBacktest> = backtests.iter().filter(|b| b.is_selected).collect();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].model, ForecastModel::HoltWinters);
+        assert!(selected[0].params.holt_winters.is_some());
+        assert!(backtests.iter().all(|b| b.score.mase.is_some()));
+    }
+
+    #[test]
+    fn should_fall_back_to_the_mae_when_the_mase_is_not_set() {
+        let backtests = selection(SelectionMetric::Mase).run(&history(|_| 5));
+        assert!(backtests.iter().all(|b| b.score.mase.is_none()));
+        assert_eq!(backtests.iter().filter(|b| b.is_selected).count(), 1);
+    }
+
+    #[test]
+    fn should_not_score_without_enough_history() {
+        let mut selection = selection(SelectionMetric::Mae);
+        selection.origins = 20;
+        assert!(selection.run(&history(|t| t)).is_empty());
+        assert!(selection.run(&[]).is_empty());
+    }
+}
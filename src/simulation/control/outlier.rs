@@ -0,0 +1,362 @@
+use std::str::FromStr;
+
+use bigdecimal::{FromPrimitive, ToPrimitive};
+use chrono::{Datelike, NaiveDate};
+use sqlx::types::BigDecimal;
+use uuid::Uuid;
+
+use crate::{
+    data::{product_mov_hist::ProductMovDaily, product_mov_outlier::NewProductMovOutlier},
+    simulation::control::statistics::{percentile, sorted},
+};
+
+/// Scales the MAD to the standard deviation of normally distributed movements.
+const MAD_TO_STD: f64 = 1.4826;
+/// Positive movements needed to fit a bound.
+pub const MIN_OBSERVATIONS: usize = 8;
+/// Weeks of year on each side of a day within which the same days of week make its season.
+const SEASON_HALF_WEEKS: u32 = 2;
+const WEEKS_IN_A_YEAR: u32 = 52;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlierMethod {
+    /// `threshold` scaled MADs above the median.
+    Mad,
+    /// `threshold` interquartile ranges above the third quartile.
+    Iqr,
+}
+
+impl FromStr for OutlierMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "mad" => Ok(OutlierMethod::Mad),
+            "iqr" => Ok(OutlierMethod::Iqr),
+            other => Err(format!("Unknown outlier method: {:?}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlierAction {
+    Clip,
+    Exclude,
+}
+
+impl FromStr for OutlierAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "clip" => Ok(OutlierAction::Clip),
+            "exclude" => Ok(OutlierAction::Exclude),
+            other => Err(format!("Unknown outlier action: {:?}", other)),
+        }
+    }
+}
+
+/// Upper bound of the ratio of a movement to its seasonal median, with the median ratio
+/// that replaces the excluded movements of the daily series, which need a value every day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierBound {
+    pub median: f64,
+    pub upper: f64,
+}
+
+/// Movement of the history above its bound, with the quantity it is cleaned to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outlier {
+    pub mov_date: NaiveDate,
+    pub quantity: &'static str,
+    pub qty: f64,
+    pub bound: f64,
+    pub cleaned_qty: f64,
+}
+
+impl Outlier {
+    pub fn to_new(
+        &self,
+        product_id: Uuid,
+        action: OutlierAction,
+    ) -> Result<NewProductMovOutlier, Box<dyn std::error::Error>> {
+        let decimal = |value: f64| BigDecimal::from_f64(value).ok_or("Invalid outlier quantity");
+        Ok(NewProductMovOutlier {
+            product_id,
+            mov_date: self.mov_date,
+            quantity: self.quantity.to_owned(),
+            qty: decimal(self.qty)?,
+            bound: decimal(self.bound)?,
+            cleaned_qty: decimal(self.cleaned_qty)?,
+            is_excluded: action == OutlierAction::Exclude,
+        })
+    }
+}
+
+/// Cleaning of the one-off movements of the history, e.g. a bulk order or a data-entry
+/// error. The movements are compared with their season, the median of the same days of
+/// week within `SEASON_HALF_WEEKS` weeks of year, so that a seasonal peak is not taken
+/// for an outlier. Only the movements above the bound are outliers: they cannot be below
+/// zero, and the days without movements are left to the forecast models.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierCleaning {
+    pub method: OutlierMethod,
+    pub threshold: f64,
+    pub action: OutlierAction,
+}
+
+impl OutlierCleaning {
+    /// Bound of the positive values, `None` with less than `MIN_OBSERVATIONS` of them
+    /// or when most of them are the same, as then any other one would be an outlier.
+    pub fn bound(&self, values: &[f64]) -> Option<OutlierBound> {
+        let values = sorted(values.iter().copied().filter(|v| *v > 0.0).collect());
+        if values.len() < MIN_OBSERVATIONS {
+            return None;
+        }
+        let median = percentile(&values, 50.0)?;
+        let (center, scale) = match self.method {
+            OutlierMethod::Mad => {
+                let deviations = sorted(values.iter().map(|v| (v - median).abs()).collect());
+                (median, MAD_TO_STD * percentile(&deviations, 50.0)?)
+            }
+            OutlierMethod::Iqr => {
+                let third_quartile = percentile(&values, 75.0)?;
+                (third_quartile, third_quartile - percentile(&values, 25.0)?)
+            }
+        };
+        if scale <= 0.0 {
+            return None;
+        }
+        Some(OutlierBound {
+            median,
+            upper: center + self.threshold * scale,
+        })
+    }
+
+    /// Bounds of the ratios of the positive movements to their seasonal medians.
+    pub fn fit(&self, history: &[ProductMovDaily]) -> OutlierBounds {
+        let ratios = |quantity: Quantity| -> Vec<f64> {
+            seasonal_movements(history, quantity)
+                .into_iter()
+                .filter_map(|(qty, season)| season.filter(|s| *s > 0.0).map(|s| qty / s))
+                .collect()
+        };
+        OutlierBounds {
+            entry_qty: self.bound(&ratios(|day| &day.entry_qty)),
+            withdrawal_qty: self.bound(&ratios(|day| &day.withdrawal_qty)),
+            action: self.action,
+        }
+    }
+}
+
+type Quantity = fn(&ProductMovDaily) -> &BigDecimal;
+type QuantityMut = fn(&mut ProductMovDaily) -> &mut BigDecimal;
+
+/// Each movement of the history with the median of the other movements of its season,
+/// `None` without any.
+fn seasonal_movements(history: &[ProductMovDaily], quantity: Quantity) -> Vec<(f64, Option<f64>)> {
+    let movements: Vec<(NaiveDate, f64)> = history
+        .iter()
+        .map(|day| (day.mov_date, quantity(day).to_f64().unwrap_or(0.0)))
+        .collect();
+    let is_same_season = |a: NaiveDate, b: NaiveDate| {
+        let weeks = a.iso_week().week().abs_diff(b.iso_week().week());
+        a.weekday() == b.weekday() && weeks.min(WEEKS_IN_A_YEAR - weeks) <= SEASON_HALF_WEEKS
+    };
+    movements
+        .iter()
+        .map(|(mov_date, qty)| {
+            let season = sorted(
+                movements
+                    .iter()
+                    .filter(|(other_date, _)| {
+                        other_date != mov_date && is_same_season(*mov_date, *other_date)
+                    })
+                    .map(|(_, other_qty)| *other_qty)
+                    .collect(),
+            );
+            (*qty, percentile(&season, 50.0))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierBounds {
+    pub entry_qty: Option<OutlierBound>,
+    pub withdrawal_qty: Option<OutlierBound>,
+    pub action: OutlierAction,
+}
+
+impl OutlierBounds {
+    /// Cleaned daily history, with the outliers found. The bounds are scaled by the
+    /// seasonal median of each movement, the excluded movements being replaced by
+    /// the median ratio of their season.
+    pub fn clean(&self, history: &[ProductMovDaily]) -> (Vec<ProductMovDaily>, Vec<Outlier>) {
+        let mut cleaned = history.to_vec();
+        let mut outliers = Vec::new();
+        let quantities: [(&'static str, Quantity, QuantityMut, Option<OutlierBound>); 2] = [
+            (
+                "entry",
+                |day| &day.entry_qty,
+                |day| &mut day.entry_qty,
+                self.entry_qty,
+            ),
+            (
+                "withdrawal",
+                |day| &day.withdrawal_qty,
+                |day| &mut day.withdrawal_qty,
+                self.withdrawal_qty,
+            ),
+        ];
+        for (quantity, value, value_mut, bound) in quantities {
+            let Some(bound) = bound else {
+                continue;
+            };
+            let movements = seasonal_movements(history, value);
+            for (day, (qty, season)) in cleaned.iter_mut().zip(movements) {
+                let Some(season) = season.filter(|s| *s > 0.0 && qty > s * bound.upper) else {
+                    continue;
+                };
+                let cleaned_qty = match self.action {
+                    OutlierAction::Clip => season * bound.upper,
+                    OutlierAction::Exclude => season * bound.median,
+                };
+                let Some(cleaned_decimal) = BigDecimal::from_f64(cleaned_qty) else {
+                    continue;
+                };
+                *value_mut(day) = cleaned_decimal;
+                outliers.push(Outlier {
+                    mov_date: day.mov_date,
+                    quantity,
+                    qty,
+                    bound: season * bound.upper,
+                    cleaned_qty,
+                });
+            }
+        }
+        outliers.sort_by_key(|outlier| outlier.mov_date);
+        (cleaned, outliers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Days;
+
+    use super::*;
+
+    fn cleaning(method: OutlierMethod, action: OutlierAction) -> OutlierCleaning {
+        OutlierCleaning {
+            method,
+            threshold: 3.5,
+            action,
+        }
+    }
+
+    /// Withdrawals of 8 to 12 with a bulk order of 10,000 on the 10th day.
+    fn history() -> Vec<ProductMovDaily> {
+        let first_date = NaiveDate::from_ymd_opt(2021, 1, 4).unwrap();
+        (0..30)
+            .map(|t| ProductMovDaily {
+                mov_date: first_date + Days::new(t),
+                entry_qty: BigDecimal::from(if t % 7 == 0 { 50 } else { 0 }),
+                withdrawal_qty: BigDecimal::from(if t == 9 { 10_000 } else { 8 + t % 5 }),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_fit_the_upper_bound() {
+        let values: Vec<f64> = (0..10).map(|t| (8 + t % 5) as f64).collect();
+        let bound = cleaning(OutlierMethod::Mad, OutlierAction::Clip)
+            .bound(&values)
+            .unwrap();
+        assert_eq!(bound.median, 10.0);
+        assert!((bound.upper - (10.0 + 3.5 * MAD_TO_STD * 1.0)).abs() < 1e-9);
+        let bound = cleaning(OutlierMethod::Iqr, OutlierAction::Clip)
+            .bound(&values)
+            .unwrap();
+        assert_eq!(bound.upper, 11.0 + 3.5 * 2.0);
+        // Too few, or all the same.
+        assert!(cleaning(OutlierMethod::Mad, OutlierAction::Clip)
+            .bound(&values[..5])
+            .is_none());
+        assert!(cleaning(OutlierMethod::Mad, OutlierAction::Clip)
+            .bound(&[50.0; 10])
+            .is_none());
+    }
+
+    #[test]
+    fn should_clip_the_outliers() {
+        let history = history();
+        let bounds = cleaning(OutlierMethod::Mad, OutlierAction::Clip).fit(&history);
+        assert!(bounds.entry_qty.is_none());
+        let (cleaned, outliers) = bounds.clean(&history);
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].mov_date, history[9].mov_date);
+        assert_eq!(outliers[0].quantity, "withdrawal");
+        assert_eq!(outliers[0].qty, 10_000.0);
+        // Clipped to the bound over the season of 9, 10 and 11.
+        let upper = bounds.withdrawal_qty.unwrap().upper;
+        assert!((outliers[0].bound - 10.0 * upper).abs() < 1e-9);
+        assert_eq!(outliers[0].cleaned_qty, outliers[0].bound);
+        assert_eq!(
+            cleaned[9].withdrawal_qty.to_f64().unwrap(),
+            outliers[0].bound
+        );
+        assert_eq!(cleaned[8].withdrawal_qty, history[8].withdrawal_qty);
+        assert_eq!(cleaned[0].entry_qty, history[0].entry_qty);
+
+        let new_outlier = outliers[0]
+            .to_new(Uuid::from_u128(0), OutlierAction::Clip)
+            .unwrap();
+        assert_eq!(new_outlier.mov_date, history[9].mov_date);
+        assert!(!new_outlier.is_excluded);
+    }
+
+    #[test]
+    fn should_replace_the_excluded_outliers_by_the_seasonal_median() {
+        let history = history();
+        let bounds = cleaning(OutlierMethod::Iqr, OutlierAction::Exclude).fit(&history);
+        let (cleaned, outliers) = bounds.clean(&history);
+        assert_eq!(outliers.len(), 1);
+        let median = bounds.withdrawal_qty.unwrap().median;
+        assert!((outliers[0].cleaned_qty - 10.0 * median).abs() < 1e-9);
+        assert!((8.0..=12.0).contains(&cleaned[9].withdrawal_qty.to_f64().unwrap()));
+    }
+
+    #[test]
+    fn should_keep_the_seasonal_peaks() {
+        // Two years of 10 to 12 withdrawals a day, 100 to 102 from the week 30 to 33.
+        let first_date = NaiveDate::from_ymd_opt(2021, 1, 4).unwrap();
+        let mut history: Vec<ProductMovDaily> = (0..104 * 7)
+            .map(|t| {
+                let mov_date = first_date + Days::new(t);
+                let peak = (30..=33).contains(&mov_date.iso_week().week());
+                ProductMovDaily {
+                    mov_date,
+                    entry_qty: BigDecimal::from(0),
+                    withdrawal_qty: BigDecimal::from(if peak { 100 } else { 10 } + t % 3),
+                }
+            })
+            .collect();
+        let bounds = cleaning(OutlierMethod::Mad, OutlierAction::Clip).fit(&history);
+        assert!(bounds.clean(&history).1.is_empty());
+
+        history[100].withdrawal_qty = BigDecimal::from(500);
+        let bounds = cleaning(OutlierMethod::Mad, OutlierAction::Clip).fit(&history);
+        let (_, outliers) = bounds.clean(&history);
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].mov_date, history[100].mov_date);
+    }
+
+    #[test]
+    fn should_parse_the_method_and_action() {
+        assert_eq!(OutlierMethod::from_str("iqr"), Ok(OutlierMethod::Iqr));
+        assert_eq!(
+            OutlierAction::from_str("exclude"),
+            Ok(OutlierAction::Exclude)
+        );
+        assert!(OutlierMethod::from_str("zscore").is_err());
+    }
+}
@@ -1,24 +1,269 @@
-use crate::data::product_mov_hist::ProductMovHist;
+use crate::data::{product_mov_event::ProductMovHourlyProfile, product_mov_hist::ProductMovHist};
+use crate::simulation::control::{
+    cost::LossCost,
+    forecast::{DailyForecast, IntermittentDemand},
+    regime::DemandRegimes,
+    statistics::draw_standard_normal,
+};
 
+use bigdecimal::{FromPrimitive, ToPrimitive, Zero};
+use rand::Rng;
 use sqlx::types::BigDecimal;
 use uuid::Uuid;
 
 use std::{collections::HashMap, str::FromStr};
 
-use chrono::{DateTime, Datelike, Utc};
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeDelta, Timelike, Utc};
 
-#[derive(Debug)]
+pub const HOURS_IN_A_DAY: u32 = 24;
+/// ISO weeks of year are from 1 to 53, as in the history.
+const MAX_WEEK_OF_YEAR: i16 = 53;
+
+pub const DEFAULT_DAY_EVENTS_ORDER: [DayEvent; 3] =
+    [DayEvent::Withdraw, DayEvent::Entry, DayEvent::RmExpired];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayEvent {
+    Withdraw,
+    Entry,
+    RmExpired,
+}
+
+impl FromStr for DayEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "withdraw" => Ok(DayEvent::Withdraw),
+            "entry" => Ok(DayEvent::Entry),
+            "expiration" => Ok(DayEvent::RmExpired),
+            other => Err(format!("Unknown day event: {:?}", other)),
+        }
+    }
+}
+
+impl DayEvent {
+    /// Parses a comma separated list like `"expiration,entry,withdraw"`.
+    /// Every event must be present exactly once.
+    pub fn parse_order(order: &str) -> Result<Vec<DayEvent>, String> {
+        let events = order
+            .split(',')
+            .map(DayEvent::from_str)
+            .collect::<Result<Vec<DayEvent>, String>>()?;
+        let is_complete = events.len() == DEFAULT_DAY_EVENTS_ORDER.len()
+            && DEFAULT_DAY_EVENTS_ORDER
+                .iter()
+                .all(|event| events.contains(event));
+        if !is_complete {
+            return Err(format!(
+                "Day events order must contain withdraw, entry and expiration exactly once: {:?}",
+                order
+            ));
+        }
+        Ok(events)
+    }
+}
+
+/// What happens to the demand that can't be met by the stock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortageMode {
+    /// The missing quantity is lost.
+    LostSales,
+    /// The missing quantity waits in a backlog to be filled by the next entries.
+    Backorder,
+}
+
+impl FromStr for ShortageMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "lost_sales" => Ok(ShortageMode::LostSales),
+            "backorder" => Ok(ShortageMode::Backorder),
+            other => Err(format!("Unknown shortage mode: {:?}", other)),
+        }
+    }
+}
+
+/// Movements of the days of the year without history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryImputation {
+    /// No movement.
+    Zero,
+    /// Average of the same day of week in the closest weeks with history, before or after.
+    NeighbouringWeeks,
+    /// Average of the same day of week over all the weeks.
+    SameWeekday,
+    /// Average of all the days.
+    OverallMean,
+}
+
+impl HistoryImputation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoryImputation::Zero => "zero",
+            HistoryImputation::NeighbouringWeeks => "neighbouring_weeks",
+            HistoryImputation::SameWeekday => "same_weekday",
+            HistoryImputation::OverallMean => "overall_mean",
+        }
+    }
+}
+
+impl FromStr for HistoryImputation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "zero" => Ok(HistoryImputation::Zero),
+            "neighbouring_weeks" => Ok(HistoryImputation::NeighbouringWeeks),
+            "same_weekday" => Ok(HistoryImputation::SameWeekday),
+            "overall_mean" => Ok(HistoryImputation::OverallMean),
+            other => Err(format!("Unknown history imputation: {:?}", other)),
+        }
+    }
+}
+
+/// Where the expected movements of a day come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateHistSource {
+    Forecast,
+    Historic,
+    /// The day of the year has no history, its movements being imputed (or zero).
+    Imputed,
+}
+
+/// Orders placed when the stock position drops to the reorder point,
+/// bringing it back up to the stock maximum quantity.
+/// The orders replace the historic entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplenishmentPolicy {
+    pub reorder_point: u64,
+    pub lead_time_days: u64,
+}
+
+/// Known one-off movement added on top of the scenario of the step containing its date,
+/// e.g. an extra delivery or a large customer order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledMovement {
+    pub date: DateTime<Utc>,
+    pub entry_qty: BigDecimal,
+    pub withdrawal_qty: BigDecimal,
+}
+
+/// Random draws fixed for all the days of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunDraws {
+    pub entry_trend_factor: f64,
+    pub withdrawal_trend_factor: f64,
+    regime_multiplier_by_date: HashMap<NaiveDate, f64>,
+}
+
+impl RunDraws {
+    /// 1 outside of a drawn regime path.
+    fn get_regime_multiplier(&self, date: &DateTime<Utc>) -> f64 {
+        self.regime_multiplier_by_date
+            .get(&date.date_naive())
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+/// Demand uplift of a promotion or known event over the historic estimate of each day
+/// within its dates: the withdrawals are multiplied, then the additive quantity is added
+/// as is, after the scenario factors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Promotion {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub withdrawal_multiplier: BigDecimal,
+    pub withdrawal_additive_qty: BigDecimal,
+}
+
+/// Shock of a stress test, from `start_date` until before `end_date`. The factors multiply
+/// the scenario entries, the scenario withdrawals and the stock maximum quantity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressShock {
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub entry_factor: f64,
+    pub withdrawal_factor: f64,
+    pub capacity_factor: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpirationComparison {
+    /// A batch expires on the day after its deadline_date (`deadline_date < date`).
+    Exclusive,
+    /// A batch expires on its deadline_date (`deadline_date <= date`).
+    Inclusive,
+}
+
+impl ExpirationComparison {
+    pub fn from_inclusive(is_inclusive: bool) -> Self {
+        if is_inclusive {
+            ExpirationComparison::Inclusive
+        } else {
+            ExpirationComparison::Exclusive
+        }
+    }
+
+    pub fn is_expired(&self, deadline_date: &DateTime<Utc>, date: &DateTime<Utc>) -> bool {
+        match self {
+            ExpirationComparison::Exclusive => deadline_date < date,
+            ExpirationComparison::Inclusive => deadline_date <= date,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SimulationParameters {
     pub stock_maximum_quantity: u64,
     pub new_batch_default_expiration_days: u64,
+    /// Shelf life of the new batches in hours, for sub-day steps. Takes precedence over
+    /// `new_batch_default_expiration_days` when set.
+    pub new_batch_expiration_hours: Option<u64>,
+    pub day_events_order: Vec<DayEvent>,
+    pub expiration_comparison: ExpirationComparison,
+    pub time_step_hours: u32,
+    pub qc_hold_days: u64,
+    pub qc_rejection_rate: f64,
+    pub shortage_mode: ShortageMode,
+    pub random_range_factor: f64,
+    /// Multiplying factors over the historic quantities, e.g. 1.2 for a 20% uptrend.
+    pub entry_trend_factor: f64,
+    pub withdrawal_trend_factor: f64,
+    /// Standard errors of the log of the trend factors, a factor drawn for each run when set.
+    pub entry_trend_log_std: f64,
+    pub withdrawal_trend_log_std: f64,
+    pub loss_cost: LossCost,
+    pub replenishment: Option<ReplenishmentPolicy>,
+    pub scheduled_movements: Vec<ScheduledMovement>,
+    pub promotions: Vec<Promotion>,
+    pub stress_shock: Option<StressShock>,
+    /// Withdrawals drawn as demand occurrences of a given size, instead of a daily average.
+    pub intermittent_demand: Option<IntermittentDemand>,
+    /// Withdrawals switching between quiet and busy regimes, a path drawn for each run.
+    pub demand_regimes: Option<DemandRegimes>,
+    /// Every random draw is replaced by its expected value, e.g. for the stress tests.
+    pub deterministic: bool,
     historic_by_woy_and_dow: HashMap<i16, HashMap<i16, ProductMovHist>>,
+    forecast_by_date: HashMap<NaiveDate, ProductMovHist>,
+    history_imputation: HistoryImputation,
+    imputed_by_woy_and_dow: HashMap<i16, HashMap<i16, ProductMovHist>>,
     default_hist: ProductMovHist,
+    entry_share_by_hour: HashMap<u32, BigDecimal>,
+    withdrawal_share_by_hour: HashMap<u32, BigDecimal>,
 }
 
 impl SimulationParameters {
+    /// Expected movements of the day of `date`: its daily forecast when there is one,
+    /// else the historic average of the same week of year and day of week,
+    /// imputed when the history has none.
     pub fn get_date_hist(&self, date: &DateTime<Utc>) -> &ProductMovHist {
-        let woy = date.iso_week().week0() as i16;
-        let dow = (date.weekday().num_days_from_sunday()) as i16;
+        if let Some(forecast) = self.forecast_by_date.get(&date.date_naive()) {
+            return forecast;
+        }
+        let (woy, dow) = Self::get_woy_and_dow(date);
         let date_hist_opt = self
             .historic_by_woy_and_dow
             .get(&woy)
@@ -27,9 +272,431 @@ impl SimulationParameters {
             "get_date_hist date: {:?}, woy: {:?}, dow: {:?}, hist: {:?}",
             date, woy, dow, date_hist_opt
         );
-        match date_hist_opt {
-            Some(hist) => hist,
-            None => &self.default_hist,
+        date_hist_opt
+            .or_else(|| {
+                self.imputed_by_woy_and_dow
+                    .get(&woy)
+                    .and_then(|week| week.get(&dow))
+            })
+            .unwrap_or(&self.default_hist)
+    }
+
+    pub fn get_date_hist_source(&self, date: &DateTime<Utc>) -> DateHistSource {
+        if self.forecast_by_date.contains_key(&date.date_naive()) {
+            return DateHistSource::Forecast;
+        }
+        let (woy, dow) = Self::get_woy_and_dow(date);
+        match self
+            .historic_by_woy_and_dow
+            .get(&woy)
+            .and_then(|week| week.get(&dow))
+        {
+            Some(_) => DateHistSource::Historic,
+            None => DateHistSource::Imputed,
+        }
+    }
+
+    fn get_woy_and_dow(date: &DateTime<Utc>) -> (i16, i16) {
+        (
+            date.iso_week().week() as i16,
+            date.weekday().num_days_from_sunday() as i16,
+        )
+    }
+
+    pub fn get_history_imputation(&self) -> HistoryImputation {
+        self.history_imputation
+    }
+
+    /// Imputes the movements of every week of year and day of week without history.
+    pub fn set_history_imputation(&mut self, history_imputation: HistoryImputation) {
+        self.history_imputation = history_imputation;
+        let mut imputed_by_woy_and_dow = HashMap::new();
+        for woy in 1..=MAX_WEEK_OF_YEAR {
+            for dow in 0..7 {
+                let is_missing = self
+                    .historic_by_woy_and_dow
+                    .get(&woy)
+                    .and_then(|week| week.get(&dow))
+                    .is_none();
+                if let Some(hist) = is_missing.then(|| self.impute(woy, dow)).flatten() {
+                    imputed_by_woy_and_dow
+                        .entry(woy)
+                        .or_insert_with(HashMap::new)
+                        .insert(dow, hist);
+                }
+            }
+        }
+        self.imputed_by_woy_and_dow = imputed_by_woy_and_dow;
+    }
+
+    /// `None` when there is no history to impute from.
+    fn impute(&self, woy: i16, dow: i16) -> Option<ProductMovHist> {
+        let hists: Vec<&ProductMovHist> = match self.history_imputation {
+            HistoryImputation::Zero => return None,
+            HistoryImputation::NeighbouringWeeks => (1..=MAX_WEEK_OF_YEAR / 2 + 1)
+                .map(|distance| {
+                    [woy - distance, woy + distance]
+                        .iter()
+                        .filter_map(|week| {
+                            self.historic_by_woy_and_dow
+                                .get(&((week - 1).rem_euclid(MAX_WEEK_OF_YEAR) + 1))
+                                .and_then(|week| week.get(&dow))
+                        })
+                        .collect::<Vec<&ProductMovHist>>()
+                })
+                .find(|hists| !hists.is_empty())
+                .unwrap_or_default(),
+            HistoryImputation::SameWeekday => self
+                .historic_by_woy_and_dow
+                .values()
+                .filter_map(|week| week.get(&dow))
+                .collect(),
+            HistoryImputation::OverallMean => self
+                .historic_by_woy_and_dow
+                .values()
+                .flat_map(|week| week.values())
+                .collect(),
+        };
+        let first = hists.first()?;
+        let count = BigDecimal::from(hists.len() as u64);
+        Some(ProductMovHist {
+            product_id: first.product_id,
+            entry_qty: hists.iter().map(|hist| &hist.entry_qty).sum::<BigDecimal>() / &count,
+            withdrawal_qty: hists
+                .iter()
+                .map(|hist| &hist.withdrawal_qty)
+                .sum::<BigDecimal>()
+                / &count,
+            week_of_year: woy,
+            day_of_week: dow,
+        })
+    }
+
+    /// Scenario movements of the step starting at `date`: the step historic quantities,
+    /// multiplied by the promotions, by the trend and regime factors drawn for the run,
+    /// by the stress and by a random factor within the random range, drawn for each movement,
+    /// plus the additive quantities of the promotions and the scheduled movements of the step.
+    /// With an intermittent demand, the withdrawal only happens when its occurrence is drawn.
+    pub fn get_scenario_hist(&self, date: &DateTime<Utc>, draws: &RunDraws) -> ProductMovHist {
+        let step_hist = self.get_promoted_step_hist(date);
+        let promotion_qty = self.get_promotion_additive_qty(date);
+        let scheduled = self.get_scheduled_movement(date);
+        let (entry_factor, withdrawal_factor) = match self.get_stress_shock(date) {
+            Some(shock) => (
+                draws.entry_trend_factor * shock.entry_factor,
+                draws.withdrawal_trend_factor * shock.withdrawal_factor,
+            ),
+            None => (draws.entry_trend_factor, draws.withdrawal_trend_factor),
+        };
+        let withdrawal_factor = withdrawal_factor * draws.get_regime_multiplier(date);
+        let mut rng = rand::thread_rng();
+        let (entry_qty, withdrawal_qty) =
+            if !self.is_random() && entry_factor == 1.0 && withdrawal_factor == 1.0 {
+                (
+                    step_hist.entry_qty.clone(),
+                    step_hist.withdrawal_qty.clone(),
+                )
+            } else {
+                (
+                    &step_hist.entry_qty * self.draw_scenario_factor(entry_factor, &mut rng),
+                    &step_hist.withdrawal_qty
+                        * self.draw_scenario_factor(withdrawal_factor, &mut rng),
+                )
+            };
+        let withdrawal_qty = match &self.intermittent_demand {
+            Some(demand) => self.draw_intermittent_withdrawal(demand, withdrawal_qty, &mut rng),
+            None => withdrawal_qty,
+        };
+        ProductMovHist {
+            entry_qty: entry_qty + scheduled.entry_qty,
+            withdrawal_qty: withdrawal_qty + promotion_qty + scheduled.withdrawal_qty,
+            ..step_hist
+        }
+    }
+
+    /// Whole units of the demand size when a demand occurs within the step,
+    /// the occurrence probability being shared across the steps of a day. The size is
+    /// rounded up with a probability of its fractional part, keeping its mean.
+    /// The expected quantity when deterministic.
+    fn draw_intermittent_withdrawal<R: Rng>(
+        &self,
+        demand: &IntermittentDemand,
+        size_qty: BigDecimal,
+        rng: &mut R,
+    ) -> BigDecimal {
+        let step_share = self.time_step_hours.min(HOURS_IN_A_DAY) as f64 / HOURS_IN_A_DAY as f64;
+        let probability = (demand.probability * step_share).clamp(0.0, 1.0);
+        if self.deterministic {
+            return size_qty * BigDecimal::from_f64(probability).unwrap_or(BigDecimal::from(0));
+        }
+        if !rng.gen_bool(probability) || size_qty <= BigDecimal::zero() {
+            return BigDecimal::from(0);
+        }
+        let size = size_qty.to_f64().unwrap_or(0.0);
+        let units = size.floor() + if rng.gen_bool(size.fract()) { 1.0 } else { 0.0 };
+        BigDecimal::from_f64(units).unwrap_or(BigDecimal::from(0))
+    }
+
+    /// Step historic quantities multiplied by the promotions active on the day of `date`.
+    /// The multipliers of overlapping promotions compound.
+    pub fn get_promoted_step_hist(&self, date: &DateTime<Utc>) -> ProductMovHist {
+        let step_hist = self.get_step_hist(date);
+        let mut active = self.get_active_promotions(date).peekable();
+        if active.peek().is_none() {
+            return step_hist;
+        }
+        let multiplier = active.fold(BigDecimal::from(1), |acc, promotion| {
+            acc * &promotion.withdrawal_multiplier
+        });
+        ProductMovHist {
+            withdrawal_qty: &step_hist.withdrawal_qty * multiplier,
+            ..step_hist
+        }
+    }
+
+    /// Daily additive quantities of the promotions active on the day of `date`,
+    /// split evenly across the steps of the day.
+    pub fn get_promotion_additive_qty(&self, date: &DateTime<Utc>) -> BigDecimal {
+        let additive_qty = self
+            .get_active_promotions(date)
+            .fold(BigDecimal::from(0), |acc, promotion| {
+                acc + &promotion.withdrawal_additive_qty
+            });
+        if additive_qty.is_zero() {
+            return additive_qty;
+        }
+        let step_share = BigDecimal::from(self.time_step_hours.min(HOURS_IN_A_DAY))
+            / BigDecimal::from(HOURS_IN_A_DAY);
+        additive_qty * step_share
+    }
+
+    fn get_active_promotions(&self, date: &DateTime<Utc>) -> impl Iterator<Item = &Promotion> {
+        let day = date.date_naive();
+        self.promotions
+            .iter()
+            .filter(move |promotion| promotion.start_date <= day && day <= promotion.end_date)
+    }
+
+    /// Draws of a run without uncertainty: the fitted trend factors and no regimes.
+    pub fn fitted_run_draws(&self) -> RunDraws {
+        RunDraws {
+            entry_trend_factor: self.entry_trend_factor,
+            withdrawal_trend_factor: self.withdrawal_trend_factor,
+            regime_multiplier_by_date: HashMap::new(),
+        }
+    }
+
+    /// Draws of a run from `first_date` to `final_date`: the trend factors drawn
+    /// log-normally around the fitted ones and the regime path.
+    pub fn draw_run<R: Rng>(
+        &self,
+        first_date: NaiveDate,
+        final_date: NaiveDate,
+        rng: &mut R,
+    ) -> RunDraws {
+        let mut draws = self.fitted_run_draws();
+        if self.deterministic {
+            return draws;
+        }
+        if self.entry_trend_log_std > 0.0 {
+            draws.entry_trend_factor *=
+                (self.entry_trend_log_std * draw_standard_normal(rng)).exp();
+        }
+        if self.withdrawal_trend_log_std > 0.0 {
+            draws.withdrawal_trend_factor *=
+                (self.withdrawal_trend_log_std * draw_standard_normal(rng)).exp();
+        }
+        if let Some(regimes) = &self.demand_regimes {
+            draws.regime_multiplier_by_date = regimes
+                .draw_path(first_date, final_date, rng)
+                .into_iter()
+                .collect();
+        }
+        draws
+    }
+
+    fn is_random(&self) -> bool {
+        self.random_range_factor > 0.0 && !self.deterministic
+    }
+
+    pub fn get_stress_shock(&self, date: &DateTime<Utc>) -> Option<&StressShock> {
+        self.stress_shock
+            .as_ref()
+            .filter(|shock| shock.start_date <= *date && *date < shock.end_date)
+    }
+
+    /// Deadline of a new batch entering on `date`.
+    pub fn get_new_batch_deadline(&self, date: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.new_batch_expiration_hours {
+            Some(hours) => date.checked_add_signed(TimeDelta::hours(i64::try_from(hours).ok()?)),
+            None => date.checked_add_days(Days::new(self.new_batch_default_expiration_days)),
+        }
+    }
+
+    /// Shelf life of the new batches in days, replacing the one in hours.
+    pub fn set_new_batch_expiration_days(&mut self, days: u64) {
+        self.new_batch_default_expiration_days = days;
+        self.new_batch_expiration_hours = None;
+    }
+
+    /// Stock maximum quantity on `date`, reduced by a capacity stress shock.
+    pub fn get_stock_maximum_quantity(&self, date: &DateTime<Utc>) -> u64 {
+        match self.get_stress_shock(date) {
+            Some(shock) => {
+                (self.stock_maximum_quantity as f64 * shock.capacity_factor).floor() as u64
+            }
+            None => self.stock_maximum_quantity,
+        }
+    }
+
+    /// Share of the replenishment orders due on `date` delivered by the supplier, the rest
+    /// waiting for the supply to resume: 0 during a full outage, 1 without a supplier outage.
+    pub fn get_supply_factor(&self, date: &DateTime<Utc>) -> f64 {
+        self.get_stress_shock(date)
+            .map_or(1.0, |shock| shock.entry_factor.clamp(0.0, 1.0))
+    }
+
+    /// Sum of the scheduled movements within the step starting at `date`.
+    pub fn get_scheduled_movement(&self, date: &DateTime<Utc>) -> ScheduledMovement {
+        let step_end = *date + TimeDelta::hours(self.time_step_hours as i64);
+        self.scheduled_movements
+            .iter()
+            .filter(|movement| *date <= movement.date && movement.date < step_end)
+            .fold(
+                ScheduledMovement {
+                    date: *date,
+                    entry_qty: BigDecimal::from(0),
+                    withdrawal_qty: BigDecimal::from(0),
+                },
+                |mut acc, movement| {
+                    acc.entry_qty += &movement.entry_qty;
+                    acc.withdrawal_qty += &movement.withdrawal_qty;
+                    acc
+                },
+            )
+    }
+
+    fn draw_scenario_factor<R: Rng>(&self, trend_factor: f64, rng: &mut R) -> BigDecimal {
+        let random_factor = if self.is_random() {
+            rng.gen_range((1.0 - self.random_range_factor)..=(1.0 + self.random_range_factor))
+        } else {
+            1.0
+        };
+        BigDecimal::from_f64((trend_factor * random_factor).max(0.0)).unwrap_or(BigDecimal::from(1))
+    }
+
+    /// Movements of the step starting at `date`. The daily historic quantities are split
+    /// by the intra-day profile, or evenly across the hours when there is no profile.
+    pub fn get_step_hist(&self, date: &DateTime<Utc>) -> ProductMovHist {
+        let date_hist = self.get_date_hist(date);
+        let step_hist = if self.time_step_hours >= HOURS_IN_A_DAY {
+            date_hist.clone()
+        } else {
+            ProductMovHist {
+                entry_qty: &date_hist.entry_qty
+                    * Self::get_step_share(
+                        &self.entry_share_by_hour,
+                        date.hour(),
+                        self.time_step_hours,
+                    ),
+                withdrawal_qty: &date_hist.withdrawal_qty
+                    * Self::get_step_share(
+                        &self.withdrawal_share_by_hour,
+                        date.hour(),
+                        self.time_step_hours,
+                    ),
+                ..date_hist.clone()
+            }
+        };
+        match &self.intermittent_demand {
+            // The size of a demand occurrence, its probability is drawn with the scenario.
+            Some(demand) => ProductMovHist {
+                withdrawal_qty: BigDecimal::from_f64(demand.size).unwrap_or(BigDecimal::from(0)),
+                ..step_hist
+            },
+            None => step_hist,
+        }
+    }
+
+    /// Replaces the historic averages of the forecast days.
+    pub fn set_daily_forecast(&mut self, forecast: Vec<DailyForecast>) {
+        self.forecast_by_date = forecast
+            .into_iter()
+            .map(|day| {
+                let hist = ProductMovHist {
+                    entry_qty: BigDecimal::from_f64(day.entry_qty).unwrap_or(BigDecimal::from(0)),
+                    withdrawal_qty: BigDecimal::from_f64(day.withdrawal_qty)
+                        .unwrap_or(BigDecimal::from(0)),
+                    week_of_year: day.date.iso_week().week() as i16,
+                    day_of_week: day.date.weekday().num_days_from_sunday() as i16,
+                    ..Self::get_default_hist()
+                };
+                (day.date, hist)
+            })
+            .collect();
+    }
+
+    pub fn set_intraday_profile(&mut self, profile: Vec<ProductMovHourlyProfile>) {
+        let mut entry_share_by_hour = HashMap::new();
+        let mut withdrawal_share_by_hour = HashMap::new();
+        for e in profile {
+            let hour = e.hour_of_day as u32;
+            entry_share_by_hour.insert(hour, e.entry_share);
+            withdrawal_share_by_hour.insert(hour, e.withdrawal_share);
+        }
+        self.entry_share_by_hour = Self::non_zero_profile_or_empty(entry_share_by_hour);
+        self.withdrawal_share_by_hour = Self::non_zero_profile_or_empty(withdrawal_share_by_hour);
+    }
+
+    /// Random share of a batch rejected by quality control, drawn uniformly around the
+    /// rejection rate, as far as the closest of 0 and 1, so that its mean is the rate.
+    /// The rate itself when deterministic.
+    pub fn draw_qc_rejection_share<R: Rng>(&self, rng: &mut R) -> BigDecimal {
+        if self.qc_rejection_rate <= 0.0 {
+            return BigDecimal::from(0);
+        }
+        let rate = self.qc_rejection_rate.min(1.0);
+        if self.deterministic {
+            return BigDecimal::from_f64(rate).unwrap_or(BigDecimal::from(0));
+        }
+        let spread = rate.min(1.0 - rate);
+        let share = rng.gen_range((rate - spread)..=(rate + spread));
+        BigDecimal::from_f64(share).unwrap_or(BigDecimal::from(0))
+    }
+
+    pub fn validate_time_step_hours(time_step_hours: u32) -> Result<u32, String> {
+        if time_step_hours == 0 || !HOURS_IN_A_DAY.is_multiple_of(time_step_hours) {
+            return Err(format!(
+                "Time step must divide a day in equal parts, got {} hours",
+                time_step_hours
+            ));
+        }
+        Ok(time_step_hours)
+    }
+
+    fn get_step_share(
+        share_by_hour: &HashMap<u32, BigDecimal>,
+        first_hour: u32,
+        time_step_hours: u32,
+    ) -> BigDecimal {
+        if share_by_hour.is_empty() {
+            return BigDecimal::from(time_step_hours) / BigDecimal::from(HOURS_IN_A_DAY);
+        }
+        (first_hour..first_hour + time_step_hours)
+            .filter_map(|hour| share_by_hour.get(&(hour % HOURS_IN_A_DAY)))
+            .fold(BigDecimal::from(0), |acc, share| acc + share)
+    }
+
+    fn non_zero_profile_or_empty(
+        share_by_hour: HashMap<u32, BigDecimal>,
+    ) -> HashMap<u32, BigDecimal> {
+        let total = share_by_hour
+            .values()
+            .fold(BigDecimal::from(0), |acc, share| acc + share);
+        if !total.is_zero() {
+            share_by_hour
+        } else {
+            HashMap::new()
         }
     }
 
@@ -41,8 +708,33 @@ impl SimulationParameters {
         Self {
             stock_maximum_quantity: stock_maximum_quantity,
             new_batch_default_expiration_days: new_batch_default_expiration_days,
+            new_batch_expiration_hours: None,
+            day_events_order: DEFAULT_DAY_EVENTS_ORDER.to_vec(),
+            expiration_comparison: ExpirationComparison::Exclusive,
+            time_step_hours: HOURS_IN_A_DAY,
+            qc_hold_days: 0,
+            qc_rejection_rate: 0.0,
+            shortage_mode: ShortageMode::LostSales,
+            random_range_factor: 0.0,
+            entry_trend_factor: 1.0,
+            withdrawal_trend_factor: 1.0,
+            entry_trend_log_std: 0.0,
+            withdrawal_trend_log_std: 0.0,
+            loss_cost: LossCost::default(),
+            replenishment: None,
+            scheduled_movements: Vec::new(),
+            promotions: Vec::new(),
+            stress_shock: None,
+            intermittent_demand: None,
+            demand_regimes: None,
+            deterministic: false,
             historic_by_woy_and_dow: Self::group_by_woy_and_dow(historic),
+            forecast_by_date: HashMap::new(),
+            history_imputation: HistoryImputation::Zero,
+            imputed_by_woy_and_dow: HashMap::new(),
             default_hist: Self::get_default_hist(),
+            entry_share_by_hour: HashMap::new(),
+            withdrawal_share_by_hour: HashMap::new(),
         }
     }
 
@@ -76,6 +768,491 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_parse_day_events_order() {
+        assert_eq!(
+            DayEvent::parse_order("expiration, entry,withdraw").unwrap(),
+            vec![DayEvent::RmExpired, DayEvent::Entry, DayEvent::Withdraw]
+        );
+        assert!(DayEvent::parse_order("withdraw,entry").is_err());
+        assert!(DayEvent::parse_order("withdraw,entry,entry").is_err());
+        assert!(DayEvent::parse_order("withdraw,entry,shipping").is_err());
+    }
+
+    #[test]
+    fn test_expiration_comparison() {
+        let date = DateTime::parse_from_rfc3339("2024-01-10T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert!(!ExpirationComparison::Exclusive.is_expired(&date, &date));
+        assert!(ExpirationComparison::Inclusive.is_expired(&date, &date));
+    }
+
+    #[test]
+    fn test_get_step_hist() {
+        let product_id = Uuid::from_str("d0bd335e-fc46-408d-90fb-209ccc521fa1").unwrap();
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![ProductMovHist {
+                product_id,
+                entry_qty: BigDecimal::from(48),
+                withdrawal_qty: BigDecimal::from(24),
+                week_of_year: 1,
+                day_of_week: 1,
+            }],
+        );
+        let date = DateTime::parse_from_rfc3339("2024-01-01T06:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            sim_param.get_step_hist(&date).entry_qty,
+            BigDecimal::from(48)
+        );
+
+        sim_param.time_step_hours = 6;
+        let hist = sim_param.get_step_hist(&date);
+        assert_eq!(hist.entry_qty, BigDecimal::from(12));
+        assert_eq!(hist.withdrawal_qty, BigDecimal::from(6));
+
+        sim_param.set_intraday_profile(vec![
+            ProductMovHourlyProfile {
+                hour_of_day: 7,
+                entry_share: BigDecimal::from_str("0.75").unwrap(),
+                withdrawal_share: BigDecimal::from(0),
+            },
+            ProductMovHourlyProfile {
+                hour_of_day: 15,
+                entry_share: BigDecimal::from_str("0.25").unwrap(),
+                withdrawal_share: BigDecimal::from(0),
+            },
+        ]);
+        let hist = sim_param.get_step_hist(&date);
+        assert_eq!(hist.entry_qty, BigDecimal::from(36));
+        assert_eq!(hist.withdrawal_qty, BigDecimal::from(6));
+    }
+
+    #[test]
+    fn test_get_scenario_hist_within_random_range() {
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![ProductMovHist {
+                product_id: Uuid::from_u128(0),
+                entry_qty: BigDecimal::from(100),
+                withdrawal_qty: BigDecimal::from(100),
+                week_of_year: 1,
+                day_of_week: 1,
+            }],
+        );
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            sim_param
+                .get_scenario_hist(&date, &sim_param.fitted_run_draws())
+                .entry_qty,
+            BigDecimal::from(100)
+        );
+
+        sim_param.random_range_factor = 0.05;
+        for _ in 0..100 {
+            let hist = sim_param.get_scenario_hist(&date, &sim_param.fitted_run_draws());
+            let entry_qty = hist.entry_qty.to_f64().unwrap();
+            let withdrawal_qty = hist.withdrawal_qty.to_f64().unwrap();
+            assert!((95.0..=105.0).contains(&entry_qty));
+            assert!((95.0..=105.0).contains(&withdrawal_qty));
+        }
+    }
+
+    #[test]
+    fn test_get_scenario_hist_with_trend_factors() {
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![ProductMovHist {
+                product_id: Uuid::from_u128(0),
+                entry_qty: BigDecimal::from(100),
+                withdrawal_qty: BigDecimal::from(100),
+                week_of_year: 1,
+                day_of_week: 1,
+            }],
+        );
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        sim_param.entry_trend_factor = 1.5;
+        sim_param.withdrawal_trend_factor = 0.5;
+        let hist = sim_param.get_scenario_hist(&date, &sim_param.fitted_run_draws());
+        assert_eq!(hist.entry_qty, BigDecimal::from(150));
+        assert_eq!(hist.withdrawal_qty, BigDecimal::from(50));
+    }
+
+    #[test]
+    fn test_get_scenario_hist_with_scheduled_movements() {
+        let mut sim_param = SimulationParameters::new(1000, 5, vec![]);
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        sim_param.scheduled_movements = vec![
+            ScheduledMovement {
+                date: date + TimeDelta::hours(9),
+                entry_qty: BigDecimal::from(200),
+                withdrawal_qty: BigDecimal::from(0),
+            },
+            ScheduledMovement {
+                date: date + TimeDelta::hours(15),
+                entry_qty: BigDecimal::from(0),
+                withdrawal_qty: BigDecimal::from(30),
+            },
+            ScheduledMovement {
+                date: date + TimeDelta::days(1),
+                entry_qty: BigDecimal::from(500),
+                withdrawal_qty: BigDecimal::from(0),
+            },
+        ];
+        let hist = sim_param.get_scenario_hist(&date, &sim_param.fitted_run_draws());
+        assert_eq!(hist.entry_qty, BigDecimal::from(200));
+        assert_eq!(hist.withdrawal_qty, BigDecimal::from(30));
+
+        sim_param.time_step_hours = 12;
+        let hist = sim_param.get_scenario_hist(
+            &(date + TimeDelta::hours(12)),
+            &sim_param.fitted_run_draws(),
+        );
+        assert_eq!(hist.entry_qty, BigDecimal::from(0));
+        assert_eq!(hist.withdrawal_qty, BigDecimal::from(30));
+    }
+
+    #[test]
+    fn test_get_scenario_hist_with_promotions() {
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![
+                ProductMovHist {
+                    product_id: Uuid::from_u128(0),
+                    entry_qty: BigDecimal::from(0),
+                    withdrawal_qty: BigDecimal::from(40),
+                    week_of_year: 1,
+                    day_of_week: 1,
+                },
+                ProductMovHist {
+                    product_id: Uuid::from_u128(0),
+                    entry_qty: BigDecimal::from(0),
+                    withdrawal_qty: BigDecimal::from(40),
+                    week_of_year: 1,
+                    day_of_week: 2,
+                },
+            ],
+        );
+        let monday = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let tuesday = monday + TimeDelta::days(1);
+        sim_param.promotions = vec![
+            Promotion {
+                start_date: monday.date_naive(),
+                end_date: monday.date_naive(),
+                withdrawal_multiplier: BigDecimal::from_str("1.5").unwrap(),
+                withdrawal_additive_qty: BigDecimal::from(0),
+            },
+            Promotion {
+                start_date: monday.date_naive(),
+                end_date: tuesday.date_naive(),
+                withdrawal_multiplier: BigDecimal::from(1),
+                withdrawal_additive_qty: BigDecimal::from(12),
+            },
+        ];
+        assert_eq!(
+            sim_param
+                .get_scenario_hist(&monday, &sim_param.fitted_run_draws())
+                .withdrawal_qty,
+            BigDecimal::from(72)
+        );
+        assert_eq!(
+            sim_param
+                .get_scenario_hist(&tuesday, &sim_param.fitted_run_draws())
+                .withdrawal_qty,
+            BigDecimal::from(52)
+        );
+
+        sim_param.time_step_hours = 12;
+        assert_eq!(
+            sim_param
+                .get_scenario_hist(&tuesday, &sim_param.fitted_run_draws())
+                .withdrawal_qty,
+            BigDecimal::from(26)
+        );
+
+        // The additive quantity is not scaled by the trend.
+        sim_param.time_step_hours = 24;
+        sim_param.withdrawal_trend_factor = 2.0;
+        assert_eq!(
+            sim_param
+                .get_scenario_hist(&tuesday, &sim_param.fitted_run_draws())
+                .withdrawal_qty,
+            BigDecimal::from(92)
+        );
+    }
+
+    #[test]
+    fn test_draw_run_trend() {
+        let mut sim_param = SimulationParameters::new(1000, 5, vec![]);
+        let mut rng = rand::thread_rng();
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(
+            sim_param.draw_run(day, day, &mut rng),
+            sim_param.fitted_run_draws()
+        );
+
+        sim_param.withdrawal_trend_factor = 1.3;
+        sim_param.withdrawal_trend_log_std = 0.1;
+        let draws = sim_param.draw_run(day, day, &mut rng);
+        assert_eq!(draws.entry_trend_factor, 1.0);
+        assert!(draws.withdrawal_trend_factor > 0.0);
+        assert_ne!(draws.withdrawal_trend_factor, 1.3);
+
+        sim_param.deterministic = true;
+        assert_eq!(
+            sim_param.draw_run(day, day, &mut rng),
+            sim_param.fitted_run_draws()
+        );
+    }
+
+    #[test]
+    fn test_get_scenario_hist_with_demand_regimes() {
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![ProductMovHist {
+                product_id: Uuid::from_u128(0),
+                entry_qty: BigDecimal::from(0),
+                withdrawal_qty: BigDecimal::from(100),
+                week_of_year: 1,
+                day_of_week: 1,
+            }],
+        );
+        let monday = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        // Regimes that never switch.
+        sim_param.demand_regimes = Some(DemandRegimes {
+            multipliers: [0.5, 2.0],
+            transitions: [[1.0, 0.0], [0.0, 1.0]],
+        });
+        assert_eq!(
+            sim_param
+                .get_scenario_hist(&monday, &sim_param.fitted_run_draws())
+                .withdrawal_qty,
+            BigDecimal::from(100)
+        );
+        let draws = sim_param.draw_run(
+            monday.date_naive(),
+            monday.date_naive(),
+            &mut rand::thread_rng(),
+        );
+        let withdrawal_qty = sim_param.get_scenario_hist(&monday, &draws).withdrawal_qty;
+        assert!([BigDecimal::from(50), BigDecimal::from(200)].contains(&withdrawal_qty));
+
+        sim_param.deterministic = true;
+        let draws = sim_param.draw_run(
+            monday.date_naive(),
+            monday.date_naive(),
+            &mut rand::thread_rng(),
+        );
+        assert_eq!(
+            sim_param.get_scenario_hist(&monday, &draws).withdrawal_qty,
+            BigDecimal::from(100)
+        );
+    }
+
+    #[test]
+    fn test_get_date_hist_from_daily_forecast() {
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![ProductMovHist {
+                product_id: Uuid::from_u128(0),
+                entry_qty: BigDecimal::from(100),
+                withdrawal_qty: BigDecimal::from(100),
+                week_of_year: 1,
+                day_of_week: 1,
+            }],
+        );
+        let monday = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        sim_param.set_daily_forecast(vec![DailyForecast {
+            date: monday.date_naive(),
+            entry_qty: 12.5,
+            withdrawal_qty: 40.0,
+        }]);
+        let hist = sim_param.get_date_hist(&monday);
+        assert_eq!(hist.entry_qty, BigDecimal::from_str("12.5").unwrap());
+        assert_eq!(hist.withdrawal_qty, BigDecimal::from(40));
+        let tuesday_hist = sim_param.get_date_hist(&(monday + TimeDelta::days(1)));
+        assert_eq!(tuesday_hist.withdrawal_qty, BigDecimal::from(0));
+    }
+
+    #[test]
+    fn test_get_date_hist_imputed() {
+        let hist = |week_of_year: i16, day_of_week: i16, withdrawal_qty: i32| ProductMovHist {
+            product_id: Uuid::from_u128(0),
+            entry_qty: BigDecimal::from(0),
+            withdrawal_qty: BigDecimal::from(withdrawal_qty),
+            week_of_year,
+            day_of_week,
+        };
+        let mut sim_param = SimulationParameters::new(
+            1000,
+            5,
+            vec![hist(3, 1, 10), hist(6, 1, 40), hist(3, 2, 100)],
+        );
+        // Mondays of the ISO weeks 3 and 4.
+        let historic_monday = DateTime::parse_from_rfc3339("2024-01-15T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let missing_monday = historic_monday + TimeDelta::days(7);
+        assert_eq!(
+            sim_param.get_date_hist_source(&historic_monday),
+            DateHistSource::Historic
+        );
+        assert_eq!(
+            sim_param.get_date_hist_source(&missing_monday),
+            DateHistSource::Imputed
+        );
+        assert_eq!(
+            sim_param.get_date_hist(&missing_monday).withdrawal_qty,
+            BigDecimal::from(0)
+        );
+
+        for (history_imputation, withdrawal_qty) in [
+            (HistoryImputation::NeighbouringWeeks, 10),
+            (HistoryImputation::SameWeekday, 25),
+            (HistoryImputation::OverallMean, 50),
+            (HistoryImputation::Zero, 0),
+        ] {
+            sim_param.set_history_imputation(history_imputation);
+            assert_eq!(sim_param.get_history_imputation(), history_imputation);
+            assert_eq!(
+                sim_param.get_date_hist(&missing_monday).withdrawal_qty,
+                BigDecimal::from(withdrawal_qty)
+            );
+            assert_eq!(
+                sim_param.get_date_hist(&historic_monday).withdrawal_qty,
+                BigDecimal::from(10)
+            );
+        }
+        assert_eq!(
+            HistoryImputation::from_str("same_weekday"),
+            Ok(HistoryImputation::SameWeekday)
+        );
+    }
+
+    #[test]
+    fn test_get_scenario_hist_with_intermittent_demand() {
+        let mut sim_param = SimulationParameters::new(1000, 5, vec![]);
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        sim_param.intermittent_demand = Some(IntermittentDemand {
+            probability: 0.25,
+            size: 3.6,
+        });
+        let withdrawals: Vec<f64> = (0..2000)
+            .map(|_| {
+                sim_param
+                    .get_scenario_hist(&date, &sim_param.fitted_run_draws())
+                    .withdrawal_qty
+                    .to_f64()
+                    .unwrap()
+            })
+            .collect();
+        assert!(withdrawals.iter().all(|qty| [0.0, 3.0, 4.0].contains(qty)));
+        let sizes: Vec<f64> = withdrawals.into_iter().filter(|qty| *qty > 0.0).collect();
+        assert!(sizes.len() > 400 && sizes.len() < 600);
+        // Rounded without bias: 3 or 4 averaging the size of 3.6.
+        let mean_size = sizes.iter().sum::<f64>() / sizes.len() as f64;
+        assert!((mean_size - 3.6).abs() < 0.15);
+
+        sim_param.deterministic = true;
+        let expected_qty = sim_param
+            .get_scenario_hist(&date, &sim_param.fitted_run_draws())
+            .withdrawal_qty;
+        assert!((expected_qty.to_f64().unwrap() - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_draw_qc_rejection_share() {
+        let mut sim_param = SimulationParameters::new(1000, 5, vec![]);
+        let mut rng = rand::thread_rng();
+        assert_eq!(
+            sim_param.draw_qc_rejection_share(&mut rng),
+            BigDecimal::from(0)
+        );
+
+        sim_param.qc_rejection_rate = 0.8;
+        let mut sum = 0.0;
+        for _ in 0..10_000 {
+            let share = sim_param
+                .draw_qc_rejection_share(&mut rng)
+                .to_f64()
+                .unwrap();
+            assert!((0.6..=1.0).contains(&share));
+            sum += share;
+        }
+        assert!((sum / 10_000.0 - 0.8).abs() < 0.01);
+
+        sim_param.qc_rejection_rate = 1.0;
+        assert_eq!(
+            sim_param.draw_qc_rejection_share(&mut rng),
+            BigDecimal::from(1)
+        );
+    }
+
+    #[test]
+    fn test_get_new_batch_deadline() {
+        let mut sim_param = SimulationParameters::new(1000, 5, vec![]);
+        let date = DateTime::parse_from_rfc3339("2024-01-01T06:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            sim_param
+                .get_new_batch_deadline(&date)
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-06T06:00:00+00:00"
+        );
+
+        sim_param.new_batch_expiration_hours = Some(18);
+        assert_eq!(
+            sim_param
+                .get_new_batch_deadline(&date)
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-02T00:00:00+00:00"
+        );
+
+        sim_param.set_new_batch_expiration_days(2);
+        assert_eq!(sim_param.new_batch_expiration_hours, None);
+        assert_eq!(
+            sim_param
+                .get_new_batch_deadline(&date)
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-03T06:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_validate_time_step_hours() {
+        assert!(SimulationParameters::validate_time_step_hours(4).is_ok());
+        assert!(SimulationParameters::validate_time_step_hours(24).is_ok());
+        assert!(SimulationParameters::validate_time_step_hours(5).is_err());
+        assert!(SimulationParameters::validate_time_step_hours(0).is_err());
+    }
+
     #[test]
     fn test_group_by_woy_and_dow() {
         let historic = vec![
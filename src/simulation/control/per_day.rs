@@ -1,23 +1,51 @@
 use crate::{
-    data::product_batch::ProductBatch, simulation::control::parameter::SimulationParameters,
+    data::product_batch::ProductBatch,
+    simulation::control::parameter::{
+        DayEvent, ReplenishmentPolicy, RunDraws, ShortageMode, SimulationParameters,
+    },
 };
+use bigdecimal::{FromPrimitive, Signed, ToPrimitive};
 use sqlx::types::BigDecimal;
 
-use chrono::{DateTime, Days, Utc};
+use chrono::{DateTime, Days, TimeDelta, Utc};
+
+/// Demand not met by the stock, waiting to be filled by the next entries.
+#[derive(Debug, Clone)]
+pub struct Backorder {
+    pub quantity: BigDecimal,
+    pub order_date: DateTime<Utc>,
+}
+
+/// Quantity ordered by the replenishment policy, not yet received.
+#[derive(Debug, Clone)]
+pub struct ReplenishmentOrder {
+    pub quantity: BigDecimal,
+    pub arrival_date: DateTime<Utc>,
+}
 
 #[derive(Debug, Clone)]
 pub struct SimulationDay {
     pub date: DateTime<Utc>,
     pub batches: Vec<ProductBatch>,
+    /// Demand lost for lack of stock.
     pub stock_shortage: Option<BigDecimal>,
+    /// Demand not met by the stock and added to the backlog, in backorder mode.
+    pub backordered: Option<BigDecimal>,
     pub stock_limit_exceeded: Option<BigDecimal>,
     pub stock_time_limit_exceeded: Option<BigDecimal>,
+    pub qc_rejected: Option<BigDecimal>,
+    pub backlog: Vec<Backorder>,
+    pub pending_orders: Vec<ReplenishmentOrder>,
+    pub demand_qty: BigDecimal,
+    pub withdrawn_qty: BigDecimal,
+    /// Sum of the withdrawn quantities weighted by the age in days of their batches.
+    pub withdrawn_age_days: f64,
     pub is_calculated: bool,
 }
 
 impl SimulationDay {
-    fn do_withdraw_mov(&mut self, sim_param: &SimulationParameters) {
-        let date_hist = sim_param.get_date_hist(&self.date);
+    fn do_withdraw_mov(&mut self, sim_param: &SimulationParameters, draws: &RunDraws) {
+        let date_hist = sim_param.get_scenario_hist(&self.date, draws);
         eprintln!("date_hist: {:?}", date_hist);
         let mut withdraw_qty = date_hist.withdrawal_qty.clone();
         eprintln!(
@@ -29,19 +57,25 @@ impl SimulationDay {
                 .map(|e| e.quantity.clone())
                 .reduce(|acc, e| acc + e)
         );
-        while withdraw_qty > BigDecimal::from(0) && self.batches.len() > 0 {
-            match self.batches.first_mut() {
-                Some(old) => {
-                    if old.quantity > withdraw_qty {
-                        old.quantity = &old.quantity - withdraw_qty;
-                        withdraw_qty = BigDecimal::from(0);
-                    } else {
-                        withdraw_qty = withdraw_qty - &old.quantity;
-                        self.batches.remove(0);
-                    }
-                }
-                None => {}
+        self.demand_qty = &self.demand_qty + &withdraw_qty;
+        let mut i = 0;
+        while withdraw_qty.is_positive() && i < self.batches.len() {
+            if Self::is_on_qc_hold(&self.batches[i], &self.date) {
+                i += 1;
+                continue;
+            }
+            let old = &mut self.batches[i];
+            let entry_date = old.entry_date;
+            let consumed = if old.quantity > withdraw_qty {
+                old.quantity = &old.quantity - &withdraw_qty;
+                withdraw_qty.clone()
+            } else {
+                let consumed = old.quantity.clone();
+                self.batches.remove(i);
+                consumed
             };
+            withdraw_qty -= &consumed;
+            self.record_withdrawal(consumed, entry_date);
         }
         eprintln!(
             "after withdraw | withdraw_qty: {:?}, batches.len(): {:?}, batches_qty: {:?}",
@@ -52,16 +86,81 @@ impl SimulationDay {
                 .map(|e| e.quantity.clone())
                 .reduce(|acc, e| acc + e)
         );
-        self.stock_shortage = if withdraw_qty > BigDecimal::from(0) {
+        let unmet_qty = if withdraw_qty.is_positive() {
             Some(withdraw_qty)
         } else {
             None
         };
+        if sim_param.shortage_mode == ShortageMode::Backorder {
+            if let Some(quantity) = &unmet_qty {
+                self.backlog.push(Backorder {
+                    quantity: quantity.clone(),
+                    order_date: self.date,
+                });
+            }
+            self.backordered = unmet_qty;
+        } else {
+            self.stock_shortage = unmet_qty;
+        }
     }
 
-    fn do_entry_mov(&mut self, sim_param: &SimulationParameters) {
-        let date_hist = sim_param.get_date_hist(&self.date);
-        eprintln!("date_hist: {:?}", date_hist);
+    fn record_withdrawal(&mut self, quantity: BigDecimal, entry_date: DateTime<Utc>) {
+        let age_days = (self.date - entry_date).num_seconds() as f64 / 86_400.0;
+        self.withdrawn_age_days += quantity.to_f64().unwrap_or(0.0) * age_days;
+        self.withdrawn_qty = &self.withdrawn_qty + quantity;
+    }
+
+    /// Fills the oldest backorders first, returning what is left of the quantity.
+    fn fill_backlog(&mut self, mut quantity: BigDecimal) -> BigDecimal {
+        while quantity.is_positive() && !self.backlog.is_empty() {
+            let oldest = &mut self.backlog[0];
+            if oldest.quantity > quantity {
+                oldest.quantity = &oldest.quantity - quantity;
+                quantity = BigDecimal::from(0);
+            } else {
+                quantity -= &oldest.quantity;
+                self.backlog.remove(0);
+            }
+        }
+        quantity
+    }
+
+    /// Total quantity in the batches, including the ones on quality control hold.
+    pub fn stock_qty(&self) -> BigDecimal {
+        self.batches
+            .iter()
+            .fold(BigDecimal::from(0), |acc, e| acc + &e.quantity)
+    }
+
+    pub fn backlog_qty(&self) -> BigDecimal {
+        self.backlog
+            .iter()
+            .fold(BigDecimal::from(0), |acc, e| acc + &e.quantity)
+    }
+
+    /// Age in days of the oldest open backorder.
+    pub fn backlog_age_days(&self) -> f64 {
+        self.backlog
+            .first()
+            .map(|oldest| (self.date - oldest.order_date).num_seconds() as f64 / 86_400.0)
+            .unwrap_or(0.0)
+    }
+
+    fn do_entry_mov(&mut self, sim_param: &SimulationParameters, draws: &RunDraws) {
+        let received_qty = if sim_param.replenishment.is_some() {
+            let arrived_qty = self.take_arrived_orders(sim_param.get_supply_factor(&self.date));
+            arrived_qty + sim_param.get_scheduled_movement(&self.date).entry_qty
+        } else {
+            let date_hist = sim_param.get_scenario_hist(&self.date, draws);
+            eprintln!("date_hist: {:?}", date_hist);
+            date_hist.entry_qty
+        };
+        let entry_qty = if sim_param.qc_hold_days > 0 {
+            received_qty.clone()
+        } else {
+            let accepted = self.reject_qc_share(received_qty.clone(), sim_param);
+            self.fill_backlog(accepted)
+        };
         let batches_qty_sum = self
             .batches
             .iter()
@@ -70,30 +169,38 @@ impl SimulationDay {
             .unwrap_or(BigDecimal::from(0));
         eprintln!(
             "before entry | entry_qty: {:?}, batches.len(): {:?}, batches_qty_sum: {:?}",
-            date_hist.entry_qty,
+            entry_qty,
             self.batches.len(),
             batches_qty_sum
         );
-        let available = BigDecimal::from(sim_param.stock_maximum_quantity) - batches_qty_sum;
-        let (final_entry_qty, exceeded_entry_qty) = if available > date_hist.entry_qty {
-            (date_hist.entry_qty.clone(), BigDecimal::from(0))
+        // A capacity cut can leave the stock above the maximum: no space at all then.
+        let available = (BigDecimal::from(sim_param.get_stock_maximum_quantity(&self.date))
+            - batches_qty_sum)
+            .max(BigDecimal::from(0));
+        let (final_entry_qty, exceeded_entry_qty) = if available > entry_qty {
+            (entry_qty, BigDecimal::from(0))
         } else {
-            (available.clone(), (date_hist.entry_qty.clone() - available))
+            (available.clone(), (entry_qty - available))
         };
-        self.batches.push(ProductBatch {
-            quantity: final_entry_qty,
-            deadline_date: self
-                .date
-                .clone()
-                .checked_add_days(Days::new(sim_param.new_batch_default_expiration_days))
-                .unwrap(),
-            entry_date: self.date.clone(),
-            finished_date: None,
-            is_finished: false,
-        });
+        let qc_release_date = if sim_param.qc_hold_days > 0 {
+            self.date
+                .checked_add_days(Days::new(sim_param.qc_hold_days))
+        } else {
+            None
+        };
+        if final_entry_qty.is_positive() {
+            self.batches.push(ProductBatch {
+                quantity: final_entry_qty,
+                deadline_date: sim_param.get_new_batch_deadline(&self.date).unwrap(),
+                entry_date: self.date.clone(),
+                finished_date: None,
+                is_finished: false,
+                qc_release_date,
+            });
+        }
         eprintln!(
             "after entry | entry_qty: {:?}, batches.len(): {:?}, batches_qty_sum: {:?}",
-            date_hist.entry_qty,
+            received_qty,
             self.batches.len(),
             self.batches
                 .iter()
@@ -101,18 +208,66 @@ impl SimulationDay {
                 .reduce(|acc, e| acc + e)
         );
 
-        self.stock_limit_exceeded = if exceeded_entry_qty > BigDecimal::from(0) {
+        self.stock_limit_exceeded = if exceeded_entry_qty.is_positive() {
             Some(exceeded_entry_qty)
         } else {
             None
         };
     }
 
-    fn do_rm_expired_batch_mov(&mut self) {
+    /// Takes the `supply_factor` share of the orders due, the rest of them staying pending.
+    fn take_arrived_orders(&mut self, supply_factor: f64) -> BigDecimal {
+        if supply_factor <= 0.0 {
+            return BigDecimal::from(0);
+        }
+        let supply_factor = BigDecimal::from_f64(supply_factor).unwrap_or(BigDecimal::from(1));
+        let mut arrived_qty = BigDecimal::from(0);
+        let date = self.date;
+        self.pending_orders.retain_mut(|order| {
+            if order.arrival_date > date {
+                return true;
+            }
+            let delivered_qty = &order.quantity * &supply_factor;
+            order.quantity -= &delivered_qty;
+            arrived_qty += delivered_qty;
+            order.quantity.is_positive()
+        });
+        arrived_qty
+    }
+
+    pub fn pending_orders_qty(&self) -> BigDecimal {
+        self.pending_orders
+            .iter()
+            .fold(BigDecimal::from(0), |acc, e| acc + &e.quantity)
+    }
+
+    /// Orders up to the stock maximum quantity when the stock position,
+    /// stock plus pending orders minus backlog, is at or below the reorder point.
+    fn do_replenishment_order(
+        &mut self,
+        policy: &ReplenishmentPolicy,
+        stock_maximum_quantity: u64,
+    ) {
+        let position = self.stock_qty() + self.pending_orders_qty() - self.backlog_qty();
+        let quantity = BigDecimal::from(stock_maximum_quantity) - &position;
+        if position <= policy.reorder_point && quantity > 0 {
+            self.pending_orders.push(ReplenishmentOrder {
+                quantity,
+                arrival_date: self
+                    .date
+                    .checked_add_days(Days::new(policy.lead_time_days))
+                    .unwrap(),
+            });
+        }
+    }
+
+    fn do_rm_expired_batch_mov(&mut self, sim_param: &SimulationParameters) {
         let mut removed_quantity = BigDecimal::from(0);
         let mut to_remove_idx = Vec::<usize>::new();
         for (i, e) in self.batches.iter().enumerate().rev() {
-            let is_to_remove = e.deadline_date < self.date;
+            let is_to_remove = sim_param
+                .expiration_comparison
+                .is_expired(&e.deadline_date, &self.date);
             eprintln!(
                 "Element at position {}: {:?}, is_to_remove: {}",
                 i, e, is_to_remove
@@ -135,32 +290,126 @@ impl SimulationDay {
                 .map(|e| e.quantity.clone())
                 .reduce(|acc, e| acc + e)
         );
-        self.stock_time_limit_exceeded = if removed_quantity > BigDecimal::from(0) {
+        self.stock_time_limit_exceeded = if removed_quantity.is_positive() {
             Some(removed_quantity)
         } else {
             None
         };
     }
 
-    pub fn calculate(&mut self, sim_param: &SimulationParameters) -> bool {
-        self.do_withdraw_mov(sim_param);
-        self.do_entry_mov(sim_param);
-        self.do_rm_expired_batch_mov();
+    /// Releases the batches whose quality control hold is over,
+    /// discarding the share rejected by the inspection.
+    fn do_qc_release_mov(&mut self, sim_param: &SimulationParameters) {
+        let mut released = Vec::<usize>::new();
+        for (i, e) in self.batches.iter().enumerate() {
+            if e.qc_release_date.is_some() && !Self::is_on_qc_hold(e, &self.date) {
+                released.push(i);
+            }
+        }
+        for i in released {
+            let quantity = self.batches[i].quantity.clone();
+            let accepted = self.reject_qc_share(quantity, sim_param);
+            self.batches[i].quantity = self.fill_backlog(accepted);
+            self.batches[i].qc_release_date = None;
+            eprintln!(
+                "qc released element at position {}: {:?}",
+                i, self.batches[i]
+            );
+        }
+    }
+
+    fn reject_qc_share(
+        &mut self,
+        quantity: BigDecimal,
+        sim_param: &SimulationParameters,
+    ) -> BigDecimal {
+        let rejected_qty = &quantity * sim_param.draw_qc_rejection_share(&mut rand::thread_rng());
+        if rejected_qty.is_positive() {
+            self.qc_rejected = Some(match self.qc_rejected.take() {
+                Some(acc) => acc + &rejected_qty,
+                None => rejected_qty.clone(),
+            });
+        }
+        quantity - rejected_qty
+    }
+
+    fn is_on_qc_hold(batch: &ProductBatch, date: &DateTime<Utc>) -> bool {
+        batch
+            .qc_release_date
+            .is_some_and(|release_date| release_date > *date)
+    }
+
+    pub fn calculate(&mut self, sim_param: &SimulationParameters, draws: &RunDraws) -> bool {
+        self.do_qc_release_mov(sim_param);
+        for event in sim_param.day_events_order.iter() {
+            match event {
+                DayEvent::Withdraw => self.do_withdraw_mov(sim_param, draws),
+                DayEvent::Entry => self.do_entry_mov(sim_param, draws),
+                DayEvent::RmExpired => self.do_rm_expired_batch_mov(sim_param),
+            }
+        }
+        if let Some(policy) = &sim_param.replenishment {
+            self.do_replenishment_order(policy, sim_param.get_stock_maximum_quantity(&self.date));
+        }
         self.is_calculated = true;
         self.is_calculated
     }
 
-    pub fn create_next(&self) -> Option<SimulationDay> {
-        match self.date.checked_add_days(Days::new(1)) {
-            Some(new_date) => Some(SimulationDay {
+    pub fn create_next(&self, time_step_hours: u32) -> Option<SimulationDay> {
+        self.date
+            .checked_add_signed(TimeDelta::hours(time_step_hours as i64))
+            .map(|new_date| SimulationDay {
                 date: new_date,
                 batches: self.batches.clone(),
                 stock_shortage: None,
+                backordered: None,
                 stock_limit_exceeded: None,
                 stock_time_limit_exceeded: None,
+                qc_rejected: None,
+                backlog: self.backlog.clone(),
+                pending_orders: self.pending_orders.clone(),
+                demand_qty: BigDecimal::from(0),
+                withdrawn_qty: BigDecimal::from(0),
+                withdrawn_age_days: 0.0,
                 is_calculated: false,
-            }),
-            None => None,
+            })
+    }
+
+    /// Merges the sub-day steps of each date into a single day, summing the losses
+    /// and keeping the batches of the last step. Daily steps are kept as they are.
+    pub fn roll_up_daily(steps: Vec<SimulationDay>) -> Vec<SimulationDay> {
+        let mut days: Vec<SimulationDay> = Vec::new();
+        for step in steps {
+            match days.last_mut() {
+                Some(day) if day.date.date_naive() == step.date.date_naive() => {
+                    day.batches = step.batches;
+                    day.backlog = step.backlog;
+                    day.pending_orders = step.pending_orders;
+                    day.demand_qty = &day.demand_qty + step.demand_qty;
+                    day.withdrawn_qty = &day.withdrawn_qty + step.withdrawn_qty;
+                    day.withdrawn_age_days += step.withdrawn_age_days;
+                    day.stock_shortage = Self::sum_losses(&day.stock_shortage, step.stock_shortage);
+                    day.backordered = Self::sum_losses(&day.backordered, step.backordered);
+                    day.stock_limit_exceeded =
+                        Self::sum_losses(&day.stock_limit_exceeded, step.stock_limit_exceeded);
+                    day.stock_time_limit_exceeded = Self::sum_losses(
+                        &day.stock_time_limit_exceeded,
+                        step.stock_time_limit_exceeded,
+                    );
+                    day.qc_rejected = Self::sum_losses(&day.qc_rejected, step.qc_rejected);
+                    day.is_calculated = day.is_calculated && step.is_calculated;
+                }
+                _ => days.push(step),
+            }
+        }
+        days
+    }
+
+    fn sum_losses(acc: &Option<BigDecimal>, loss: Option<BigDecimal>) -> Option<BigDecimal> {
+        match (acc, loss) {
+            (Some(acc), Some(loss)) => Some(acc + loss),
+            (Some(acc), None) => Some(acc.clone()),
+            (None, loss) => loss,
         }
     }
 }
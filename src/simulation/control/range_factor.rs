@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Days};
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::product_mov_hist::ProductMovDaily,
+    simulation::control::{forecast::daily_series, statistics::mean},
+};
+
+/// Beyond it, the drawn factor could be negative.
+pub const MAX_RANGE_FACTOR: f64 = 1.0;
+
+/// Random range factor matching the observed spread of the daily movements around their
+/// average of the same week of year and day of week. The random factor being drawn
+/// uniformly within `1 ± r`, with a standard deviation of `r / √3`, `r` is `√3` times the
+/// relative standard deviation of the movements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeFactorFit {
+    pub factor: f64,
+    pub relative_std: f64,
+    /// Days of the averages with a movement and observed at least twice.
+    pub observed_days: usize,
+}
+
+impl RangeFactorFit {
+    /// Pools the entries and withdrawals, rounding the factor to hundredths.
+    /// `None` when no average has a movement and was observed at least twice.
+    pub fn fit(history: &[ProductMovDaily]) -> Option<Self> {
+        let mut squared_sum = 0.0;
+        let mut degrees_of_freedom = 0;
+        let mut observed_days = 0;
+        let quantities: [fn(&ProductMovDaily) -> &BigDecimal; 2] =
+            [|day| &day.entry_qty, |day| &day.withdrawal_qty];
+        for quantity in quantities {
+            let (first_date, series) = daily_series(history, quantity)?;
+            let mut by_woy_and_dow: HashMap<(u32, u32), Vec<f64>> = HashMap::new();
+            for (i, value) in series.iter().enumerate() {
+                let date = first_date + Days::new(i as u64);
+                by_woy_and_dow
+                    .entry((
+                        date.iso_week().week(),
+                        date.weekday().num_days_from_sunday(),
+                    ))
+                    .or_default()
+                    .push(*value);
+            }
+            for values in by_woy_and_dow.values() {
+                let average = mean(values);
+                if values.len() < 2 || average <= 0.0 {
+                    continue;
+                }
+                squared_sum += values
+                    .iter()
+                    .map(|value| (value / average - 1.0).powi(2))
+                    .sum::<f64>();
+                degrees_of_freedom += values.len() - 1;
+                observed_days += values.len();
+            }
+        }
+        if degrees_of_freedom == 0 {
+            return None;
+        }
+        let relative_std = (squared_sum / degrees_of_freedom as f64).sqrt();
+        Some(Self {
+            factor: ((3.0_f64.sqrt() * relative_std).min(MAX_RANGE_FACTOR) * 100.0).round() / 100.0,
+            relative_std,
+            observed_days,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn history(quantity: fn(i32) -> i32) -> Vec<ProductMovDaily> {
+        let first_date = NaiveDate::from_ymd_opt(2019, 1, 7).unwrap();
+        (0..3 * 364)
+            .map(|t| first_date + Days::new(t))
+            .map(|date| ProductMovDaily {
+                mov_date: date,
+                entry_qty: BigDecimal::from(0),
+                withdrawal_qty: BigDecimal::from(quantity(date.iso_week().year() - 2019)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_fit_the_spread_around_the_seasonal_average() {
+        // Most weeks of year and days of week observed as 90, 100 and 110 over the years.
+        let fit = RangeFactorFit::fit(&history(|year| [90, 100, 110][year as usize])).unwrap();
+        assert!((fit.relative_std - 0.1).abs() < 1e-2);
+        assert_eq!(fit.factor, 0.17);
+        assert!(fit.observed_days > 1000);
+    }
+
+    #[test]
+    fn should_not_fit_without_repeated_movements() {
+        assert!(RangeFactorFit::fit(&history(|_| 0)).is_none());
+        assert!(RangeFactorFit::fit(&[]).is_none());
+        let fit = RangeFactorFit::fit(&history(|_| 5)).unwrap();
+        assert_eq!(fit.factor, 0.0);
+    }
+
+    #[test]
+    fn should_cap_the_factor() {
+        let fit = RangeFactorFit::fit(&history(|year| [0, 0, 300][year as usize])).unwrap();
+        assert_eq!(fit.factor, MAX_RANGE_FACTOR);
+    }
+}
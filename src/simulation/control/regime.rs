@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Days, NaiveDate};
+use rand::Rng;
+
+use crate::{
+    data::product_mov_hist::ProductMovDaily,
+    simulation::control::{
+        forecast::{daily_series, WEEKLY_PERIOD},
+        statistics::mean,
+    },
+};
+
+/// Weeks of history needed to fit the regimes.
+pub const MIN_WEEKS: usize = 8;
+const MAX_ITERATIONS: usize = 50;
+
+pub const QUIET: usize = 0;
+pub const BUSY: usize = 1;
+
+/// Markov regime-switching demand: the withdrawals alternate between a quiet and a busy
+/// regime lasting for weeks, each with its own multiplier of the expected withdrawals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DemandRegimes {
+    /// Multipliers of the quiet and busy regimes, averaging 1 over time.
+    pub multipliers: [f64; 2],
+    /// Probability of moving from a regime one day to a regime the next day.
+    pub transitions: [[f64; 2]; 2],
+}
+
+impl DemandRegimes {
+    /// Splits the weekly withdrawals, relative to their season, in two regimes by 2-means
+    /// and counts the transitions from a week to the next one, with add-one smoothing,
+    /// converted to daily transitions. `None` without `MIN_WEEKS` of seasonal ratios or
+    /// without both regimes.
+    pub fn fit(history: &[ProductMovDaily]) -> Option<Self> {
+        let (first_date, series) = daily_series(history, |day| &day.withdrawal_qty)?;
+        let weeks: Vec<(u32, f64)> = series
+            .chunks_exact(WEEKLY_PERIOD)
+            .enumerate()
+            .map(|(index, week)| {
+                let week_date = first_date + Days::new((index * WEEKLY_PERIOD) as u64);
+                (week_date.iso_week().week(), mean(week))
+            })
+            .collect();
+        let levels = Self::seasonal_ratios(&weeks);
+        let overall = mean(&levels);
+        if levels.len() < MIN_WEEKS || overall <= 0.0 {
+            return None;
+        }
+
+        let mut threshold = overall;
+        let mut labels: Vec<usize> = Vec::new();
+        for _ in 0..MAX_ITERATIONS {
+            labels = levels
+                .iter()
+                .map(|level| if *level > threshold { BUSY } else { QUIET })
+                .collect();
+            let means = Self::regime_means(&levels, &labels)?;
+            let next_threshold = (means[QUIET] + means[BUSY]) / 2.0;
+            if next_threshold == threshold {
+                break;
+            }
+            threshold = next_threshold;
+        }
+        let means = Self::regime_means(&levels, &labels)?;
+
+        let mut counts = [[1.0_f64; 2]; 2];
+        for pair in labels.windows(2) {
+            counts[pair[0]][pair[1]] += 1.0;
+        }
+        let mut transitions = [[0.0; 2]; 2];
+        for from in [QUIET, BUSY] {
+            let weekly_stay = counts[from][from] / (counts[from][QUIET] + counts[from][BUSY]);
+            let daily_stay = weekly_stay.powf(1.0 / WEEKLY_PERIOD as f64);
+            transitions[from][from] = daily_stay;
+            transitions[from][1 - from] = 1.0 - daily_stay;
+        }
+
+        let mut regimes = Self {
+            multipliers: [means[QUIET] / overall, means[BUSY] / overall],
+            transitions,
+        };
+        let stationary = regimes.stationary();
+        let average = stationary[QUIET] * regimes.multipliers[QUIET]
+            + stationary[BUSY] * regimes.multipliers[BUSY];
+        regimes.multipliers = regimes.multipliers.map(|multiplier| multiplier / average);
+        Some(regimes)
+    }
+
+    /// Ratio of each week to the average of the same ISO week of year over the history, the
+    /// historic average the regime multipliers apply to. A week of year seen only once is
+    /// its own average and is left out.
+    fn seasonal_ratios(weeks: &[(u32, f64)]) -> Vec<f64> {
+        let mut levels_by_woy: HashMap<u32, Vec<f64>> = HashMap::new();
+        for (week_of_year, level) in weeks {
+            levels_by_woy.entry(*week_of_year).or_default().push(*level);
+        }
+        weeks
+            .iter()
+            .filter_map(|(week_of_year, level)| {
+                let same_weeks = &levels_by_woy[week_of_year];
+                let expected = mean(same_weeks);
+                (same_weeks.len() > 1 && expected > 0.0).then(|| level / expected)
+            })
+            .collect()
+    }
+
+    /// `None` when a regime has no week.
+    fn regime_means(levels: &[f64], labels: &[usize]) -> Option<[f64; 2]> {
+        let of = |regime: usize| -> Option<f64> {
+            let values: Vec<f64> = levels
+                .iter()
+                .zip(labels)
+                .filter(|(_, r)| **r == regime)
+                .map(|(level, _)| *level)
+                .collect();
+            Some(mean(&values)).filter(|_| !values.is_empty())
+        };
+        Some([of(QUIET)?, of(BUSY)?])
+    }
+
+    /// Long-run share of the days in each regime.
+    pub fn stationary(&self) -> [f64; 2] {
+        let to_busy = self.transitions[QUIET][BUSY];
+        let to_quiet = self.transitions[BUSY][QUIET];
+        if to_busy + to_quiet <= 0.0 {
+            return [0.5, 0.5];
+        }
+        [
+            to_quiet / (to_busy + to_quiet),
+            to_busy / (to_busy + to_quiet),
+        ]
+    }
+
+    /// Multiplier of each day from `first_date` to `final_date`, starting from a regime
+    /// drawn from the stationary shares.
+    pub fn draw_path<R: Rng>(
+        &self,
+        first_date: NaiveDate,
+        final_date: NaiveDate,
+        rng: &mut R,
+    ) -> Vec<(NaiveDate, f64)> {
+        let mut regime = if rng.gen_bool(self.stationary()[BUSY].clamp(0.0, 1.0)) {
+            BUSY
+        } else {
+            QUIET
+        };
+        let mut path = Vec::new();
+        let mut date = first_date;
+        while date <= final_date {
+            path.push((date, self.multipliers[regime]));
+            if rng.gen_bool(self.transitions[regime][1 - regime].clamp(0.0, 1.0)) {
+                regime = 1 - regime;
+            }
+            match date.checked_add_days(Days::new(1)) {
+                Some(next_date) => date = next_date,
+                None => break,
+            }
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::BigDecimal;
+
+    use super::*;
+
+    /// Weeks from 2021-01-04, the first ISO week of 2021, of 52 ISO weeks each year until
+    /// 2023, with the withdrawals of each day given by its week of year and year.
+    fn weekly_history(weeks: u64, withdrawal: impl Fn(u64, u64) -> u64) -> Vec<ProductMovDaily> {
+        let first_date = NaiveDate::from_ymd_opt(2021, 1, 4).unwrap();
+        (0..weeks * 7)
+            .map(|t| ProductMovDaily {
+                mov_date: first_date + Days::new(t),
+                entry_qty: BigDecimal::from(0),
+                withdrawal_qty: BigDecimal::from(withdrawal((t / 7) % 52, t / 7 / 52)),
+            })
+            .collect()
+    }
+
+    /// Seasonal withdrawals, from 1x to 2x along the year, in runs of 2 busy weeks of 4x
+    /// the season and 4 quiet weeks of 1x, each week of year busy in one year out of three.
+    fn history(weeks: u64) -> Vec<ProductMovDaily> {
+        weekly_history(weeks, |week, year| {
+            let season = 10 + 10 * week / 51;
+            if (week / 2) % 3 == year {
+                4 * season
+            } else {
+                season
+            }
+        })
+    }
+
+    #[test]
+    fn should_fit_the_regimes() {
+        let regimes = DemandRegimes::fit(&history(156)).unwrap();
+        // Each week of year averages 2x its season: the quiet weeks 0.5 of it and the busy 2.
+        assert!((regimes.multipliers[QUIET] - 0.5).abs() < 0.05);
+        assert!((regimes.multipliers[BUSY] - 2.0).abs() < 0.15);
+        assert!((regimes.multipliers[BUSY] / regimes.multipliers[QUIET] - 4.0).abs() < 1e-9);
+        let stationary = regimes.stationary();
+        let average = stationary[QUIET] * regimes.multipliers[QUIET]
+            + stationary[BUSY] * regimes.multipliers[BUSY];
+        assert!((average - 1.0).abs() < 1e-9);
+        // Runs of weeks: the regimes rarely switch from a day to the next.
+        assert!(regimes.transitions[QUIET][QUIET] > 0.9);
+        assert!(regimes.transitions[BUSY][BUSY] > 0.85);
+        assert!(regimes.transitions[QUIET][QUIET] > regimes.transitions[BUSY][BUSY]);
+        for from in [QUIET, BUSY] {
+            assert!((regimes.transitions[from].iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn should_not_fit_without_enough_history_or_regimes() {
+        assert!(DemandRegimes::fit(&history(MIN_WEEKS as u64 - 1)).is_none());
+        assert!(DemandRegimes::fit(&[]).is_none());
+        let steady = weekly_history(104, |_, _| 10);
+        assert!(DemandRegimes::fit(&steady).is_none());
+        // A single year is its own historic average.
+        assert!(DemandRegimes::fit(&history(52)).is_none());
+    }
+
+    #[test]
+    fn should_not_take_the_season_for_regimes() {
+        let seasonal = weekly_history(104, |week, _| if week % 13 < 3 { 40 } else { 10 });
+        assert!(DemandRegimes::fit(&seasonal).is_none());
+    }
+
+    #[test]
+    fn should_draw_runs_of_regimes() {
+        let regimes = DemandRegimes {
+            multipliers: [0.5, 2.0],
+            transitions: [[0.95, 0.05], [0.1, 0.9]],
+        };
+        let first_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let final_date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let path = regimes.draw_path(first_date, final_date, &mut rand::thread_rng());
+        assert_eq!(path.len(), 366);
+        assert_eq!(path[0].0, first_date);
+        assert!(path
+            .iter()
+            .all(|(_, multiplier)| *multiplier == 0.5 || *multiplier == 2.0));
+        let switches = path
+            .windows(2)
+            .filter(|pair| pair[0].1 != pair[1].1)
+            .count();
+        assert!(switches < 100);
+    }
+}
@@ -0,0 +1,165 @@
+use bigdecimal::{FromPrimitive, ToPrimitive};
+use chrono::Days;
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::product_replenishment_recommendation::NewProductReplenishmentRecommendation,
+    simulation::control::{
+        kpi::RunKpis, parameter::ReplenishmentPolicy, per_day::SimulationDay, SimulationControl,
+    },
+};
+
+/// Expected outcome of simulating one candidate replenishment policy.
+#[derive(Debug, Clone)]
+pub struct ReplenishmentCandidate {
+    pub minimum_quantity: u64,
+    pub reorder_point: u64,
+    /// Mean cycle service level across the runs.
+    pub service_level: f64,
+    /// Mean expired quantity per run.
+    pub expired_quantity: f64,
+}
+
+impl ReplenishmentCandidate {
+    fn is_better_than(&self, other: &Self, target_service_level: f64) -> bool {
+        let reaches_target = self.service_level >= target_service_level;
+        let other_reaches_target = other.service_level >= target_service_level;
+        if reaches_target != other_reaches_target {
+            return reaches_target;
+        }
+        if !reaches_target && self.service_level != other.service_level {
+            return self.service_level > other.service_level;
+        }
+        self.expired_quantity < other.expired_quantity
+    }
+}
+
+/// Searches the safety stock (`minimum_quantity`) reaching the target service level
+/// with the lowest expected expired quantity. The reorder point of each candidate
+/// is its safety stock plus the expected demand during the lead time.
+pub struct ReplenishmentSearch {
+    pub target_service_level: f64,
+    pub lead_time_days: u64,
+    pub candidates: u64,
+    pub runs: u64,
+}
+
+impl ReplenishmentSearch {
+    pub fn recommend(
+        &self,
+        simulation: &mut SimulationControl,
+    ) -> Result<NewProductReplenishmentRecommendation, Box<dyn std::error::Error>> {
+        let evaluated = self.evaluate_candidates(simulation);
+
+        let candidates = evaluated.len();
+        let best = evaluated
+            .into_iter()
+            .reduce(|best, candidate| {
+                if candidate.is_better_than(&best, self.target_service_level) {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .ok_or("No replenishment candidates to evaluate")?;
+        Ok(NewProductReplenishmentRecommendation {
+            product_id: simulation.product_id,
+            start_date: simulation.first_day.date.date_naive(),
+            end_date: simulation.final_date.date_naive(),
+            target_service_level: Self::to_decimal(self.target_service_level)?,
+            lead_time_days: i16::try_from(self.lead_time_days)?,
+            minimum_quantity: i32::try_from(best.minimum_quantity)?,
+            reorder_point: i32::try_from(best.reorder_point)?,
+            expected_service_level: Self::to_decimal(best.service_level)?,
+            expected_expired_quantity: Self::to_decimal(best.expired_quantity)?,
+            is_target_reached: best.service_level >= self.target_service_level,
+            candidates: i32::try_from(candidates)?,
+        })
+    }
+
+    /// Candidates are spread evenly from zero to the stock maximum quantity.
+    /// The replenishment policy of the simulation is restored afterwards.
+    pub fn evaluate_candidates(
+        &self,
+        simulation: &mut SimulationControl,
+    ) -> Vec<ReplenishmentCandidate> {
+        let stock_maximum_quantity = simulation.sim_param.stock_maximum_quantity;
+        let lead_time_demand = self.lead_time_demand(simulation);
+        let steps = self.candidates.max(2) - 1;
+        let original_policy = simulation.sim_param.replenishment.take();
+        let mut evaluated: Vec<ReplenishmentCandidate> = Vec::new();
+        for i in 0..=steps {
+            let minimum_quantity = stock_maximum_quantity * i / steps;
+            let reorder_point = (minimum_quantity + lead_time_demand).min(stock_maximum_quantity);
+            if evaluated
+                .iter()
+                .any(|candidate| candidate.reorder_point == reorder_point)
+            {
+                continue;
+            }
+            simulation.sim_param.replenishment = Some(ReplenishmentPolicy {
+                reorder_point,
+                lead_time_days: self.lead_time_days,
+            });
+            evaluated.push(self.evaluate(simulation, minimum_quantity, reorder_point));
+        }
+        simulation.sim_param.replenishment = original_policy;
+        evaluated
+    }
+
+    fn evaluate(
+        &self,
+        simulation: &SimulationControl,
+        minimum_quantity: u64,
+        reorder_point: u64,
+    ) -> ReplenishmentCandidate {
+        let mut service_level = 0.0;
+        let mut expired_quantity = 0.0;
+        let runs = self.runs.max(1);
+        for _n in 0..runs {
+            let days = SimulationDay::roll_up_daily(simulation.run_once());
+            let kpis = RunKpis::from_days(&days, &simulation.sim_param.loss_cost);
+            service_level += kpis.cycle_service_level.unwrap_or(0.0);
+            expired_quantity += days
+                .iter()
+                .filter_map(|day| day.stock_time_limit_exceeded.as_ref())
+                .filter_map(|quantity| quantity.to_f64())
+                .sum::<f64>();
+        }
+        ReplenishmentCandidate {
+            minimum_quantity,
+            reorder_point,
+            service_level: service_level / runs as f64,
+            expired_quantity: expired_quantity / runs as f64,
+        }
+    }
+
+    /// Mean daily historic withdrawal over the simulated period times the lead time, rounded up.
+    fn lead_time_demand(&self, simulation: &SimulationControl) -> u64 {
+        let mut date = simulation.first_day.date;
+        let mut days = 0;
+        let mut withdrawal_qty = 0.0;
+        while date <= simulation.final_date {
+            withdrawal_qty += simulation
+                .sim_param
+                .get_date_hist(&date)
+                .withdrawal_qty
+                .to_f64()
+                .unwrap_or(0.0);
+            days += 1;
+            date = match date.checked_add_days(Days::new(1)) {
+                Some(next_date) => next_date,
+                None => break,
+            };
+        }
+        if days == 0 {
+            return 0;
+        }
+        (withdrawal_qty / days as f64 * self.lead_time_days as f64).ceil() as u64
+    }
+
+    fn to_decimal(value: f64) -> Result<BigDecimal, Box<dyn std::error::Error>> {
+        BigDecimal::from_f64((value * 1000.0).round() / 1000.0)
+            .ok_or(format!("Invalid replenishment value: {}", value).into())
+    }
+}
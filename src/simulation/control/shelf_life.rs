@@ -0,0 +1,127 @@
+use crate::{
+    data::{
+        product_shelf_life_analysis::NewProductShelfLifeAnalysis,
+        product_shelf_life_sensitivity::NewProductShelfLifeSensitivity,
+    },
+    simulation::control::SimulationControl,
+};
+
+/// Shelf life values, in days, simulated by the sensitivity analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShelfLifeRange {
+    pub min_days: u64,
+    pub max_days: u64,
+    pub step_days: u64,
+}
+
+impl ShelfLifeRange {
+    /// From a quarter to twice the current shelf life, in about ten steps.
+    pub fn around(current_days: u64) -> Self {
+        let min_days = (current_days / 4).max(1);
+        let max_days = (current_days * 2).max(min_days);
+        Self {
+            min_days,
+            max_days,
+            step_days: ((max_days - min_days) / 10).max(1),
+        }
+    }
+
+    pub fn validate(self) -> Result<Self, String> {
+        if self.min_days == 0 || self.step_days == 0 || self.min_days > self.max_days {
+            return Err(format!("Invalid shelf life range: {:?}", self));
+        }
+        Ok(self)
+    }
+
+    pub fn days(&self) -> impl Iterator<Item = u64> {
+        (self.min_days..=self.max_days).step_by(self.step_days as usize)
+    }
+}
+
+/// Re-runs the simulation for each shelf life of the new batches,
+/// reporting how the loss probabilities respond.
+pub struct ShelfLifeSensitivity {
+    pub range: ShelfLifeRange,
+    pub runs: u64,
+}
+
+impl ShelfLifeSensitivity {
+    /// The shelf life of the simulation is restored afterwards.
+    pub fn analyze(
+        &self,
+        simulation: &mut SimulationControl,
+    ) -> Result<
+        (
+            NewProductShelfLifeAnalysis,
+            Vec<NewProductShelfLifeSensitivity>,
+        ),
+        Box<dyn std::error::Error>,
+    > {
+        let current_days = simulation.sim_param.new_batch_default_expiration_days;
+        let current_hours = simulation.sim_param.new_batch_expiration_hours;
+        let mut sensitivity: Vec<NewProductShelfLifeSensitivity> = Vec::new();
+        for days in self.range.days() {
+            simulation.sim_param.set_new_batch_expiration_days(days);
+            let evaluated = simulation.run_n_times(self.runs.max(1));
+            simulation.sim_param.new_batch_default_expiration_days = current_days;
+            simulation.sim_param.new_batch_expiration_hours = current_hours;
+            let summary = evaluated?.summary;
+            sensitivity.push(NewProductShelfLifeSensitivity {
+                new_batch_default_expiration_days: i16::try_from(days)?,
+                probability_losses_by_missing: summary.probability_losses_by_missing,
+                probability_losses_by_nospace: summary.probability_losses_by_nospace,
+                probability_losses_by_expirat: summary.probability_losses_by_expirat,
+                probability_losses_by_rejection: summary.probability_losses_by_rejection,
+                probability_no_losses: summary.probability_no_losses,
+                expected_loss_cost: summary.expected_loss_cost,
+            });
+        }
+        let analysis = NewProductShelfLifeAnalysis {
+            product_id: simulation.product_id,
+            start_date: simulation.first_day.date.date_naive(),
+            end_date: simulation.final_date.date_naive(),
+            current_expiration_days: i16::try_from(current_days)?,
+        };
+        Ok((analysis, sensitivity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_build_range_around_current_shelf_life() {
+        let range = ShelfLifeRange::around(11);
+        assert_eq!(
+            range,
+            ShelfLifeRange {
+                min_days: 2,
+                max_days: 22,
+                step_days: 2,
+            }
+        );
+        assert_eq!(range.days().count(), 11);
+        assert_eq!(
+            ShelfLifeRange::around(0).days().collect::<Vec<u64>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_range() {
+        let range = ShelfLifeRange {
+            min_days: 5,
+            max_days: 1,
+            step_days: 1,
+        };
+        assert!(range.validate().is_err());
+        let range = ShelfLifeRange {
+            min_days: 1,
+            max_days: 5,
+            step_days: 0,
+        };
+        assert!(range.validate().is_err());
+    }
+}
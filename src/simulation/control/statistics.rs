@@ -0,0 +1,62 @@
+use rand::Rng;
+
+/// Percentile of the values, from 0 to 100, with linear interpolation between
+/// the closest ranks. `sorted_values` must be sorted in ascending order.
+pub fn percentile(sorted_values: &[f64], p: f64) -> Option<f64> {
+    if sorted_values.is_empty() {
+        return None;
+    }
+    let rank = (p.clamp(0.0, 100.0) / 100.0) * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    Some(sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * weight)
+}
+
+/// Zero without values.
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub fn sorted(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values
+}
+
+/// Standard normal draw, by the Box-Muller transform.
+pub fn draw_standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let values = sorted(vec![5.0, 1.0, 4.0, 2.0, 3.0]);
+        assert_eq!(percentile(&values, 0.0), Some(1.0));
+        assert_eq!(percentile(&values, 50.0), Some(3.0));
+        assert_eq!(percentile(&values, 100.0), Some(5.0));
+        assert_eq!(percentile(&values, 25.0), Some(2.0));
+        assert_eq!(percentile(&values, 5.0), Some(1.2));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_draw_standard_normal() {
+        let mut rng = rand::thread_rng();
+        let draws: Vec<f64> = (0..10_000)
+            .map(|_| draw_standard_normal(&mut rng))
+            .collect();
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        let variance = draws.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / draws.len() as f64;
+        assert!(mean.abs() < 0.1);
+        assert!((variance - 1.0).abs() < 0.1);
+    }
+}
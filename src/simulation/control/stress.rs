@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use bigdecimal::FromPrimitive;
+use chrono::{DateTime, Days, Utc};
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::product_simulation_stress_result::NewProductSimulationStressResult,
+    simulation::control::{parameter::StressShock, per_day::SimulationDay, SimulationControl},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StressKind {
+    /// The factor multiplies the entries, no deliveries at all with 0.
+    SupplierOutage,
+    /// The factor multiplies the withdrawals.
+    DemandShock,
+    /// The factor multiplies the stock maximum quantity.
+    CapacityCut,
+}
+
+impl FromStr for StressKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "supplier_outage" => Ok(StressKind::SupplierOutage),
+            "demand_shock" => Ok(StressKind::DemandShock),
+            "capacity_cut" => Ok(StressKind::CapacityCut),
+            other => Err(format!("Unknown stress test kind: {:?}", other)),
+        }
+    }
+}
+
+/// Named worst-case scenario, e.g. "supplier outage 7 days".
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressTest {
+    pub name: String,
+    pub kind: StressKind,
+    pub start_offset_days: u64,
+    /// Until the end of the simulation when not set.
+    pub duration_days: Option<u64>,
+    pub factor: f64,
+}
+
+impl StressTest {
+    /// Shock of the test, its offset counted from the first simulated day.
+    pub fn shock(
+        &self,
+        initial_date: DateTime<Utc>,
+        final_date: DateTime<Utc>,
+    ) -> Result<StressShock, String> {
+        let out_of_range = || format!("Stress test {:?} out of the date range", self.name);
+        let start_date = initial_date
+            .checked_add_days(Days::new(self.start_offset_days))
+            .ok_or_else(out_of_range)?;
+        let end_date = match self.duration_days {
+            Some(duration_days) => start_date.checked_add_days(Days::new(duration_days)),
+            None => final_date.checked_add_days(Days::new(1)),
+        }
+        .ok_or_else(out_of_range)?;
+        let (entry_factor, withdrawal_factor, capacity_factor) = match self.kind {
+            StressKind::SupplierOutage => (self.factor, 1.0, 1.0),
+            StressKind::DemandShock => (1.0, self.factor, 1.0),
+            StressKind::CapacityCut => (1.0, 1.0, self.factor),
+        };
+        Ok(StressShock {
+            start_date,
+            end_date,
+            entry_factor,
+            withdrawal_factor,
+            capacity_factor,
+        })
+    }
+}
+
+/// Runs each stress test once, deterministically, next to the Monte Carlo runs.
+pub struct StressTestSuite {
+    pub tests: Vec<StressTest>,
+}
+
+impl StressTestSuite {
+    /// The stress shock and the random draws of the simulation are restored afterwards.
+    pub fn run(
+        &self,
+        simulation: &mut SimulationControl,
+    ) -> Result<Vec<NewProductSimulationStressResult>, Box<dyn std::error::Error>> {
+        let shocks = self
+            .tests
+            .iter()
+            .map(|test| test.shock(simulation.first_day.date, simulation.final_date))
+            .collect::<Result<Vec<StressShock>, String>>()?;
+        let current_shock = simulation.sim_param.stress_shock.take();
+        let current_deterministic = simulation.sim_param.deterministic;
+        simulation.sim_param.deterministic = true;
+        let mut results: Vec<NewProductSimulationStressResult> = Vec::new();
+        for (test, shock) in self.tests.iter().zip(shocks) {
+            simulation.sim_param.stress_shock = Some(shock);
+            let days = simulation.run_once();
+            results.push(Self::summarize(test, &days, simulation)?);
+        }
+        simulation.sim_param.stress_shock = current_shock;
+        simulation.sim_param.deterministic = current_deterministic;
+        Ok(results)
+    }
+
+    fn summarize(
+        test: &StressTest,
+        days: &[SimulationDay],
+        simulation: &SimulationControl,
+    ) -> Result<NewProductSimulationStressResult, Box<dyn std::error::Error>> {
+        let total = |loss: fn(&SimulationDay) -> &Option<BigDecimal>| {
+            days.iter()
+                .filter_map(|day| loss(day).as_ref())
+                .fold(BigDecimal::from(0), |acc, quantity| acc + quantity)
+        };
+        let first_date_with_losses = days
+            .iter()
+            .find(|day| {
+                day.stock_shortage.is_some()
+                    || day.stock_limit_exceeded.is_some()
+                    || day.stock_time_limit_exceeded.is_some()
+                    || day.qc_rejected.is_some()
+            })
+            .map(|day| day.date.date_naive());
+        Ok(NewProductSimulationStressResult {
+            stress_test_name: test.name.clone(),
+            shortage_qty: total(|day| &day.stock_shortage),
+            nospace_qty: total(|day| &day.stock_limit_exceeded),
+            expired_qty: total(|day| &day.stock_time_limit_exceeded),
+            rejected_qty: total(|day| &day.qc_rejected),
+            loss_cost: BigDecimal::from_f64(simulation.sim_param.loss_cost.of_days(days))
+                .ok_or("Invalid stress test loss cost")?,
+            minimum_stock_qty: days
+                .iter()
+                .map(|day| day.stock_qty())
+                .min()
+                .unwrap_or(BigDecimal::from(0)),
+            final_stock_qty: days
+                .last()
+                .map(|day| day.stock_qty())
+                .unwrap_or(BigDecimal::from(0)),
+            first_date_with_losses,
+        })
+    }
+}
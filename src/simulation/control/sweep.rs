@@ -0,0 +1,528 @@
+use std::str::FromStr;
+
+use bigdecimal::{FromPrimitive, ToPrimitive};
+use rand::Rng;
+use sqlx::types::BigDecimal;
+
+use crate::{
+    data::{
+        product_parameter_sweep::NewProductParameterSweep,
+        product_parameter_sweep_point::NewProductParameterSweepPoint,
+        product_parameter_sweep_sensitivity::NewProductParameterSweepSensitivity,
+        product_simulation_summary::NewProductSimulationSummary,
+    },
+    simulation::control::{
+        parameter::SimulationParameters,
+        statistics::{percentile, sorted},
+        SimulationControl,
+    },
+};
+
+/// Upper limit of simulated points, a grid grows with the product of its axes lengths.
+pub const MAX_SWEEP_POINTS: usize = 1000;
+
+/// Simulation input that can be swept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepParameter {
+    RandomRangeFactor,
+    EntryTrendFactor,
+    WithdrawalTrendFactor,
+    StockMaximumQuantity,
+    ShelfLifeDays,
+    QcRejectionRate,
+    ReorderPoint,
+    LeadTimeDays,
+}
+
+impl FromStr for SweepParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "random_range_factor" => Ok(SweepParameter::RandomRangeFactor),
+            "entry_trend_factor" => Ok(SweepParameter::EntryTrendFactor),
+            "withdrawal_trend_factor" => Ok(SweepParameter::WithdrawalTrendFactor),
+            "stock_maximum_quantity" => Ok(SweepParameter::StockMaximumQuantity),
+            "new_batch_default_expiration_days" => Ok(SweepParameter::ShelfLifeDays),
+            "qc_rejection_rate" => Ok(SweepParameter::QcRejectionRate),
+            "reorder_point" => Ok(SweepParameter::ReorderPoint),
+            "lead_time_days" => Ok(SweepParameter::LeadTimeDays),
+            other => Err(format!("Unknown sweep parameter: {:?}", other)),
+        }
+    }
+}
+
+impl SweepParameter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SweepParameter::RandomRangeFactor => "random_range_factor",
+            SweepParameter::EntryTrendFactor => "entry_trend_factor",
+            SweepParameter::WithdrawalTrendFactor => "withdrawal_trend_factor",
+            SweepParameter::StockMaximumQuantity => "stock_maximum_quantity",
+            SweepParameter::ShelfLifeDays => "new_batch_default_expiration_days",
+            SweepParameter::QcRejectionRate => "qc_rejection_rate",
+            SweepParameter::ReorderPoint => "reorder_point",
+            SweepParameter::LeadTimeDays => "lead_time_days",
+        }
+    }
+
+    /// Quantities and days are rounded to the nearest integer.
+    /// Reorder point and lead time need a replenishment policy to change.
+    pub fn apply(&self, sim_param: &mut SimulationParameters, value: f64) -> Result<(), String> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!("Invalid {} value: {}", self.name(), value));
+        }
+        let integer = value.round() as u64;
+        match self {
+            SweepParameter::RandomRangeFactor => sim_param.random_range_factor = value,
+            SweepParameter::EntryTrendFactor => sim_param.entry_trend_factor = value,
+            SweepParameter::WithdrawalTrendFactor => sim_param.withdrawal_trend_factor = value,
+            SweepParameter::StockMaximumQuantity => sim_param.stock_maximum_quantity = integer,
+            SweepParameter::ShelfLifeDays => sim_param.set_new_batch_expiration_days(integer),
+            SweepParameter::QcRejectionRate => sim_param.qc_rejection_rate = value,
+            SweepParameter::ReorderPoint | SweepParameter::LeadTimeDays => {
+                let policy = sim_param.replenishment.as_mut().ok_or(format!(
+                    "Sweeping {} needs a replenishment policy",
+                    self.name()
+                ))?;
+                if *self == SweepParameter::ReorderPoint {
+                    policy.reorder_point = integer;
+                } else {
+                    policy.lead_time_days = integer;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Values of one swept parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepAxis {
+    pub parameter: SweepParameter,
+    pub values: Vec<f64>,
+}
+
+impl SweepAxis {
+    /// Parses `;` separated axes, each one either a list `name=v1,v2,v3`
+    /// or a range `name=min:max:step`, e.g. `random_range_factor=0.05,0.1;stock_maximum_quantity=100:300:100`.
+    pub fn parse_all(spec: &str) -> Result<Vec<SweepAxis>, String> {
+        let axes = spec
+            .split(';')
+            .filter(|axis| !axis.trim().is_empty())
+            .map(SweepAxis::from_str)
+            .collect::<Result<Vec<SweepAxis>, String>>()?;
+        if axes.is_empty() {
+            return Err(format!("No sweep axes: {:?}", spec));
+        }
+        for (i, axis) in axes.iter().enumerate() {
+            if axes[..i]
+                .iter()
+                .any(|other| other.parameter == axis.parameter)
+            {
+                return Err(format!(
+                    "Repeated sweep parameter: {}",
+                    axis.parameter.name()
+                ));
+            }
+        }
+        Ok(axes)
+    }
+
+    fn parse_value(value: &str) -> Result<f64, String> {
+        value
+            .trim()
+            .parse::<f64>()
+            .map_err(|err| format!("Invalid sweep value {:?}: {}", value, err))
+    }
+
+    fn min(&self) -> f64 {
+        self.values.iter().cloned().fold(f64::INFINITY, f64::min)
+    }
+
+    fn max(&self) -> f64 {
+        self.values
+            .iter()
+            .cloned()
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+impl FromStr for SweepAxis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, values) = s
+            .split_once('=')
+            .ok_or(format!("Sweep axis must be name=values: {:?}", s))?;
+        let parameter = SweepParameter::from_str(name)?;
+        let values = if values.contains(':') {
+            match values
+                .split(':')
+                .map(Self::parse_value)
+                .collect::<Result<Vec<f64>, String>>()?[..]
+            {
+                [min, max, step] if step > 0.0 && min <= max => {
+                    let steps = ((max - min) / step + 1e-9).floor();
+                    if !steps.is_finite() || steps >= MAX_SWEEP_POINTS as f64 {
+                        return Err(format!(
+                            "Sweep range must have from 1 to {} values: {:?}",
+                            MAX_SWEEP_POINTS, s
+                        ));
+                    }
+                    (0..=steps as usize)
+                        .map(|i| min + step * i as f64)
+                        .collect()
+                }
+                _ => return Err(format!("Invalid sweep range: {:?}", s)),
+            }
+        } else {
+            values
+                .split(',')
+                .map(Self::parse_value)
+                .collect::<Result<Vec<f64>, String>>()?
+        };
+        Ok(SweepAxis { parameter, values })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepDesign {
+    /// Every combination of the axes values.
+    Grid,
+    /// Points drawn uniformly between the minimum and the maximum value of each axis.
+    RandomSample { samples: u64 },
+}
+
+impl SweepDesign {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SweepDesign::Grid => "grid",
+            SweepDesign::RandomSample { .. } => "random",
+        }
+    }
+}
+
+type MetricGetter = fn(&NewProductParameterSweepPoint) -> &BigDecimal;
+
+const SWEEP_METRICS: [(&str, MetricGetter); 6] = [
+    ("probability_losses_by_missing", |point| {
+        &point.probability_losses_by_missing
+    }),
+    ("probability_losses_by_nospace", |point| {
+        &point.probability_losses_by_nospace
+    }),
+    ("probability_losses_by_expirat", |point| {
+        &point.probability_losses_by_expirat
+    }),
+    ("probability_losses_by_rejection", |point| {
+        &point.probability_losses_by_rejection
+    }),
+    ("probability_no_losses", |point| {
+        &point.probability_no_losses
+    }),
+    ("expected_loss_cost", |point| &point.expected_loss_cost),
+];
+
+type SweepResults = (
+    NewProductParameterSweep,
+    Vec<NewProductParameterSweepPoint>,
+    Vec<NewProductParameterSweepSensitivity>,
+);
+
+/// Runs `run_n_times` for each point of a grid or random sample over the
+/// simulation parameters, measuring the sensitivity of the results to each one.
+pub struct ParameterSweep {
+    pub axes: Vec<SweepAxis>,
+    pub design: SweepDesign,
+    pub runs: u64,
+}
+
+impl ParameterSweep {
+    /// Each point holds one value for each axis, in the axes order.
+    /// The number of points is checked before building them.
+    pub fn points(&self) -> Result<Vec<Vec<f64>>, String> {
+        let points_len = match self.design {
+            SweepDesign::Grid => self
+                .axes
+                .iter()
+                .try_fold(1_usize, |len, axis| len.checked_mul(axis.values.len())),
+            SweepDesign::RandomSample { samples } => usize::try_from(samples).ok(),
+        };
+        match points_len {
+            Some(len) if len > 0 && len <= MAX_SWEEP_POINTS => {}
+            _ => {
+                return Err(format!(
+                    "Sweep must have from 1 to {} points, got {}",
+                    MAX_SWEEP_POINTS,
+                    points_len.map_or("too many".to_owned(), |len| len.to_string())
+                ))
+            }
+        }
+        let points = match self.design {
+            SweepDesign::Grid => self.axes.iter().fold(vec![vec![]], |points, axis| {
+                points
+                    .iter()
+                    .flat_map(|point| {
+                        axis.values.iter().map(move |value| {
+                            let mut point = point.clone();
+                            point.push(*value);
+                            point
+                        })
+                    })
+                    .collect()
+            }),
+            SweepDesign::RandomSample { samples } => {
+                let mut rng = rand::thread_rng();
+                (0..samples)
+                    .map(|_| {
+                        self.axes
+                            .iter()
+                            .map(|axis| rng.gen_range(axis.min()..=axis.max()))
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+        Ok(points)
+    }
+
+    /// The parameters of the simulation are restored afterwards.
+    pub fn run(
+        &self,
+        simulation: &mut SimulationControl,
+    ) -> Result<SweepResults, Box<dyn std::error::Error>> {
+        self.check_axes(simulation)?;
+        let points = self.points()?;
+        let original_param = simulation.sim_param.clone();
+        let mut results: Vec<NewProductParameterSweepPoint> = Vec::new();
+        for (i, point) in points.iter().enumerate() {
+            simulation.sim_param = original_param.clone();
+            let evaluated = self
+                .apply(&mut simulation.sim_param, point)
+                .map_err(|err| err.into())
+                .and_then(|_| simulation.run_n_times(self.runs.max(1)))
+                .and_then(|summary| Self::to_point(i, &simulation.sim_param, summary.summary));
+            simulation.sim_param = original_param.clone();
+            results.push(evaluated?);
+        }
+        let sensitivities = self.sensitivities(&points, &results)?;
+        let sweep = NewProductParameterSweep {
+            product_id: simulation.product_id,
+            start_date: simulation.first_day.date.date_naive(),
+            end_date: simulation.final_date.date_naive(),
+            design: self.design.name().to_owned(),
+            axes: self
+                .axes
+                .iter()
+                .map(|axis| axis.parameter.name())
+                .collect::<Vec<&str>>()
+                .join(","),
+            runs: i32::try_from(self.runs.max(1))?,
+            points: i32::try_from(results.len())?,
+        };
+        Ok((sweep, results, sensitivities))
+    }
+
+    /// A stock maximum quantity below the initial stock would leave no space for any entry
+    /// while the stock is above it, so the lowest value of the axis must hold the initial stock.
+    fn check_axes(&self, simulation: &SimulationControl) -> Result<(), String> {
+        let initial_stock_qty = simulation.first_day.stock_qty().to_f64().unwrap_or(0.0);
+        match self.axes.iter().find(|axis| {
+            axis.parameter == SweepParameter::StockMaximumQuantity
+                && axis.min().round() < initial_stock_qty
+        }) {
+            Some(axis) => Err(format!(
+                "Sweep values of stock_maximum_quantity must be at least the initial stock quantity {}, got {}",
+                initial_stock_qty,
+                axis.min()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn apply(&self, sim_param: &mut SimulationParameters, point: &[f64]) -> Result<(), String> {
+        for (axis, value) in self.axes.iter().zip(point) {
+            axis.parameter.apply(sim_param, *value)?;
+        }
+        Ok(())
+    }
+
+    fn to_point(
+        point_index: usize,
+        sim_param: &SimulationParameters,
+        summary: NewProductSimulationSummary,
+    ) -> Result<NewProductParameterSweepPoint, Box<dyn std::error::Error>> {
+        Ok(NewProductParameterSweepPoint {
+            point_index: i32::try_from(point_index)?,
+            random_range_factor: Self::to_decimal(sim_param.random_range_factor)?,
+            entry_trend_factor: Self::to_decimal(sim_param.entry_trend_factor)?,
+            withdrawal_trend_factor: Self::to_decimal(sim_param.withdrawal_trend_factor)?,
+            stock_maximum_quantity: i32::try_from(sim_param.stock_maximum_quantity)?,
+            new_batch_default_expiration_days: i16::try_from(
+                sim_param.new_batch_default_expiration_days,
+            )?,
+            qc_rejection_rate: Self::to_decimal(sim_param.qc_rejection_rate)?,
+            reorder_point: sim_param
+                .replenishment
+                .map(|policy| i32::try_from(policy.reorder_point))
+                .transpose()?,
+            lead_time_days: sim_param
+                .replenishment
+                .map(|policy| i16::try_from(policy.lead_time_days))
+                .transpose()?,
+            probability_losses_by_missing: summary.probability_losses_by_missing,
+            probability_losses_by_nospace: summary.probability_losses_by_nospace,
+            probability_losses_by_expirat: summary.probability_losses_by_expirat,
+            probability_losses_by_rejection: summary.probability_losses_by_rejection,
+            probability_no_losses: summary.probability_no_losses,
+            expected_loss_cost: summary.expected_loss_cost,
+        })
+    }
+
+    /// Tornado chart data: for each parameter and metric, the mean metric over the points
+    /// with the parameter in its lower quartile and over the points in its upper quartile,
+    /// with the mean parameter value of each group. Parameters without variation are skipped.
+    fn sensitivities(
+        &self,
+        points: &[Vec<f64>],
+        results: &[NewProductParameterSweepPoint],
+    ) -> Result<Vec<NewProductParameterSweepSensitivity>, Box<dyn std::error::Error>> {
+        let mut sensitivities = Vec::new();
+        for (i, axis) in self.axes.iter().enumerate() {
+            let values = sorted(points.iter().map(|point| point[i]).collect());
+            let (p25, p75) = match (percentile(&values, 25.0), percentile(&values, 75.0)) {
+                (Some(p25), Some(p75)) if p25 < p75 => (p25, p75),
+                _ => continue,
+            };
+            let (low_value, low) = Self::group(points, results, i, |value| value <= p25);
+            let (high_value, high) = Self::group(points, results, i, |value| value >= p75);
+            for (metric, get) in SWEEP_METRICS {
+                let metric_at_low = Self::mean(&low, get);
+                let metric_at_high = Self::mean(&high, get);
+                sensitivities.push(NewProductParameterSweepSensitivity {
+                    parameter: axis.parameter.name().to_owned(),
+                    metric: metric.to_owned(),
+                    low_value: Self::to_decimal(low_value)?,
+                    high_value: Self::to_decimal(high_value)?,
+                    metric_at_low: Self::to_decimal(metric_at_low)?,
+                    metric_at_high: Self::to_decimal(metric_at_high)?,
+                    swing: Self::to_decimal((metric_at_high - metric_at_low).abs())?,
+                });
+            }
+        }
+        Ok(sensitivities)
+    }
+
+    /// Results of the points whose value of the axis `i` matches, with their mean value.
+    fn group<'a>(
+        points: &[Vec<f64>],
+        results: &'a [NewProductParameterSweepPoint],
+        i: usize,
+        matches: impl Fn(f64) -> bool,
+    ) -> (f64, Vec<&'a NewProductParameterSweepPoint>) {
+        let (values, group): (Vec<f64>, Vec<&NewProductParameterSweepPoint>) = points
+            .iter()
+            .zip(results)
+            .filter(|(point, _)| matches(point[i]))
+            .map(|(point, result)| (point[i], result))
+            .unzip();
+        (
+            values.iter().sum::<f64>() / values.len().max(1) as f64,
+            group,
+        )
+    }
+
+    fn mean(results: &[&NewProductParameterSweepPoint], get: MetricGetter) -> f64 {
+        if results.is_empty() {
+            return 0.0;
+        }
+        results
+            .iter()
+            .filter_map(|result| get(result).to_f64())
+            .sum::<f64>()
+            / results.len() as f64
+    }
+
+    fn to_decimal(value: f64) -> Result<BigDecimal, Box<dyn std::error::Error>> {
+        BigDecimal::from_f64(value).ok_or(format!("Invalid sweep value: {}", value).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_parse_sweep_axes() {
+        let axes =
+            SweepAxis::parse_all("random_range_factor=0.05,0.1;stock_maximum_quantity=100:300:100")
+                .unwrap();
+        assert_eq!(
+            axes,
+            vec![
+                SweepAxis {
+                    parameter: SweepParameter::RandomRangeFactor,
+                    values: vec![0.05, 0.1],
+                },
+                SweepAxis {
+                    parameter: SweepParameter::StockMaximumQuantity,
+                    values: vec![100.0, 200.0, 300.0],
+                },
+            ]
+        );
+        assert!(SweepAxis::parse_all("").is_err());
+        assert!(SweepAxis::parse_all("unknown=1").is_err());
+        assert!(SweepAxis::parse_all("reorder_point=1:0:1").is_err());
+        assert!(SweepAxis::parse_all("lead_time_days=1;lead_time_days=2").is_err());
+        assert!(SweepAxis::parse_all("stock_maximum_quantity=0:1e12:1").is_err());
+    }
+
+    #[test]
+    fn should_build_grid_and_random_points() {
+        let mut sweep = ParameterSweep {
+            axes: SweepAxis::parse_all(
+                "random_range_factor=0.05,0.1;stock_maximum_quantity=100:300:100",
+            )
+            .unwrap(),
+            design: SweepDesign::Grid,
+            runs: 1,
+        };
+        let points = sweep.points().unwrap();
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0], vec![0.05, 100.0]);
+        assert_eq!(points[5], vec![0.1, 300.0]);
+
+        sweep.design = SweepDesign::RandomSample { samples: 20 };
+        let points = sweep.points().unwrap();
+        assert_eq!(points.len(), 20);
+        assert!(points
+            .iter()
+            .all(|point| (0.05..=0.1).contains(&point[0]) && (100.0..=300.0).contains(&point[1])));
+
+        sweep.design = SweepDesign::RandomSample { samples: u64::MAX };
+        assert!(sweep.points().is_err());
+        sweep.axes = SweepAxis::parse_all(
+            "stock_maximum_quantity=1:100:1;reorder_point=1:100:1;lead_time_days=1:100:1",
+        )
+        .unwrap();
+        sweep.design = SweepDesign::Grid;
+        assert!(sweep.points().is_err());
+    }
+
+    #[test]
+    fn should_need_replenishment_policy_to_sweep_lead_time() {
+        let mut sim_param = SimulationParameters::new(100, 5, vec![]);
+        assert!(SweepParameter::LeadTimeDays
+            .apply(&mut sim_param, 2.0)
+            .is_err());
+        assert!(SweepParameter::StockMaximumQuantity
+            .apply(&mut sim_param, -1.0)
+            .is_err());
+        SweepParameter::StockMaximumQuantity
+            .apply(&mut sim_param, 149.6)
+            .unwrap();
+        assert_eq!(sim_param.stock_maximum_quantity, 150);
+    }
+}
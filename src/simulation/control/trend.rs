@@ -0,0 +1,102 @@
+/// Trend of a product movement, fitted by a log-linear regression of its yearly volumes
+/// over the simulated season, `ln(volume) = a + b * year`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrendFit {
+    /// Volume expected for the target year over the average of the fitted years,
+    /// i.e. the multiplying factor over the historic averages.
+    pub factor: f64,
+    /// Standard error of the log of the factor.
+    pub log_std_error: f64,
+}
+
+impl Default for TrendFit {
+    fn default() -> Self {
+        Self {
+            factor: 1.0,
+            log_std_error: 0.0,
+        }
+    }
+}
+
+impl TrendFit {
+    /// Fits the `(year, volume)` pairs, projecting the trend to `target_year`.
+    /// Years without volume are left out, as well as the target year and the later ones,
+    /// and there is no trend with less than two years left.
+    pub fn fit(yearly_volumes: &[(i32, f64)], target_year: i32) -> Self {
+        let points: Vec<(f64, f64)> = yearly_volumes
+            .iter()
+            .filter(|(year, volume)| *year < target_year && *volume > 0.0)
+            .map(|(year, volume)| (*year as f64, volume.ln()))
+            .collect();
+        let n = points.len() as f64;
+        if points.len() < 2 {
+            return Self::default();
+        }
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx = points
+            .iter()
+            .map(|(x, _)| (x - mean_x).powi(2))
+            .sum::<f64>();
+        let sxy = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum::<f64>();
+        let slope = sxy / sxx;
+        let intercept = mean_y - slope * mean_x;
+        let target_x = target_year as f64;
+        let expected_volume = (intercept + slope * target_x).exp();
+        let mean_volume = points.iter().map(|(_, y)| y.exp()).sum::<f64>() / n;
+
+        let log_std_error = if points.len() > 2 {
+            let sse = points
+                .iter()
+                .map(|(x, y)| (y - intercept - slope * x).powi(2))
+                .sum::<f64>();
+            let residual_std = (sse / (n - 2.0)).sqrt();
+            residual_std * (1.0 / n + (target_x - mean_x).powi(2) / sxx).sqrt()
+        } else {
+            0.0
+        };
+        Self {
+            factor: expected_volume / mean_volume,
+            log_std_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_fit_a_steady_growth() {
+        let volumes: Vec<(i32, f64)> = (2017..2022)
+            .map(|year| (year, 100.0 * 1.3_f64.powi(year - 2017)))
+            .collect();
+        let fit = TrendFit::fit(&volumes, 2022);
+        let mean_volume = volumes.iter().map(|(_, v)| v).sum::<f64>() / 5.0;
+        assert!((fit.factor - 100.0 * 1.3_f64.powi(5) / mean_volume).abs() < 1e-9);
+        assert!(fit.factor > 1.3);
+        assert!(fit.log_std_error < 1e-9);
+    }
+
+    #[test]
+    fn should_measure_the_uncertainty_of_a_noisy_trend() {
+        let fit = TrendFit::fit(
+            &[(2018, 100.0), (2019, 80.0), (2020, 120.0), (2021, 100.0)],
+            2022,
+        );
+        assert!(fit.factor > 1.0);
+        assert!(fit.log_std_error > 0.0);
+    }
+
+    #[test]
+    fn should_not_fit_without_enough_years() {
+        assert_eq!(TrendFit::fit(&[], 2022), TrendFit::default());
+        assert_eq!(
+            TrendFit::fit(&[(2020, 0.0), (2021, 100.0), (2022, 200.0)], 2022),
+            TrendFit::default()
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::str::FromStr;
+
+use crate::{
+    data::product_batch::ProductBatch,
+    simulation::control::{
+        parameter::{ReplenishmentPolicy, ScheduledMovement, ShortageMode},
+        SimulationControl,
+    },
+};
+use bigdecimal::Signed;
+use chrono::{DateTime, Utc};
+use sqlx::types::BigDecimal;
+
+/// In-memory changes over the data of a product, simulated without touching
+/// its props, batches or historic. Unset values keep the product data.
+#[derive(Debug, Clone, Default)]
+pub struct WhatIfOverrides {
+    /// Batches added to the initial stock, e.g. a delivery being negotiated.
+    pub extra_batches: Vec<ProductBatch>,
+    pub stock_maximum_quantity: Option<u64>,
+    pub new_batch_default_expiration_days: Option<u64>,
+    pub qc_hold_days: Option<u64>,
+    pub qc_rejection_rate: Option<f64>,
+    pub shortage_mode: Option<ShortageMode>,
+    pub replenishment: Option<ReplenishmentPolicy>,
+    pub scheduled_movements: Vec<ScheduledMovement>,
+    /// Distribution of the scenario movements around the historic quantities.
+    pub random_range_factor: Option<f64>,
+    pub entry_trend_factor: Option<f64>,
+    pub withdrawal_trend_factor: Option<f64>,
+    pub simulation_runs: Option<u64>,
+}
+
+impl WhatIfOverrides {
+    pub fn validate(self) -> Result<Self, String> {
+        let is_share = |value: Option<f64>| value.is_none_or(|v| (0.0..=1.0).contains(&v));
+        let is_factor = |value: Option<f64>| value.is_none_or(|v| v >= 0.0);
+        let is_valid_batch = |batch: &ProductBatch| {
+            !batch.quantity.is_negative() && batch.deadline_date >= batch.entry_date
+        };
+        let is_valid_movement = |movement: &ScheduledMovement| {
+            !movement.entry_qty.is_negative() && !movement.withdrawal_qty.is_negative()
+        };
+        if !is_share(self.qc_rejection_rate)
+            || !is_share(self.random_range_factor)
+            || !is_factor(self.entry_trend_factor)
+            || !is_factor(self.withdrawal_trend_factor)
+            || self.simulation_runs == Some(0)
+            || !self.extra_batches.iter().all(is_valid_batch)
+            || !self.scheduled_movements.iter().all(is_valid_movement)
+        {
+            return Err(format!("Invalid what-if overrides: {:?}", self));
+        }
+        Ok(self)
+    }
+
+    /// The extra batches are merged into the initial stock by entry date,
+    /// so that they are withdrawn in FIFO order with the existing ones. A batch entering
+    /// after the first day is rejected: it is not in stock yet, use a scheduled entry,
+    /// and so is a stock maximum quantity below the initial stock with the extra batches.
+    pub fn apply(&self, simulation: &mut SimulationControl) -> Result<(), String> {
+        let first_date = simulation.first_day.date;
+        if let Some(batch) = self
+            .extra_batches
+            .iter()
+            .find(|batch| batch.entry_date > first_date)
+        {
+            return Err(format!(
+                "What-if batch entering after the first day {}, schedule an entry instead: {:?}",
+                first_date, batch
+            ));
+        }
+        // A capacity below the stock would leave no space for any entry while it is above it.
+        if let Some(stock_maximum_quantity) = self.stock_maximum_quantity {
+            let stock_qty = simulation.first_day.stock_qty()
+                + self
+                    .extra_batches
+                    .iter()
+                    .map(|batch| &batch.quantity)
+                    .sum::<BigDecimal>();
+            if stock_qty > stock_maximum_quantity {
+                return Err(format!(
+                    "What-if stock_maximum_quantity {} below the initial stock quantity {}",
+                    stock_maximum_quantity, stock_qty
+                ));
+            }
+        }
+        let batches = &mut simulation.first_day.batches;
+        batches.extend(self.extra_batches.iter().cloned());
+        batches.sort_by_key(|batch| batch.entry_date);
+
+        let sim_param = &mut simulation.sim_param;
+        if let Some(stock_maximum_quantity) = self.stock_maximum_quantity {
+            sim_param.stock_maximum_quantity = stock_maximum_quantity;
+        }
+        if let Some(expiration_days) = self.new_batch_default_expiration_days {
+            sim_param.set_new_batch_expiration_days(expiration_days);
+        }
+        if let Some(qc_hold_days) = self.qc_hold_days {
+            sim_param.qc_hold_days = qc_hold_days;
+        }
+        if let Some(qc_rejection_rate) = self.qc_rejection_rate {
+            sim_param.qc_rejection_rate = qc_rejection_rate;
+        }
+        if let Some(shortage_mode) = self.shortage_mode {
+            sim_param.shortage_mode = shortage_mode;
+        }
+        if let Some(replenishment) = self.replenishment {
+            sim_param.replenishment = Some(replenishment);
+        }
+        sim_param
+            .scheduled_movements
+            .extend(self.scheduled_movements.iter().cloned());
+        if let Some(random_range_factor) = self.random_range_factor {
+            sim_param.random_range_factor = random_range_factor;
+        }
+        if let Some(entry_trend_factor) = self.entry_trend_factor {
+            sim_param.entry_trend_factor = entry_trend_factor;
+        }
+        if let Some(withdrawal_trend_factor) = self.withdrawal_trend_factor {
+            sim_param.withdrawal_trend_factor = withdrawal_trend_factor;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for WhatIfOverrides {
+    type Err = String;
+
+    /// Parses `;` separated `name=value` overrides, e.g.
+    /// `stock_maximum_quantity=300;scheduled=2022-01-07T00:00:00Z/200/0`.
+    /// Repeatable: `batch=entry_date/deadline_date/quantity` and
+    /// `scheduled=date/entry_qty/withdrawal_qty`. Policy: `replenishment=reorder_point/lead_time_days`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut overrides = WhatIfOverrides::default();
+        for item in s.split(';').filter(|item| !item.trim().is_empty()) {
+            let (name, value) = item
+                .split_once('=')
+                .ok_or(format!("Expected name=value, got {:?}", item))?;
+            let value = value.trim();
+            match name.trim() {
+                "stock_maximum_quantity" => {
+                    overrides.stock_maximum_quantity = Some(parse_value(value)?)
+                }
+                "new_batch_default_expiration_days" => {
+                    overrides.new_batch_default_expiration_days = Some(parse_value(value)?)
+                }
+                "qc_hold_days" => overrides.qc_hold_days = Some(parse_value(value)?),
+                "qc_rejection_rate" => overrides.qc_rejection_rate = Some(parse_value(value)?),
+                "shortage_mode" => overrides.shortage_mode = Some(ShortageMode::from_str(value)?),
+                "replenishment" => {
+                    let [reorder_point, lead_time_days] = parse_parts(value)?;
+                    overrides.replenishment = Some(ReplenishmentPolicy {
+                        reorder_point: parse_value(reorder_point)?,
+                        lead_time_days: parse_value(lead_time_days)?,
+                    });
+                }
+                "batch" => {
+                    let [entry_date, deadline_date, quantity] = parse_parts(value)?;
+                    overrides.extra_batches.push(ProductBatch {
+                        entry_date: parse_value::<DateTime<Utc>>(entry_date)?,
+                        deadline_date: parse_value::<DateTime<Utc>>(deadline_date)?,
+                        finished_date: None,
+                        is_finished: false,
+                        quantity: parse_value::<BigDecimal>(quantity)?,
+                        qc_release_date: None,
+                    });
+                }
+                "scheduled" => {
+                    let [date, entry_qty, withdrawal_qty] = parse_parts(value)?;
+                    overrides.scheduled_movements.push(ScheduledMovement {
+                        date: parse_value::<DateTime<Utc>>(date)?,
+                        entry_qty: parse_value::<BigDecimal>(entry_qty)?,
+                        withdrawal_qty: parse_value::<BigDecimal>(withdrawal_qty)?,
+                    });
+                }
+                "random_range_factor" => overrides.random_range_factor = Some(parse_value(value)?),
+                "entry_trend_factor" => overrides.entry_trend_factor = Some(parse_value(value)?),
+                "withdrawal_trend_factor" => {
+                    overrides.withdrawal_trend_factor = Some(parse_value(value)?)
+                }
+                "simulation_runs" => overrides.simulation_runs = Some(parse_value(value)?),
+                other => return Err(format!("Unknown what-if override: {:?}", other)),
+            }
+        }
+        Ok(overrides)
+    }
+}
+
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|err| format!("Invalid what-if value {:?}: {}", value, err))
+}
+
+fn parse_parts<const N: usize>(value: &str) -> Result<[&str; N], String> {
+    value
+        .split('/')
+        .collect::<Vec<&str>>()
+        .try_into()
+        .map_err(|_| format!("Expected {} '/' separated values, got {:?}", N, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_overrides() {
+        let overrides = WhatIfOverrides::from_str(
+            "stock_maximum_quantity=300; shortage_mode=backorder;replenishment=50/3;\
+             batch=2022-01-01T00:00:00Z/2022-01-20T00:00:00Z/120;\
+             scheduled=2022-01-07T00:00:00Z/200/0;scheduled=2022-01-08T00:00:00Z/0/40;\
+             withdrawal_trend_factor=1.2",
+        )
+        .unwrap();
+        assert_eq!(overrides.stock_maximum_quantity, Some(300));
+        assert_eq!(overrides.shortage_mode, Some(ShortageMode::Backorder));
+        assert_eq!(
+            overrides.replenishment,
+            Some(ReplenishmentPolicy {
+                reorder_point: 50,
+                lead_time_days: 3,
+            })
+        );
+        assert_eq!(overrides.extra_batches.len(), 1);
+        assert_eq!(overrides.extra_batches[0].quantity, BigDecimal::from(120));
+        assert_eq!(overrides.scheduled_movements.len(), 2);
+        assert_eq!(
+            overrides.scheduled_movements[1].withdrawal_qty,
+            BigDecimal::from(40)
+        );
+        assert_eq!(overrides.withdrawal_trend_factor, Some(1.2));
+        assert_eq!(overrides.random_range_factor, None);
+    }
+
+    #[test]
+    fn should_reject_invalid_overrides() {
+        assert!(WhatIfOverrides::from_str("stock_capacity=300").is_err());
+        assert!(WhatIfOverrides::from_str("scheduled=2022-01-07T00:00:00Z/200").is_err());
+        assert!(WhatIfOverrides::from_str("random_range_factor=1.5")
+            .unwrap()
+            .validate()
+            .is_err());
+        assert!(WhatIfOverrides::from_str("simulation_runs=0")
+            .unwrap()
+            .validate()
+            .is_err());
+        assert!(
+            WhatIfOverrides::from_str("batch=2022-01-01T00:00:00Z/2022-01-20T00:00:00Z/-120")
+                .unwrap()
+                .validate()
+                .is_err()
+        );
+        assert!(
+            WhatIfOverrides::from_str("batch=2022-01-20T00:00:00Z/2022-01-01T00:00:00Z/120")
+                .unwrap()
+                .validate()
+                .is_err()
+        );
+        assert!(
+            WhatIfOverrides::from_str("scheduled=2022-01-07T00:00:00Z/200/-5")
+                .unwrap()
+                .validate()
+                .is_err()
+        );
+    }
+}
@@ -1,34 +1,163 @@
 use crate::data::{
-    general_conf::GeneralConfRepository,
+    general_conf::{GeneralConf, GeneralConfRepository},
+    product_analog::ProductAnalogRepository,
     product_batch::{ProductBatch, ProductBatchRepository},
-    product_mov_hist::{ProductMovHist, ProductMovHistRepository},
+    product_calibration_backtest::ProductCalibrationBacktestRepository,
+    product_calibration_reliability::ProductCalibrationReliabilityRepository,
+    product_capacity_curve::ProductCapacityCurveRepository,
+    product_capacity_recommendation::ProductCapacityRecommendationRepository,
+    product_forecast_backtest::{ProductForecastBacktest, ProductForecastBacktestRepository},
+    product_mov_event::{ProductMovEventRepository, ProductMovHourlyProfile},
+    product_mov_hist::{ProductMovHist, ProductMovHistRepository, ProductMovYearlyVolume},
+    product_mov_outlier::{NewProductMovOutlier, ProductMovOutlierRepository},
+    product_parameter_sweep::ProductParameterSweepRepository,
+    product_parameter_sweep_point::ProductParameterSweepPointRepository,
+    product_parameter_sweep_sensitivity::ProductParameterSweepSensitivityRepository,
+    product_promotion::ProductPromotionRepository,
     product_props::ProductPropsRepository,
+    product_random_range_suggestion::{
+        NewProductRandomRangeSuggestion, ProductRandomRangeSuggestionRepository,
+    },
+    product_replenishment_recommendation::ProductReplenishmentRecommendationRepository,
+    product_shelf_life_analysis::ProductShelfLifeAnalysisRepository,
+    product_shelf_life_sensitivity::ProductShelfLifeSensitivityRepository,
+    product_simulation_data_quality::{
+        ProductSimulationDataQuality, ProductSimulationDataQualityRepository,
+    },
+    product_simulation_first_loss::{
+        ProductSimulationFirstLoss, ProductSimulationFirstLossRepository,
+    },
+    product_simulation_kpi::{ProductSimulationKpi, ProductSimulationKpiRepository},
+    product_simulation_stress_result::{
+        ProductSimulationStressResult, ProductSimulationStressResultRepository,
+    },
+    product_simulation_summary::{ProductSimulationSummary, ProductSimulationSummaryRepository},
+    product_simulation_summary_by_day::{
+        ProductSimulationSummaryByDay, ProductSimulationSummaryByDayRepository,
+    },
+    stress_test::StressTestRepository,
 };
+use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 use uuid::Uuid;
 
-use std::{convert::TryFrom, env};
+use std::{convert::TryFrom, env, str::FromStr};
 
-use chrono::{DateTime, Datelike, Days, Utc};
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
 
-use super::control::SimulationControl;
+use super::control::{
+    analog::{AnalogBlend, AnalogHistory},
+    calibration::{reference_dates, replay_observed, CalibrationCounter, CalibrationResult},
+    capacity::CapacitySearch,
+    cost::LossCost,
+    forecast::{
+        DailyForecast, ForecastModel, ForecastParams, HoltWintersParams, IntermittentDemand,
+    },
+    model_selection::{ModelSelection, SelectionMetric, CANDIDATE_MODELS},
+    outlier::{OutlierAction, OutlierCleaning, OutlierMethod},
+    parameter::{
+        DayEvent, ExpirationComparison, HistoryImputation, Promotion, ReplenishmentPolicy,
+        ShortageMode, SimulationParameters, HOURS_IN_A_DAY,
+    },
+    range_factor::RangeFactorFit,
+    regime::DemandRegimes,
+    replenishment::ReplenishmentSearch,
+    shelf_life::ShelfLifeSensitivity,
+    stress::{StressKind, StressTest, StressTestSuite},
+    sweep::{ParameterSweep, SweepAxis, SweepDesign},
+    trend::TrendFit,
+    SimulationControl,
+};
+
+pub(crate) use super::control::{
+    shelf_life::ShelfLifeRange, what_if::WhatIfOverrides, SimulationSummary,
+};
 
 const DEFAULT_DATABASE_POOL_SIZE: u32 = 5;
+const SIMULATION_RUNS: u64 = 100;
 
 struct SimData {
     initial_date: DateTime<Utc>,
     final_date: DateTime<Utc>,
     stock_maximum_quantity: u64,
     new_batch_default_expiration_days: u64,
+    new_batch_expiration_hours: Option<u64>,
     product_batches: Vec<ProductBatch>,
     historic: Vec<ProductMovHist>,
+    day_events_order: Vec<DayEvent>,
+    expiration_comparison: ExpirationComparison,
+    time_step_hours: u32,
+    intraday_profile: Vec<ProductMovHourlyProfile>,
+    qc_hold_days: u64,
+    qc_rejection_rate: f64,
+    shortage_mode: ShortageMode,
+    random_range_factor: f64,
+    loss_cost: LossCost,
+    replenishment: Option<ReplenishmentPolicy>,
+    replenishment_lead_time_days: u64,
+    target_service_level: f64,
+    replenishment_candidates: u64,
+    target_nospace_probability: f64,
+    capacity_candidates: u64,
+    capacity_search_max_factor: u64,
+    promotions: Vec<Promotion>,
+    entry_trend: TrendFit,
+    withdrawal_trend: TrendFit,
+    daily_forecast: Vec<DailyForecast>,
+    intermittent_demand: Option<IntermittentDemand>,
+    history_imputation: HistoryImputation,
+    demand_regimes: Option<DemandRegimes>,
+    outlier_log: Option<OutlierLog>,
+}
+
+/// Outliers found in the daily history from `start_date` until before `end_date`.
+struct OutlierLog {
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    outliers: Vec<NewProductMovOutlier>,
+}
+
+/// Latest simulation of a product with its details, as saved.
+#[derive(Debug)]
+pub struct SimulationReport {
+    pub summary: ProductSimulationSummary,
+    /// Daily loss probabilities and stock percentiles, for the fan chart.
+    pub by_day: Vec<ProductSimulationSummaryByDay>,
+    pub kpis: Vec<ProductSimulationKpi>,
+    pub first_losses: Vec<ProductSimulationFirstLoss>,
+    pub stress_results: Vec<ProductSimulationStressResult>,
+    pub data_quality: Option<ProductSimulationDataQuality>,
 }
 
 pub struct Orchestrator {
+    db: Pool<Postgres>,
     product_mov_hist_repository: ProductMovHistRepository,
+    product_mov_outlier_repository: ProductMovOutlierRepository,
+    product_analog_repository: ProductAnalogRepository,
+    product_mov_event_repository: ProductMovEventRepository,
     product_batch_repository: ProductBatchRepository,
     general_conf_repository: GeneralConfRepository,
     product_props_repository: ProductPropsRepository,
+    product_promotion_repository: ProductPromotionRepository,
+    product_forecast_backtest_repository: ProductForecastBacktestRepository,
+    product_calibration_backtest_repository: ProductCalibrationBacktestRepository,
+    product_calibration_reliability_repository: ProductCalibrationReliabilityRepository,
+    product_random_range_suggestion_repository: ProductRandomRangeSuggestionRepository,
+    product_simulation_summary_repository: ProductSimulationSummaryRepository,
+    product_simulation_summary_by_day_repository: ProductSimulationSummaryByDayRepository,
+    product_simulation_kpi_repository: ProductSimulationKpiRepository,
+    product_simulation_data_quality_repository: ProductSimulationDataQualityRepository,
+    product_simulation_first_loss_repository: ProductSimulationFirstLossRepository,
+    product_simulation_stress_result_repository: ProductSimulationStressResultRepository,
+    stress_test_repository: StressTestRepository,
+    product_replenishment_recommendation_repository: ProductReplenishmentRecommendationRepository,
+    product_capacity_recommendation_repository: ProductCapacityRecommendationRepository,
+    product_capacity_curve_repository: ProductCapacityCurveRepository,
+    product_shelf_life_analysis_repository: ProductShelfLifeAnalysisRepository,
+    product_shelf_life_sensitivity_repository: ProductShelfLifeSensitivityRepository,
+    product_parameter_sweep_repository: ProductParameterSweepRepository,
+    product_parameter_sweep_point_repository: ProductParameterSweepPointRepository,
+    product_parameter_sweep_sensitivity_repository: ProductParameterSweepSensitivityRepository,
 }
 
 impl Orchestrator {
@@ -36,9 +165,57 @@ impl Orchestrator {
         let db = Self::get_db_conn_pool().await?;
         Ok(Self {
             product_mov_hist_repository: ProductMovHistRepository::new(db.clone()),
+            product_mov_outlier_repository: ProductMovOutlierRepository::new(db.clone()),
+            product_analog_repository: ProductAnalogRepository::new(db.clone()),
+            product_mov_event_repository: ProductMovEventRepository::new(db.clone()),
             product_batch_repository: ProductBatchRepository::new(db.clone()),
             general_conf_repository: GeneralConfRepository::new(db.clone()),
             product_props_repository: ProductPropsRepository::new(db.clone()),
+            product_promotion_repository: ProductPromotionRepository::new(db.clone()),
+            product_forecast_backtest_repository: ProductForecastBacktestRepository::new(
+                db.clone(),
+            ),
+            product_calibration_backtest_repository: ProductCalibrationBacktestRepository::new(
+                db.clone(),
+            ),
+            product_calibration_reliability_repository:
+                ProductCalibrationReliabilityRepository::new(db.clone()),
+            product_random_range_suggestion_repository: ProductRandomRangeSuggestionRepository::new(
+                db.clone(),
+            ),
+            product_simulation_summary_repository: ProductSimulationSummaryRepository::new(
+                db.clone(),
+            ),
+            product_simulation_summary_by_day_repository:
+                ProductSimulationSummaryByDayRepository::new(db.clone()),
+            product_simulation_kpi_repository: ProductSimulationKpiRepository::new(db.clone()),
+            product_simulation_data_quality_repository: ProductSimulationDataQualityRepository::new(
+                db.clone(),
+            ),
+            product_simulation_first_loss_repository: ProductSimulationFirstLossRepository::new(
+                db.clone(),
+            ),
+            product_simulation_stress_result_repository:
+                ProductSimulationStressResultRepository::new(db.clone()),
+            stress_test_repository: StressTestRepository::new(db.clone()),
+            product_replenishment_recommendation_repository:
+                ProductReplenishmentRecommendationRepository::new(db.clone()),
+            product_capacity_recommendation_repository:
+                ProductCapacityRecommendationRepository::new(db.clone()),
+            product_capacity_curve_repository: ProductCapacityCurveRepository::new(db.clone()),
+            product_shelf_life_analysis_repository: ProductShelfLifeAnalysisRepository::new(
+                db.clone(),
+            ),
+            product_shelf_life_sensitivity_repository: ProductShelfLifeSensitivityRepository::new(
+                db.clone(),
+            ),
+            product_parameter_sweep_repository: ProductParameterSweepRepository::new(db.clone()),
+            product_parameter_sweep_point_repository: ProductParameterSweepPointRepository::new(
+                db.clone(),
+            ),
+            product_parameter_sweep_sensitivity_repository:
+                ProductParameterSweepSensitivityRepository::new(db.clone()),
+            db,
         })
     }
 
@@ -47,16 +224,706 @@ impl Orchestrator {
         product_id: Uuid,
         reference_date: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sim_data = self.prepare_data_for(product_id, reference_date).await?;
+        let outlier_log = sim_data.outlier_log.take();
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+
+        let mut summary = simulation.run_n_times(SIMULATION_RUNS)?;
+        summary.stress_results = self.get_stress_test_suite().await?.run(&mut simulation)?;
+        self.save_summary(&summary).await?;
+        // Only the simulation of the product logs the outliers it was cleaned of.
+        if let Some(outlier_log) = outlier_log {
+            self.product_mov_outlier_repository
+                .replace_all_by_product_and_dates(
+                    product_id,
+                    outlier_log.start_date,
+                    outlier_log.end_date,
+                    &outlier_log.outliers,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the simulation of a product with in-memory overrides over its data.
+    /// The results are only written when a label is given, tagging the summary
+    /// as a what-if run so that it is not mistaken for the product forecast.
+    pub(crate) async fn run_what_if_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+        overrides: WhatIfOverrides,
+        what_if_label: Option<&str>,
+    ) -> Result<SimulationSummary, Box<dyn std::error::Error>> {
+        let overrides = overrides.validate()?;
+        let sim_data = self.prepare_data_for(product_id, reference_date).await?;
+        let simulation_runs = overrides.simulation_runs.unwrap_or(SIMULATION_RUNS);
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+        overrides.apply(&mut simulation)?;
+
+        let mut summary = simulation.run_n_times(simulation_runs)?;
+        summary.stress_results = self.get_stress_test_suite().await?.run(&mut simulation)?;
+        if let Some(label) = what_if_label {
+            summary.summary.what_if_label = Some(label.to_string());
+            self.save_summary(&summary).await?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Returns the id generated for the summary.
+    /// Saves the summary with its details in a single transaction.
+    async fn save_summary(
+        &self,
+        summary: &SimulationSummary,
+    ) -> Result<i32, Box<dyn std::error::Error>> {
+        let mut tx = self.db.begin().await?;
+        let (_, product_simulation_summary_id) = self
+            .product_simulation_summary_repository
+            .insert(&mut tx, &summary.summary)
+            .await?;
+        self.product_simulation_summary_by_day_repository
+            .insert_all(&mut tx, product_simulation_summary_id, &summary.by_day)
+            .await?;
+        self.product_simulation_kpi_repository
+            .insert_all(&mut tx, product_simulation_summary_id, &summary.kpis)
+            .await?;
+        self.product_simulation_first_loss_repository
+            .insert_all(
+                &mut tx,
+                product_simulation_summary_id,
+                &summary.first_losses,
+            )
+            .await?;
+        self.product_simulation_stress_result_repository
+            .insert_all(
+                &mut tx,
+                product_simulation_summary_id,
+                &summary.stress_results,
+            )
+            .await?;
+        self.product_simulation_data_quality_repository
+            .insert(
+                &mut tx,
+                product_simulation_summary_id,
+                &summary.data_quality,
+            )
+            .await?;
+        tx.commit().await?;
+        Ok(product_simulation_summary_id)
+    }
+
+    /// Reads back the latest simulation of a product, leaving out the what-if runs.
+    pub async fn report_by_product(
+        &self,
+        product_id: Uuid,
+    ) -> Result<SimulationReport, Box<dyn std::error::Error>> {
+        let (_, summaries) = self
+            .product_simulation_summary_repository
+            .find_all_by_product(product_id)
+            .await?;
+        let summary = summaries
+            .into_iter()
+            .filter(|summary| summary.what_if_label.is_none())
+            .max_by_key(|summary| summary.id)
+            .ok_or(format!("No simulation of the product {}", product_id))?;
+        let (_, by_day) = self
+            .product_simulation_summary_by_day_repository
+            .find_all_by_product_simulation_summary(summary.id)
+            .await?;
+        let (_, kpis) = self
+            .product_simulation_kpi_repository
+            .find_all_by_product_simulation_summary(summary.id)
+            .await?;
+        let (_, first_losses) = self
+            .product_simulation_first_loss_repository
+            .find_all_by_product_simulation_summary(summary.id)
+            .await?;
+        let (_, stress_results) = self
+            .product_simulation_stress_result_repository
+            .find_all_by_product_simulation_summary(summary.id)
+            .await?;
+        let (_, data_quality) = self
+            .product_simulation_data_quality_repository
+            .find_by_product_simulation_summary(summary.id)
+            .await?;
+        Ok(SimulationReport {
+            summary,
+            by_day,
+            kpis,
+            first_losses,
+            stress_results,
+            data_quality,
+        })
+    }
+
+    /// Latest simulation of each product, leaving out the what-if runs,
+    /// the ones with more money at risk first.
+    pub async fn rank_by_expected_loss_cost(
+        &self,
+    ) -> Result<Vec<ProductSimulationSummary>, Box<dyn std::error::Error>> {
+        let (_, summaries) = self
+            .product_simulation_summary_repository
+            .find_latest_ranked_by_expected_loss_cost()
+            .await?;
+        Ok(summaries)
+    }
+
+    async fn get_stress_test_suite(&self) -> Result<StressTestSuite, Box<dyn std::error::Error>> {
+        let (_, stress_tests) = self.stress_test_repository.find_all_by_status(true).await?;
+        let tests = stress_tests
+            .into_iter()
+            .map(|stress_test| {
+                Ok(StressTest {
+                    kind: StressKind::from_str(&stress_test.kind)?,
+                    start_offset_days: u64::try_from(stress_test.start_offset_days)?,
+                    duration_days: stress_test.duration_days.map(u64::try_from).transpose()?,
+                    factor: stress_test
+                        .factor
+                        .to_f64()
+                        .ok_or("Invalid stress test factor")?,
+                    name: stress_test.name,
+                })
+            })
+            .collect::<Result<Vec<StressTest>, Box<dyn std::error::Error>>>()?;
+        Ok(StressTestSuite { tests })
+    }
+
+    /// Suggests a replenishment policy for each active product,
+    /// writing it for review instead of changing the product props.
+    pub async fn recommend_replenishment(
+        &self,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, products) = self
+            .product_props_repository
+            .find_all_by_status(true)
+            .await?;
+        let mut failures = Vec::new();
+        for product in products {
+            if let Err(error) = self
+                .recommend_replenishment_by_product(product.id, reference_date)
+                .await
+            {
+                eprintln!(
+                    "Failure to recommend the replenishment of product {}: {}",
+                    product.id, error
+                );
+                failures.push(product.id);
+            }
+        }
+        Self::check_failures("recommend the replenishment", &failures)
+    }
+
+    pub async fn recommend_replenishment_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sim_data = self.prepare_data_for(product_id, reference_date).await?;
+        let search = ReplenishmentSearch {
+            target_service_level: sim_data.target_service_level,
+            lead_time_days: sim_data.replenishment_lead_time_days,
+            candidates: sim_data.replenishment_candidates,
+            runs: SIMULATION_RUNS,
+        };
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+
+        let recommendation = search.recommend(&mut simulation)?;
+        self.product_replenishment_recommendation_repository
+            .insert(&recommendation)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Backtests the forecast models of each active product, storing the scores
+    /// and the selected model, used by default by the next simulations.
+    pub async fn select_forecast_models(
+        &self,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, products) = self
+            .product_props_repository
+            .find_all_by_status(true)
+            .await?;
+        let mut failures = Vec::new();
+        for product in products {
+            if let Err(error) = self
+                .select_forecast_model_by_product(product.id, reference_date)
+                .await
+            {
+                eprintln!(
+                    "Failure to select the forecast model of product {}: {}",
+                    product.id, error
+                );
+                failures.push(product.id);
+            }
+        }
+        Self::check_failures("select the forecast model", &failures)
+    }
+
+    pub async fn select_forecast_model_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, general_conf) = self.general_conf_repository.find_last().await?;
+        let (_, product_props) = self
+            .product_props_repository
+            .find_one_by_product(product_id)
+            .await?;
+        let reference_date = DateTime::parse_from_rfc3339(reference_date)?.date_naive();
+        let maximum_historic_days = u64::try_from(
+            product_props
+                .maximum_historic_days
+                .unwrap_or(general_conf.default_maximum_historic_days),
+        )?;
+        let selection = ModelSelection {
+            candidates: CANDIDATE_MODELS.to_vec(),
+            origins: usize::try_from(general_conf.default_forecast_backtest_origins)?,
+            horizon_days: usize::try_from(general_conf.default_forecast_backtest_horizon_days)?,
+            pinball_quantile: general_conf
+                .default_forecast_pinball_quantile
+                .to_f64()
+                .ok_or("Invalid default_forecast_pinball_quantile")?,
+            metric: SelectionMetric::from_str(&general_conf.default_forecast_selection_metric)?,
+        };
+
+        let (_, daily_history) = self
+            .product_mov_hist_repository
+            .find_daily_by_product_id(
+                product_id,
+                reference_date - Days::new(maximum_historic_days),
+                reference_date,
+                product_props
+                    .exclude_promotions_from_history
+                    .unwrap_or(general_conf.default_exclude_promotions_from_history),
+            )
+            .await?;
+        let backtests = selection
+            .run(&daily_history)
+            .iter()
+            .map(|backtest| backtest.to_new(&selection))
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        if backtests.is_empty() {
+            eprintln!(
+                "Not enough daily history to backtest the forecast models of {:?}",
+                product_id
+            );
+        }
+        self.product_forecast_backtest_repository
+            .insert_all(product_id, reference_date, &backtests)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Runs the simulation of each active product at past reference dates, before
+    /// `reference_date`, scoring its daily loss probabilities against the losses of
+    /// replaying the observed movements. The scores are written for each product and overall.
+    pub async fn backtest_calibration(
+        &self,
+        reference_date: &str,
+        reference_dates_count: Option<u64>,
+        step_days: Option<u64>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, general_conf) = self.general_conf_repository.find_last().await?;
+        let observed_until = DateTime::parse_from_rfc3339(reference_date)?.date_naive();
+        let dates = Self::get_calibration_dates(
+            &general_conf,
+            observed_until,
+            reference_dates_count,
+            step_days,
+        )?;
+        let (first_reference_date, last_reference_date) = (dates[0], dates[dates.len() - 1]);
+        let bins = usize::try_from(general_conf.default_calibration_bins)?;
+
+        let (_, products) = self
+            .product_props_repository
+            .find_all_by_status(true)
+            .await?;
+        let mut overall = CalibrationCounter::default();
+        let mut failures = Vec::new();
+        for product in products {
+            let backtest: Result<CalibrationCounter, Box<dyn std::error::Error>> = async {
+                let mut counter = CalibrationCounter::default();
+                for date in dates.iter() {
+                    self.backtest_calibration_by_product(
+                        product.id,
+                        *date,
+                        observed_until,
+                        None,
+                        &mut counter,
+                    )
+                    .await?;
+                }
+                let results = counter.summarize(first_reference_date, last_reference_date, bins)?;
+                self.save_calibration(Some(product.id), &results).await?;
+                Ok(counter)
+            }
+            .await;
+            match backtest {
+                Ok(counter) => overall.merge(&counter),
+                Err(error) => {
+                    eprintln!(
+                        "Failure to backtest the calibration of product {}: {}",
+                        product.id, error
+                    );
+                    failures.push(product.id);
+                }
+            }
+        }
+        let results = overall.summarize(first_reference_date, last_reference_date, bins)?;
+        self.save_calibration(None, &results).await?;
+
+        Self::check_failures("backtest the calibration", &failures)
+    }
+
+    /// Reports the products failed by a batch action, once the other products are done.
+    fn check_failures(action: &str, failures: &[Uuid]) -> Result<(), Box<dyn std::error::Error>> {
+        if failures.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "Failure to {} of {} product(s): {:?}",
+            action,
+            failures.len(),
+            failures
+        )
+        .into())
+    }
+
+    /// Past reference dates of the calibration backtest, the oldest first.
+    fn get_calibration_dates(
+        general_conf: &GeneralConf,
+        observed_until: NaiveDate,
+        reference_dates_count: Option<u64>,
+        step_days: Option<u64>,
+    ) -> Result<Vec<NaiveDate>, Box<dyn std::error::Error>> {
+        let dates = reference_dates(
+            observed_until,
+            reference_dates_count.unwrap_or(u64::try_from(
+                general_conf.default_calibration_reference_dates,
+            )?),
+            step_days.unwrap_or(u64::try_from(general_conf.default_calibration_step_days)?),
+        );
+        if dates.is_empty() {
+            return Err("No reference date to backtest".into());
+        }
+        Ok(dates)
+    }
+
+    /// The stock at the past `date` is made of the batches in stock at that date.
+    /// `random_range_factor` replaces the one of the product when set.
+    async fn backtest_calibration_by_product(
+        &self,
+        product_id: Uuid,
+        date: NaiveDate,
+        observed_until: NaiveDate,
+        random_range_factor: Option<f64>,
+        counter: &mut CalibrationCounter,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let initial_date = date
+            .and_hms_opt(0, 0, 0)
+            .ok_or("Invalid reference date")?
+            .and_utc();
+        let mut sim_data = self
+            .prepare_data_for(product_id, &initial_date.to_rfc3339())
+            .await?;
+        let (_, product_batches) = self
+            .product_batch_repository
+            .find_all_by_product_at(product_id, initial_date)
+            .await?;
+        sim_data.product_batches = product_batches;
+        if let Some(random_range_factor) = random_range_factor {
+            sim_data.random_range_factor = random_range_factor;
+        }
+        let final_date = sim_data.final_date;
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+
+        let predicted = simulation.run_n_times(SIMULATION_RUNS)?;
+        let (_, observed_movements) = self
+            .product_mov_hist_repository
+            .find_daily_by_product_id(
+                product_id,
+                date,
+                (final_date.date_naive() + Days::new(1)).min(observed_until),
+                false,
+            )
+            .await?;
+        let observed = replay_observed(&mut simulation, &observed_movements);
+        counter.add(&predicted.by_day, &observed, observed_until);
+
+        Ok(())
+    }
+
+    async fn save_calibration(
+        &self,
+        product_id: Option<Uuid>,
+        results: &[CalibrationResult],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for (backtest, reliability) in results {
+            let (_, product_calibration_backtest_id) = self
+                .product_calibration_backtest_repository
+                .insert(product_id, backtest)
+                .await?;
+            self.product_calibration_reliability_repository
+                .insert_all(product_calibration_backtest_id, reliability)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Suggests a random range factor for each active product from the spread of its
+    /// history, writing it for review with the calibration backtest scores of the current
+    /// and of the suggested factor instead of changing the product props.
+    pub async fn calibrate_random_range(
+        &self,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, products) = self
+            .product_props_repository
+            .find_all_by_status(true)
+            .await?;
+        let mut failures = Vec::new();
+        for product in products {
+            if let Err(error) = self
+                .calibrate_random_range_by_product(product.id, reference_date)
+                .await
+            {
+                eprintln!(
+                    "Failure to calibrate the random range of product {}: {}",
+                    product.id, error
+                );
+                failures.push(product.id);
+            }
+        }
+        Self::check_failures("calibrate the random range", &failures)
+    }
+
+    pub async fn calibrate_random_range_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, general_conf) = self.general_conf_repository.find_last().await?;
+        let (_, product_props) = self
+            .product_props_repository
+            .find_one_by_product(product_id)
+            .await?;
+        let reference_date = DateTime::parse_from_rfc3339(reference_date)?.date_naive();
+        let maximum_historic_days = u64::try_from(
+            product_props
+                .maximum_historic_days
+                .unwrap_or(general_conf.default_maximum_historic_days),
+        )?;
+        let current_factor = product_props
+            .scenario_random_range_factor
+            .unwrap_or(general_conf.default_scenario_random_range_factor.clone());
+
+        let (_, daily_history) = self
+            .product_mov_hist_repository
+            .find_daily_by_product_id(
+                product_id,
+                reference_date - Days::new(maximum_historic_days),
+                reference_date,
+                product_props
+                    .exclude_promotions_from_history
+                    .unwrap_or(general_conf.default_exclude_promotions_from_history),
+            )
+            .await?;
+        let Some(fit) = RangeFactorFit::fit(&daily_history) else {
+            eprintln!(
+                "Not enough history to calibrate the random range factor of {:?}",
+                product_id
+            );
+            return Ok(());
+        };
+
+        let dates = Self::get_calibration_dates(&general_conf, reference_date, None, None)?;
+        let mut brier_scores: Vec<Option<f64>> = Vec::new();
+        for random_range_factor in [None, Some(fit.factor)] {
+            let mut counter = CalibrationCounter::default();
+            for date in dates.iter() {
+                self.backtest_calibration_by_product(
+                    product_id,
+                    *date,
+                    reference_date,
+                    random_range_factor,
+                    &mut counter,
+                )
+                .await?;
+            }
+            brier_scores.push(counter.brier_score());
+        }
+        let decimal = |value: f64| BigDecimal::from_f64(value).ok_or("Invalid random range score");
+        let suggestion = NewProductRandomRangeSuggestion {
+            product_id,
+            reference_date,
+            current_factor,
+            suggested_factor: decimal(fit.factor)?.round(2),
+            relative_std: decimal(fit.relative_std)?.round(4),
+            observed_days: i32::try_from(fit.observed_days)?,
+            brier_score_before: brier_scores[0].map(decimal).transpose()?,
+            brier_score_after: brier_scores[1].map(decimal).transpose()?,
+        };
+        self.product_random_range_suggestion_repository
+            .insert(&suggestion)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Sweeps the stock maximum quantity of each active product,
+    /// writing the loss curve and the suggested capacity for review.
+    pub async fn optimize_capacity(
+        &self,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (_, products) = self
+            .product_props_repository
+            .find_all_by_status(true)
+            .await?;
+        let mut failures = Vec::new();
+        for product in products {
+            if let Err(error) = self
+                .optimize_capacity_by_product(product.id, reference_date)
+                .await
+            {
+                eprintln!(
+                    "Failure to optimize the capacity of product {}: {}",
+                    product.id, error
+                );
+                failures.push(product.id);
+            }
+        }
+        Self::check_failures("optimize the capacity", &failures)
+    }
+
+    pub async fn optimize_capacity_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sim_data = self.prepare_data_for(product_id, reference_date).await?;
+        let search = CapacitySearch {
+            target_nospace_probability: sim_data.target_nospace_probability,
+            candidates: sim_data.capacity_candidates,
+            runs: SIMULATION_RUNS,
+            max_upper_factor: sim_data.capacity_search_max_factor,
+        };
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+
+        let (recommendation, curve) = search.recommend(&mut simulation)?;
+        let (_, product_capacity_recommendation_id) = self
+            .product_capacity_recommendation_repository
+            .insert(&recommendation)
+            .await?;
+        self.product_capacity_curve_repository
+            .insert_all(product_capacity_recommendation_id, &curve)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Re-runs the simulation of a product for each shelf life of the range,
+    /// by default around its current new_batch_default_expiration_days.
+    pub async fn analyze_shelf_life_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+        range: Option<ShelfLifeRange>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sim_data = self.prepare_data_for(product_id, reference_date).await?;
+        let sensitivity = ShelfLifeSensitivity {
+            range: range
+                .unwrap_or(ShelfLifeRange::around(
+                    sim_data.new_batch_default_expiration_days,
+                ))
+                .validate()?,
+            runs: SIMULATION_RUNS,
+        };
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+
+        let (analysis, points) = sensitivity.analyze(&mut simulation)?;
+        let (_, product_shelf_life_analysis_id) = self
+            .product_shelf_life_analysis_repository
+            .insert(&analysis)
+            .await?;
+        self.product_shelf_life_sensitivity_repository
+            .insert_all(product_shelf_life_analysis_id, &points)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Runs the simulation of a product for each point of the sweep, declared as
+    /// `;` separated axes like `random_range_factor=0.05,0.1;stock_maximum_quantity=100:300:100`.
+    /// The axes are combined in a grid, or sampled randomly within their bounds when `samples` is set.
+    pub async fn sweep_parameters_by_product(
+        &self,
+        product_id: Uuid,
+        reference_date: &str,
+        axes: &str,
+        samples: Option<u64>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sim_data = self.prepare_data_for(product_id, reference_date).await?;
+        let sweep = ParameterSweep {
+            axes: SweepAxis::parse_all(axes)?,
+            design: samples
+                .map(|samples| SweepDesign::RandomSample { samples })
+                .unwrap_or(SweepDesign::Grid),
+            runs: SIMULATION_RUNS,
+        };
+        let mut simulation = Self::build_simulation(product_id, sim_data);
+
+        let (parameter_sweep, points, sensitivities) = sweep.run(&mut simulation)?;
+        let (_, product_parameter_sweep_id) = self
+            .product_parameter_sweep_repository
+            .insert(&parameter_sweep)
+            .await?;
+        self.product_parameter_sweep_point_repository
+            .insert_all(product_parameter_sweep_id, &points)
+            .await?;
+        self.product_parameter_sweep_sensitivity_repository
+            .insert_all(product_parameter_sweep_id, &sensitivities)
+            .await?;
+
+        Ok(())
+    }
+
+    fn build_simulation(product_id: Uuid, sim_data: SimData) -> SimulationControl {
         let SimData {
             initial_date,
             final_date,
             stock_maximum_quantity,
             new_batch_default_expiration_days,
+            new_batch_expiration_hours,
             product_batches,
             historic,
-        } = self.prepare_data_for(product_id, reference_date).await?;
+            day_events_order,
+            expiration_comparison,
+            time_step_hours,
+            intraday_profile,
+            qc_hold_days,
+            qc_rejection_rate,
+            shortage_mode,
+            random_range_factor,
+            loss_cost,
+            replenishment,
+            promotions,
+            entry_trend,
+            withdrawal_trend,
+            daily_forecast,
+            intermittent_demand,
+            history_imputation,
+            demand_regimes,
+            ..
+        } = sim_data;
 
-        let simulation = SimulationControl::new(
+        let mut simulation = SimulationControl::new(
             product_id,
             initial_date,
             final_date,
@@ -65,10 +932,29 @@ impl Orchestrator {
             product_batches,
             historic,
         );
-
-        let days = simulation.run_once();
-
-        Ok(())
+        simulation.sim_param.new_batch_expiration_hours = new_batch_expiration_hours;
+        simulation.sim_param.day_events_order = day_events_order;
+        simulation.sim_param.expiration_comparison = expiration_comparison;
+        simulation.sim_param.time_step_hours = time_step_hours;
+        simulation.sim_param.set_intraday_profile(intraday_profile);
+        simulation.sim_param.qc_hold_days = qc_hold_days;
+        simulation.sim_param.qc_rejection_rate = qc_rejection_rate;
+        simulation.sim_param.shortage_mode = shortage_mode;
+        simulation.sim_param.random_range_factor = random_range_factor;
+        simulation.sim_param.loss_cost = loss_cost;
+        simulation.sim_param.replenishment = replenishment;
+        simulation.sim_param.promotions = promotions;
+        simulation.sim_param.entry_trend_factor = entry_trend.factor;
+        simulation.sim_param.entry_trend_log_std = entry_trend.log_std_error;
+        simulation.sim_param.withdrawal_trend_factor = withdrawal_trend.factor;
+        simulation.sim_param.withdrawal_trend_log_std = withdrawal_trend.log_std_error;
+        simulation.sim_param.set_daily_forecast(daily_forecast);
+        simulation.sim_param.intermittent_demand = intermittent_demand;
+        simulation.sim_param.demand_regimes = demand_regimes;
+        simulation
+            .sim_param
+            .set_history_imputation(history_imputation);
+        simulation
     }
 
     async fn prepare_data_for(
@@ -93,7 +979,173 @@ impl Orchestrator {
 
         let new_batch_default_expiration_days =
             u64::try_from(product_props.new_batch_default_expiration_days)?;
+        let new_batch_expiration_hours = product_props
+            .new_batch_expiration_hours
+            .map(u64::try_from)
+            .transpose()?;
         let stock_maximum_quantity = u64::try_from(product_props.maximum_quantity)?;
+        let qc_hold_days = u64::try_from(product_props.qc_hold_days)?;
+        let qc_rejection_rate = product_props
+            .qc_rejection_rate
+            .to_f64()
+            .ok_or("Invalid qc_rejection_rate")?;
+        let shortage_mode = ShortageMode::from_str(&product_props.shortage_mode)?;
+        let random_range_factor = product_props
+            .scenario_random_range_factor
+            .unwrap_or(general_conf.default_scenario_random_range_factor)
+            .to_f64()
+            .ok_or("Invalid scenario_random_range_factor")?;
+        let loss_cost = LossCost {
+            unit_cost: product_props
+                .unit_cost
+                .to_f64()
+                .ok_or("Invalid unit_cost")?,
+            lost_margin_per_unit: product_props
+                .lost_margin_per_unit
+                .to_f64()
+                .ok_or("Invalid lost_margin_per_unit")?,
+            disposal_cost_per_unit: product_props
+                .disposal_cost_per_unit
+                .to_f64()
+                .ok_or("Invalid disposal_cost_per_unit")?,
+            overcapacity_cost_per_unit: product_props
+                .overcapacity_cost_per_unit
+                .to_f64()
+                .ok_or("Invalid overcapacity_cost_per_unit")?,
+            backorder_cost_per_unit_day: product_props
+                .backorder_cost_per_unit_day
+                .to_f64()
+                .ok_or("Invalid backorder_cost_per_unit_day")?,
+        };
+        let replenishment_lead_time_days =
+            u64::try_from(product_props.replenishment_lead_time_days)?;
+        let replenishment = product_props
+            .reorder_point
+            .map(u64::try_from)
+            .transpose()?
+            .map(|reorder_point| ReplenishmentPolicy {
+                reorder_point,
+                lead_time_days: replenishment_lead_time_days,
+            });
+        let target_service_level = product_props
+            .target_service_level
+            .unwrap_or(general_conf.default_target_service_level)
+            .to_f64()
+            .ok_or("Invalid target_service_level")?;
+        let replenishment_candidates =
+            u64::try_from(general_conf.default_replenishment_candidates)?;
+        let target_nospace_probability = product_props
+            .target_nospace_probability
+            .unwrap_or(general_conf.default_target_nospace_probability)
+            .to_f64()
+            .ok_or("Invalid target_nospace_probability")?;
+        let capacity_candidates = u64::try_from(general_conf.default_capacity_candidates)?;
+        let capacity_search_max_factor =
+            u64::try_from(general_conf.default_capacity_search_max_factor)?;
+        let exclude_promotions_from_history = product_props
+            .exclude_promotions_from_history
+            .unwrap_or(general_conf.default_exclude_promotions_from_history);
+        // The forecast model of the product prevails over the one selected by backtest,
+        // as of the initial date, so that a backtest does not see the history it simulates.
+        let selected_backtest = match product_props.forecast_model {
+            Some(_) => None,
+            None => {
+                self.product_forecast_backtest_repository
+                    .find_latest_selected_by_product(product_id, initial_date.date_naive())
+                    .await?
+                    .1
+            }
+        };
+        let (forecast_model, forecast_params) = match &selected_backtest {
+            Some(backtest) => (
+                ForecastModel::from_str(&backtest.model)?,
+                Self::get_forecast_params(backtest)?,
+            ),
+            None => (
+                ForecastModel::from_str(
+                    product_props
+                        .forecast_model
+                        .as_deref()
+                        .unwrap_or(&general_conf.default_forecast_model),
+                )?,
+                ForecastParams::default(),
+            ),
+        };
+        let maximum_historic_days = u64::try_from(
+            product_props
+                .maximum_historic_days
+                .unwrap_or(general_conf.default_maximum_historic_days),
+        )?;
+        let day_events_order = DayEvent::parse_order(
+            product_props
+                .day_events_order
+                .as_deref()
+                .unwrap_or(&general_conf.default_day_events_order),
+        )?;
+        let expiration_comparison = ExpirationComparison::from_inclusive(
+            product_props
+                .expiration_inclusive
+                .unwrap_or(general_conf.default_expiration_inclusive),
+        );
+        let time_step_hours = SimulationParameters::validate_time_step_hours(u32::try_from(
+            product_props
+                .time_step_hours
+                .unwrap_or(general_conf.default_time_step_hours),
+        )?)?;
+
+        let history_imputation = HistoryImputation::from_str(
+            product_props
+                .history_imputation
+                .as_deref()
+                .unwrap_or(&general_conf.default_history_imputation),
+        )?;
+        let clean_outliers = product_props
+            .clean_outliers
+            .unwrap_or(general_conf.default_clean_outliers);
+        let demand_regimes = product_props
+            .demand_regimes
+            .unwrap_or(general_conf.default_demand_regimes);
+        let daily_history =
+            if forecast_model != ForecastModel::Average || clean_outliers || demand_regimes {
+                self.product_mov_hist_repository
+                    .find_daily_by_product_id(
+                        product_id,
+                        initial_date.date_naive() - Days::new(maximum_historic_days),
+                        initial_date.date_naive(),
+                        exclude_promotions_from_history,
+                    )
+                    .await?
+                    .1
+            } else {
+                Vec::new()
+            };
+        // The outliers found in the daily history also clean the averages.
+        let (daily_history, outlier_log) = if clean_outliers {
+            let cleaning = OutlierCleaning {
+                method: OutlierMethod::from_str(&general_conf.default_outlier_method)?,
+                threshold: general_conf
+                    .default_outlier_threshold
+                    .to_f64()
+                    .ok_or("Invalid default_outlier_threshold")?,
+                action: OutlierAction::from_str(&general_conf.default_outlier_action)?,
+            };
+            let (cleaned, outliers) = cleaning.fit(&daily_history).clean(&daily_history);
+            eprintln!("{} outliers {:?}", outliers.len(), cleaning.action);
+            let outlier_log = OutlierLog {
+                start_date: initial_date.date_naive() - Days::new(maximum_historic_days),
+                end_date: initial_date.date_naive(),
+                outliers: outliers
+                    .iter()
+                    .map(|outlier| outlier.to_new(product_id, cleaning.action))
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+            (cleaned, Some(outlier_log))
+        } else {
+            (daily_history, None)
+        };
+        let outliers = outlier_log
+            .as_ref()
+            .map_or(&[][..], |outlier_log| &outlier_log.outliers);
 
         let (_, historic) = self
             .product_mov_hist_repository
@@ -101,21 +1153,255 @@ impl Orchestrator {
                 product_id,
                 initial_week,
                 final_week,
+                exclude_promotions_from_history,
+                initial_date.date_naive(),
+                outliers,
+            )
+            .await?;
+        // New products borrow the history of their analogs until they have enough of their own.
+        let (_, product_analogs) = self
+            .product_analog_repository
+            .find_all_by_product(product_id)
+            .await?;
+        let (historic, own_weight) = if product_analogs.is_empty() {
+            (historic, 1.0)
+        } else {
+            let (_, own_days) = self
+                .product_mov_hist_repository
+                .count_days_by_product_id(
+                    product_id,
+                    initial_date.date_naive() - Days::new(maximum_historic_days),
+                    initial_date.date_naive(),
+                )
+                .await?;
+            let own_weight = AnalogBlend::own_weight(
+                u64::try_from(own_days)?,
+                u64::try_from(
+                    product_props
+                        .analog_full_history_days
+                        .unwrap_or(general_conf.default_analog_full_history_days),
+                )?,
+            );
+            let mut analogs = Vec::new();
+            for product_analog in product_analogs.iter().filter(|_| own_weight < 1.0) {
+                let (_, analog_historic) = self
+                    .product_mov_hist_repository
+                    .aggregate_by_product_id_and_week_of_year_and_day_of_week(
+                        product_analog.analog_product_id,
+                        initial_week,
+                        final_week,
+                        exclude_promotions_from_history,
+                        initial_date.date_naive(),
+                        &[],
+                    )
+                    .await?;
+                analogs.push(AnalogHistory {
+                    scaling_factor: product_analog
+                        .scaling_factor
+                        .to_f64()
+                        .ok_or("Invalid analog scaling_factor")?,
+                    historic: analog_historic,
+                });
+            }
+            eprintln!("own history weight: {:?}", own_weight);
+            let blend = AnalogBlend {
+                analogs,
+                own_weight,
+            };
+            (blend.blend(product_id, historic), blend.own_weight)
+        };
+        // The daily models would be fitted on the few days of the own history only,
+        // overriding the blend, so the blended averages are simulated until it is complete.
+        let forecast_model = if own_weight < 1.0 && forecast_model != ForecastModel::Average {
+            eprintln!(
+                "Blending the analog history, using the average instead of {:?}",
+                forecast_model
+            );
+            ForecastModel::Average
+        } else {
+            forecast_model
+        };
+
+        let (daily_forecast, intermittent_demand) = if forecast_model != ForecastModel::Average {
+            let intermittent_zero_demand_ratio = general_conf
+                .default_intermittent_zero_demand_ratio
+                .to_f64()
+                .ok_or("Invalid default_intermittent_zero_demand_ratio")?;
+            let forecast_model =
+                forecast_model.resolve(&daily_history, intermittent_zero_demand_ratio);
+            let daily_forecast = forecast_model
+                .forecast_daily(
+                    &daily_history,
+                    initial_date.date_naive(),
+                    final_date.date_naive(),
+                    &forecast_params,
+                )
+                .unwrap_or_default();
+            let intermittent_demand =
+                forecast_model.intermittent_demand(&daily_history, &forecast_params);
+            if forecast_model != ForecastModel::Average
+                && daily_forecast.is_empty()
+                && intermittent_demand.is_none()
+            {
+                eprintln!(
+                    "Not enough daily history for {:?}, using the average",
+                    forecast_model
+                );
+            }
+            (daily_forecast, intermittent_demand)
+        } else {
+            (Vec::new(), None)
+        };
+        let demand_regimes = if demand_regimes {
+            let regimes = DemandRegimes::fit(&daily_history);
+            if regimes.is_none() {
+                eprintln!("Not enough weekly history to fit the demand regimes");
+            }
+            regimes
+        } else {
+            None
+        };
+        eprintln!("demand_regimes: {:?}", demand_regimes);
+        // The forecast models have their own trend.
+        let estimate_trend = daily_forecast.is_empty()
+            && intermittent_demand.is_none()
+            && product_props
+                .estimate_trend
+                .unwrap_or(general_conf.default_estimate_trend);
+        let (entry_trend, withdrawal_trend) = if estimate_trend {
+            let (_, yearly_volumes) = self
+                .product_mov_hist_repository
+                .aggregate_yearly_by_product_id_and_week_of_year(
+                    product_id,
+                    initial_week,
+                    final_week,
+                    exclude_promotions_from_history,
+                    initial_date.date_naive(),
+                    outliers,
+                )
+                .await?;
+            let target_year = initial_date.iso_week().year();
+            let fit = |volume: fn(&ProductMovYearlyVolume) -> &BigDecimal| {
+                let volumes: Vec<(i32, f64)> = yearly_volumes
+                    .iter()
+                    .map(|e| (e.year as i32, volume(e).to_f64().unwrap_or(0.0)))
+                    .collect();
+                TrendFit::fit(&volumes, target_year)
+            };
+            (fit(|e| &e.entry_qty), fit(|e| &e.withdrawal_qty))
+        } else {
+            (TrendFit::default(), TrendFit::default())
+        };
+        eprintln!(
+            "entry_trend: {:?}, withdrawal_trend: {:?}",
+            entry_trend, withdrawal_trend
+        );
+
+        let (_, product_promotions) = self
+            .product_promotion_repository
+            .find_all_by_product_and_dates(
+                product_id,
+                product_props.category.as_deref(),
+                initial_date.date_naive(),
+                final_date.date_naive(),
             )
             .await?;
+        let promotions = product_promotions
+            .into_iter()
+            .map(|promotion| Promotion {
+                start_date: promotion.start_date,
+                end_date: promotion.end_date,
+                withdrawal_multiplier: promotion
+                    .withdrawal_multiplier
+                    .unwrap_or(BigDecimal::from(1)),
+                withdrawal_additive_qty: promotion
+                    .withdrawal_additive_qty
+                    .unwrap_or(BigDecimal::from(0)),
+            })
+            .collect();
 
         let (_, product_batches) = self
             .product_batch_repository
             .find_all_by_product(product_id)
             .await?;
 
+        let intraday_profile = if time_step_hours < HOURS_IN_A_DAY {
+            let (_, profile) = self
+                .product_mov_event_repository
+                .aggregate_hourly_profile_by_product_id(product_id)
+                .await?;
+            profile
+        } else {
+            Vec::new()
+        };
+
         Ok(SimData {
             initial_date,
             final_date,
             stock_maximum_quantity,
             new_batch_default_expiration_days,
+            new_batch_expiration_hours,
             product_batches,
             historic,
+            day_events_order,
+            expiration_comparison,
+            time_step_hours,
+            intraday_profile,
+            qc_hold_days,
+            qc_rejection_rate,
+            shortage_mode,
+            random_range_factor,
+            loss_cost,
+            replenishment,
+            replenishment_lead_time_days,
+            target_service_level,
+            replenishment_candidates,
+            target_nospace_probability,
+            capacity_candidates,
+            capacity_search_max_factor,
+            promotions,
+            entry_trend,
+            withdrawal_trend,
+            daily_forecast,
+            intermittent_demand,
+            history_imputation,
+            demand_regimes,
+            outlier_log,
+        })
+    }
+
+    /// Smoothing factors stored with the backtest of the selected model.
+    fn get_forecast_params(
+        backtest: &ProductForecastBacktest,
+    ) -> Result<ForecastParams, Box<dyn std::error::Error>> {
+        let value = |column: &Option<BigDecimal>| {
+            column
+                .as_ref()
+                .map(|value| value.to_f64().ok_or("Invalid forecast backtest param"))
+                .transpose()
+        };
+        let holt_winters = match (
+            value(&backtest.alpha)?,
+            value(&backtest.beta)?,
+            value(&backtest.gamma_weekly)?,
+            value(&backtest.gamma_yearly)?,
+        ) {
+            (Some(alpha), Some(beta), Some(gamma_weekly), Some(gamma_yearly)) => {
+                Some(HoltWintersParams {
+                    alpha,
+                    beta,
+                    gamma_weekly,
+                    gamma_yearly,
+                })
+            }
+            _ => None,
+        };
+        Ok(ForecastParams {
+            croston_alpha: match holt_winters {
+                Some(_) => None,
+                None => value(&backtest.alpha)?,
+            },
+            holt_winters,
         })
     }
 
@@ -200,4 +1486,73 @@ mod tests {
         assert_eq!(initial_week, 1);
         assert_eq!(final_week, 13);
     }
+    #[tokio::test]
+    async fn report_by_product() {
+        let orchestrator = Orchestrator::new().await.unwrap();
+        let product_id = Uuid::from_str("59d81b0a-a9ca-472c-bdfe-3081317157a3").unwrap();
+        let report = orchestrator.report_by_product(product_id).await.unwrap();
+        assert_eq!(report.summary.id, 2);
+        assert_eq!(report.summary.product_id, product_id);
+        assert_eq!(report.by_day.len(), 1);
+        assert!(report.data_quality.is_none());
+
+        let product_id = Uuid::from_str("d0bd335e-fc46-408d-90fb-000000000000").unwrap();
+        assert!(orchestrator.report_by_product(product_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rank_by_expected_loss_cost() {
+        let orchestrator = Orchestrator::new().await.unwrap();
+        let ranking = orchestrator.rank_by_expected_loss_cost().await.unwrap();
+        assert_eq!(ranking.len(), 3);
+        assert!(ranking
+            .windows(2)
+            .all(|pair| pair[0].expected_loss_cost >= pair[1].expected_loss_cost));
+        let product_id = Uuid::from_str("b010b78b-3236-4ddb-b68e-d833eb75d8be").unwrap();
+        let latest = ranking
+            .iter()
+            .find(|summary| summary.product_id == product_id)
+            .unwrap();
+        assert_eq!(latest.id, 4);
+    }
+
+    #[tokio::test]
+    async fn run_what_if_by_product_without_label() {
+        let orchestrator = Orchestrator::new().await.unwrap();
+        let product_id = Uuid::from_str("d0bd335e-fc46-408d-90fb-209ccc521fa1").unwrap();
+        let written_tables = [
+            "product_simulation_summary",
+            "product_simulation_summary_by_day",
+            "product_simulation_kpi",
+            "product_simulation_first_loss",
+            "product_simulation_stress_result",
+            "product_simulation_data_quality",
+            "product_mov_outlier",
+        ];
+        let count_rows = || async {
+            let mut counts = Vec::new();
+            for table in written_tables {
+                let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+                    .fetch_one(&orchestrator.db)
+                    .await
+                    .unwrap();
+                counts.push(count);
+            }
+            counts
+        };
+        let counts_before = count_rows().await;
+
+        let summary = orchestrator
+            .run_what_if_by_product(
+                product_id,
+                "2022-01-01T00:00:00Z",
+                WhatIfOverrides::from_str("random_range_factor=0.1;simulation_runs=2").unwrap(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(summary.summary.product_id, product_id);
+        assert_eq!(summary.summary.what_if_label, None);
+        assert_eq!(count_rows().await, counts_before);
+    }
 }